@@ -1,13 +1,14 @@
 use crate::errors::{HError, HResult};
 use ndarray::{ArcArray, Dimension, StrideShape};
-use num_complex::ComplexFloat;
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct HArray<T: ComplexFloat, D: Dimension>(pub ArcArray<T, D>);
+/// An n-dimensional array. Most operations require float or complex elements, but integer elements can be used to
+/// hold decoded samples without converting them.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct HArray<T, D: Dimension>(pub ArcArray<T, D>);
 
 impl<T, D> HArray<T, D>
 where
-    T: ComplexFloat,
+    T: Clone,
     D: Dimension,
 {
     pub fn new_from_shape_vec<Sh>(shape: Sh, v: Vec<T>) -> HResult<HArray<T, D>>
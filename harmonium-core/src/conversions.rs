@@ -4,6 +4,7 @@ use crate::{
 };
 use ndarray::{Dimension, IxDyn};
 use num_complex::ComplexFloat;
+use num_traits::{Float, NumCast, PrimInt, Signed};
 
 pub trait IntoDynamic<T, D>
 where
@@ -44,3 +45,103 @@ where
         })?))
     }
 }
+
+pub trait IntoFloat<F, D>
+where
+    D: Dimension,
+{
+    /// Converts integer samples to float samples normalized in the range of \[-1.0, 1.0), dividing them by
+    /// `2^(bits - 1)`. The float type must represent every value of the integer type, so the conversion is exact and
+    /// gives the same values as decoding to a float type.
+    fn into_float(self) -> HArray<F, D>;
+}
+
+impl<I, F, D> IntoFloat<F, D> for HArray<I, D>
+where
+    I: PrimInt + Signed,
+    F: Float + From<I>,
+    D: Dimension,
+{
+    fn into_float(self) -> HArray<F, D> {
+        let scale = -<F as From<I>>::from(I::min_value());
+        HArray(
+            self.0
+                .mapv(|x| <F as From<I>>::from(x) / scale)
+                .into_shared(),
+        )
+    }
+}
+
+pub trait IntoInt<I, D>
+where
+    D: Dimension,
+{
+    /// Converts normalized float samples back to integer samples, multiplying them by `2^(bits - 1)`. This is the
+    /// inverse of `IntoFloat::into_float`. Returns an error if a sample would not be converted exactly.
+    fn into_int(self) -> HResult<HArray<I, D>>;
+}
+
+impl<F, I, D> IntoInt<I, D> for HArray<F, D>
+where
+    F: Float + From<I>,
+    I: PrimInt + Signed,
+    D: Dimension,
+{
+    fn into_int(self) -> HResult<HArray<I, D>> {
+        let scale = -<F as From<I>>::from(I::min_value());
+        let mut v = Vec::with_capacity(self.len());
+        for x in self.0.iter() {
+            let y = *x * scale;
+            match <I as NumCast>::from(y) {
+                Some(y_int) if <F as From<I>>::from(y_int) == y => v.push(y_int),
+                _ => {
+                    return Err(HError::OutOfSpecError(
+                        "a sample cannot be converted to an integer without loss".to_string(),
+                    ))
+                }
+            }
+        }
+        HArray::new_from_shape_vec(self.0.raw_dim(), v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Ix2;
+
+    #[test]
+    fn into_float_int_test() {
+        let v = vec![i16::MIN, -1, 0, 1, i16::MAX, 12345];
+        let harray = HArray::<i16, Ix2>::new_from_shape_vec((2, 3), v).unwrap();
+
+        let lhs: HArray<f32, Ix2> = harray.clone().into_float();
+        let rhs = HArray::new_from_shape_vec(
+            (2, 3),
+            vec![
+                -1.,
+                -1. / 32768.,
+                0.,
+                1. / 32768.,
+                32767. / 32768.,
+                12345. / 32768.,
+            ],
+        )
+        .unwrap();
+        assert_eq!(lhs, rhs);
+        let lhs: HArray<i16, Ix2> = lhs.into_int().unwrap();
+        assert_eq!(lhs, harray);
+
+        let v = vec![i32::MIN, -1, 0, i32::MAX];
+        let harray = HArray::<i32, Ix2>::new_from_shape_vec((1, 4), v).unwrap();
+        let harray_f64: HArray<f64, Ix2> = harray.clone().into_float();
+        let lhs: HArray<i32, Ix2> = harray_f64.into_int().unwrap();
+        assert_eq!(lhs, harray);
+
+        // Values between two integers or out of range are rejected.
+        let harray = HArray::<f32, Ix2>::new_from_shape_vec((1, 2), vec![0., 0.1]).unwrap();
+        assert!(IntoInt::<i16, Ix2>::into_int(harray).is_err());
+        let harray = HArray::<f32, Ix2>::new_from_shape_vec((1, 2), vec![0., 1.]).unwrap();
+        assert!(IntoInt::<i16, Ix2>::into_int(harray).is_err());
+    }
+}
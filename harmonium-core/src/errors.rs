@@ -3,20 +3,27 @@ use std::{error, fmt, io};
 #[allow(clippy::enum_variant_names)]
 pub enum HError {
     /// IO error.
-    IoError(String),
+    Io(io::Error),
     /// The stream contained malformed data and could not be decoded or demuxed.
     DecodeError(String),
     /// The stream could not be seeked.
     SeekError(String),
+    /// A format, codec or feature that is not supported.
+    Unsupported { what: String },
     /// A default or user-defined limit was reached while decoding or demuxing the stream. Limits
     /// are used to prevent denial-of-service attacks from malicious streams.
     LimitError(String),
     /// Resampling errors.
-    ResampleError(String),
+    ResampleError(Box<dyn error::Error + Send + Sync>),
+    /// The shape of an array is not the expected one.
+    ShapeMismatch {
+        expected: Vec<usize>,
+        got: Vec<usize>,
+    },
     /// Arguments out of specification.
     OutOfSpecError(String),
     /// Errors related to playing audio.
-    PlayError(String),
+    PlayError(Box<dyn error::Error + Send + Sync>),
     /// Other types of error.
     OtherError(String),
 }
@@ -24,13 +31,19 @@ pub enum HError {
 impl fmt::Display for HError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            HError::IoError(msg) => write!(f, "IoError: {}", msg),
+            HError::Io(err) => write!(f, "IoError: {}", err),
             HError::DecodeError(msg) => write!(f, "DecodeError: {}", msg),
             HError::SeekError(msg) => write!(f, "SeekError: {}", msg),
+            HError::Unsupported { what } => write!(f, "Unsupported: {}", what),
             HError::LimitError(msg) => write!(f, "LimitError: {}", msg),
-            HError::ResampleError(msg) => write!(f, "ResampleError: {}", msg),
+            HError::ResampleError(err) => write!(f, "ResampleError: {}", err),
+            HError::ShapeMismatch { expected, got } => write!(
+                f,
+                "ShapeMismatch: expected shape {:?}, got {:?}",
+                expected, got
+            ),
             HError::OutOfSpecError(msg) => write!(f, "OutOfSpecError: {}", msg),
-            HError::PlayError(msg) => write!(f, "PlayError: {}", msg),
+            HError::PlayError(err) => write!(f, "PlayError: {}", err),
             HError::OtherError(msg) => write!(f, "OtherError: {}", msg),
         }
     }
@@ -42,21 +55,31 @@ impl fmt::Debug for HError {
     }
 }
 
-impl error::Error for HError {}
+impl error::Error for HError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            HError::Io(err) => Some(err),
+            HError::ResampleError(err) | HError::PlayError(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 impl From<io::Error> for HError {
     fn from(err: io::Error) -> Self {
-        HError::IoError(err.to_string())
+        HError::Io(err)
     }
 }
 
 impl From<symphonia::core::errors::Error> for HError {
     fn from(err: symphonia::core::errors::Error) -> Self {
         match err {
-            symphonia::core::errors::Error::IoError(_) => HError::IoError(err.to_string()),
+            symphonia::core::errors::Error::IoError(err) => HError::Io(err),
             symphonia::core::errors::Error::DecodeError(_) => HError::DecodeError(err.to_string()),
             symphonia::core::errors::Error::SeekError(_) => HError::SeekError(err.to_string()),
-            symphonia::core::errors::Error::Unsupported(_) => HError::IoError(err.to_string()),
+            symphonia::core::errors::Error::Unsupported(what) => HError::Unsupported {
+                what: what.to_string(),
+            },
             symphonia::core::errors::Error::LimitError(_) => HError::LimitError(err.to_string()),
             symphonia::core::errors::Error::ResetRequired => HError::OtherError(err.to_string()),
         }
@@ -65,50 +88,108 @@ impl From<symphonia::core::errors::Error> for HError {
 
 impl From<rubato::ResampleError> for HError {
     fn from(err: rubato::ResampleError) -> Self {
-        HError::ResampleError(err.to_string())
+        HError::ResampleError(Box::new(err))
     }
 }
 
 impl From<rubato::ResamplerConstructionError> for HError {
     fn from(err: rubato::ResamplerConstructionError) -> Self {
-        HError::ResampleError(err.to_string())
+        HError::ResampleError(Box::new(err))
     }
 }
 
 impl From<rodio::StreamError> for HError {
     fn from(err: rodio::StreamError) -> Self {
-        HError::PlayError(err.to_string())
+        HError::PlayError(Box::new(err))
     }
 }
 
 impl From<rodio::PlayError> for HError {
     fn from(err: rodio::PlayError) -> Self {
-        HError::PlayError(err.to_string())
+        HError::PlayError(Box::new(err))
     }
 }
 
 impl From<rodio::DevicesError> for HError {
     fn from(err: rodio::DevicesError) -> Self {
-        HError::PlayError(err.to_string())
+        HError::PlayError(Box::new(err))
     }
 }
 
 impl From<rodio::source::SeekError> for HError {
     fn from(err: rodio::source::SeekError) -> Self {
-        HError::PlayError(err.to_string())
+        // The error is not `Sync`, so only its message is kept.
+        HError::PlayError(err.to_string().into())
     }
 }
 
 impl From<rodio::cpal::DeviceNameError> for HError {
     fn from(err: rodio::cpal::DeviceNameError) -> Self {
-        HError::PlayError(err.to_string())
+        HError::PlayError(Box::new(err))
     }
 }
 
 impl From<rodio::cpal::SupportedStreamConfigsError> for HError {
     fn from(err: rodio::cpal::SupportedStreamConfigsError) -> Self {
-        HError::PlayError(err.to_string())
+        HError::PlayError(Box::new(err))
+    }
+}
+
+impl From<rodio::cpal::DefaultStreamConfigError> for HError {
+    fn from(err: rodio::cpal::DefaultStreamConfigError) -> Self {
+        HError::PlayError(Box::new(err))
+    }
+}
+
+impl From<rodio::cpal::BuildStreamError> for HError {
+    fn from(err: rodio::cpal::BuildStreamError) -> Self {
+        HError::PlayError(Box::new(err))
+    }
+}
+
+impl From<rodio::cpal::StreamError> for HError {
+    fn from(err: rodio::cpal::StreamError) -> Self {
+        HError::PlayError(Box::new(err))
+    }
+}
+
+impl From<rodio::cpal::PlayStreamError> for HError {
+    fn from(err: rodio::cpal::PlayStreamError) -> Self {
+        HError::PlayError(Box::new(err))
     }
 }
 
 pub type HResult<T> = Result<T, HError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn errors_test() {
+        let err = HError::from(io::Error::new(io::ErrorKind::NotFound, "file not found"));
+        assert!(matches!(err, HError::Io(_)));
+        assert_eq!(err.to_string(), "IoError: file not found");
+        assert_eq!(err.source().unwrap().to_string(), "file not found");
+
+        let err = HError::from(symphonia::core::errors::Error::Unsupported("codec"));
+        assert!(matches!(err, HError::Unsupported { ref what } if what == "codec"));
+        assert!(err.source().is_none());
+
+        let err = HError::from(symphonia::core::errors::Error::IoError(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "end of stream",
+        )));
+        assert!(matches!(err, HError::Io(ref e) if e.kind() == io::ErrorKind::UnexpectedEof));
+
+        let err = HError::ShapeMismatch {
+            expected: vec![2, 10],
+            got: vec![1, 10],
+        };
+        assert_eq!(
+            err.to_string(),
+            "ShapeMismatch: expected shape [2, 10], got [1, 10]"
+        );
+    }
+}
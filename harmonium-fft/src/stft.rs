@@ -568,10 +568,10 @@ fn validate_fft_length(fft_length: usize, lower: usize, upper: usize) -> HResult
 fn validate_window_length<T>(window: Option<&[T]>, window_length: usize) -> HResult<()> {
     if let Some(slice) = window {
         if slice.len() != window_length {
-            let got_length = slice.len();
-            Err(HError::OutOfSpecError(format!(
-                "Expected window length == {window_length}. Got {got_length}."
-            )))
+            Err(HError::ShapeMismatch {
+                expected: vec![window_length],
+                got: vec![slice.len()],
+            })
         } else {
             Ok(())
         }
@@ -0,0 +1,124 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use harmonium_core::errors::{HError, HResult};
+
+use crate::{
+    decode::{HBext, HBroadcastMetadata, HIxml},
+    markers::{is_wav, read_wav_chunks, zstr},
+};
+
+/// Reads the `bext` and `iXML` chunks of a Broadcast Wave file. Returns `None` if the stream is not a WAVE file or if
+/// neither chunk is present.
+///
+/// The reader is left at the start of the stream.
+pub(crate) fn read_broadcast_metadata<R: Read + Seek>(
+    reader: &mut R,
+) -> HResult<Option<HBroadcastMetadata>> {
+    let mut magic = [0; 12];
+    reader.seek(SeekFrom::Start(0))?;
+    let is_wav = reader.read_exact(&mut magic).is_ok() && is_wav(&magic);
+
+    let mut bext = None;
+    let mut ixml = None;
+    if is_wav {
+        for (id, body) in read_wav_chunks(reader, &[b"bext", b"iXML"])? {
+            match &id {
+                b"bext" => bext = Some(parse_bext(&body)?),
+                b"iXML" => ixml = Some(parse_ixml(&body)),
+                _ => (),
+            }
+        }
+    }
+
+    reader.seek(SeekFrom::Start(0))?;
+
+    if bext.is_none() && ixml.is_none() {
+        Ok(None)
+    } else {
+        Ok(Some(HBroadcastMetadata { bext, ixml }))
+    }
+}
+
+/// Parses a `bext` chunk, as specified by EBU Tech 3285.
+fn parse_bext(body: &[u8]) -> HResult<HBext> {
+    // Size of the fixed fields, up to the coding history.
+    const BEXT_LEN: usize = 602;
+
+    // Some writers omit the reserved bytes of version 0, so only the fields up to the version are required.
+    if body.len() < 348 {
+        return Err(HError::DecodeError("bext: chunk too short".into()));
+    }
+
+    let time_reference_low = u32::from_le_bytes(body[338..342].try_into().unwrap()) as u64;
+    let time_reference_high = u32::from_le_bytes(body[342..346].try_into().unwrap()) as u64;
+
+    Ok(HBext {
+        description: text(&body[0..256]),
+        originator: text(&body[256..288]),
+        originator_reference: text(&body[288..320]),
+        origination_date: text(&body[320..330]),
+        origination_time: text(&body[330..338]),
+        time_reference: (time_reference_high << 32) | time_reference_low,
+        version: u16::from_le_bytes(body[346..348].try_into().unwrap()),
+        coding_history: body.get(BEXT_LEN..).map(text).unwrap_or_default(),
+    })
+}
+
+/// Parses the fields of an `iXML` chunk that identify a take. The whole document is kept in `HIxml::xml`.
+fn parse_ixml(body: &[u8]) -> HIxml {
+    let xml = text(body);
+
+    HIxml {
+        project: xml_element(&xml, "PROJECT"),
+        scene: xml_element(&xml, "SCENE"),
+        take: xml_element(&xml, "TAKE"),
+        tape: xml_element(&xml, "TAPE"),
+        note: xml_element(&xml, "NOTE"),
+        circled: xml_element(&xml, "CIRCLED").map(|x| x.eq_ignore_ascii_case("true")),
+        xml,
+    }
+}
+
+/// Returns the unescaped text of the first element with the given name. Only elements without attributes or
+/// children are supported, which is the case of the iXML fields.
+fn xml_element(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    let value = xml[start..end]
+        .trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+
+    (!value.is_empty()).then_some(value)
+}
+
+/// Reads a NUL-padded text field. Some writers pad with spaces instead.
+fn text(bytes: &[u8]) -> String {
+    zstr(bytes).trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ixml_test() {
+        let xml = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\r\n<BWFXML><IXML_VERSION>1.61</IXML_VERSION>\
+            <PROJECT>Tom &amp; Jerry</PROJECT><SCENE>12A</SCENE><TAKE>3</TAKE><TAPE></TAPE>\
+            <CIRCLED>TRUE</CIRCLED></BWFXML>\0\0";
+
+        let ixml = parse_ixml(xml);
+        assert_eq!(ixml.project.as_deref(), Some("Tom & Jerry"));
+        assert_eq!(ixml.scene.as_deref(), Some("12A"));
+        assert_eq!(ixml.take.as_deref(), Some("3"));
+        assert_eq!(ixml.tape, None);
+        assert_eq!(ixml.note, None);
+        assert_eq!(ixml.circled, Some(true));
+        assert!(ixml.xml.ends_with("</BWFXML>"));
+    }
+}
@@ -1,22 +1,43 @@
-use std::{fs::File, path::Path};
+use std::{
+    fs::File,
+    io::{Cursor, Read, Seek, SeekFrom},
+    num::NonZero,
+    ops::Range,
+    path::Path,
+};
 
 use harmonium_core::{
     array::HArray,
     errors::{HError, HResult},
 };
-use ndarray::{ArcArray2, Ix2};
-use num_traits::{Float, FloatConst};
+use ndarray::{s, ArcArray2, Ix2};
+use num_traits::{Float, FloatConst, PrimInt, Signed, Zero};
 use symphonia::core::{
     audio::SampleBuffer,
-    codecs::{Decoder, DecoderOptions},
+    checksum::Md5,
+    codecs::{
+        CodecParameters, CodecType, Decoder, DecoderOptions, VerificationCheck,
+        CODEC_TYPE_PCM_F32BE, CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_F64BE, CODEC_TYPE_PCM_F64LE,
+        CODEC_TYPE_PCM_S16BE, CODEC_TYPE_PCM_S16LE, CODEC_TYPE_PCM_S24BE, CODEC_TYPE_PCM_S24LE,
+        CODEC_TYPE_PCM_S32BE, CODEC_TYPE_PCM_S32LE, CODEC_TYPE_PCM_S8, CODEC_TYPE_PCM_U16BE,
+        CODEC_TYPE_PCM_U16LE, CODEC_TYPE_PCM_U24BE, CODEC_TYPE_PCM_U24LE, CODEC_TYPE_PCM_U32BE,
+        CODEC_TYPE_PCM_U32LE, CODEC_TYPE_PCM_U8,
+    },
     conv::ConvertibleSample,
-    formats::{FormatOptions, FormatReader},
-    io::MediaSourceStream,
-    meta::{MetadataOptions, MetadataRevision},
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo, Track},
+    io::{MediaSource, MediaSourceStream, Monitor},
+    meta::{MetadataLog, MetadataOptions, MetadataRevision, Value},
     probe::Hint,
+    sample::SampleFormat,
+    units::{Time, TimeBase},
 };
 
-#[derive(Debug, PartialEq)]
+use crate::{
+    bwf, markers,
+    raw::{HRawFormat, RawReader},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum HVerifyDecode {
     Passed,
     Failed,
@@ -40,24 +61,375 @@ pub struct HVisualMetadata(pub Option<Vec<HSingleVisualMetadata>>);
 pub struct HSingleVisualMetadata {
     pub usage: String,
     pub media_type: String,
-    pub dimensions: String,
-    pub bpp: String,
+    /// Width and height in pixels, if known.
+    pub dimensions: Option<(u32, u32)>,
+    /// Bits per pixel, if known.
+    pub bpp: Option<u32>,
     pub color_mode: String,
-    pub size: String,
+    /// The encoded image, in the format given by `media_type`.
+    pub data: Vec<u8>,
     pub tags_vec: Option<Vec<HTag>>,
 }
 
+/// How a looped region is played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HLoopType {
+    Forward,
+    /// Forward, then backward.
+    Alternating,
+    Backward,
+}
+
+/// A chapter, a cue point or a loop.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HMarker {
+    pub label: String,
+    /// First frame of the marker.
+    pub start: u64,
+    /// The frame after the last one of the marker. `None` for cue points and for a last chapter that lasts until the
+    /// end of the stream.
+    pub end: Option<u64>,
+    /// `Some` for loops.
+    pub loop_type: Option<HLoopType>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct HMarkers(pub Option<Vec<HMarker>>);
+
+/// The `bext` chunk of a Broadcast Wave file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HBext {
+    pub description: String,
+    /// Name of the device or organization that created the recording.
+    pub originator: String,
+    pub originator_reference: String,
+    /// Date of the recording, as `yyyy-mm-dd`.
+    pub origination_date: String,
+    /// Time of the recording, as `hh:mm:ss`.
+    pub origination_time: String,
+    /// Position of the first frame of the file, in frames since midnight. Recordings of different devices can be
+    /// aligned on it.
+    pub time_reference: u64,
+    /// Version of the `bext` chunk.
+    pub version: u16,
+    /// History of the coding processes applied to the audio, one per line.
+    pub coding_history: String,
+}
+
+/// The `iXML` chunk of a Broadcast Wave file. Missing or empty fields are `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HIxml {
+    pub project: Option<String>,
+    pub scene: Option<String>,
+    pub take: Option<String>,
+    pub tape: Option<String>,
+    pub note: Option<String>,
+    /// Whether the take was marked as good.
+    pub circled: Option<bool>,
+    /// The whole iXML document.
+    pub xml: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HBroadcastMetadata {
+    pub bext: Option<HBext>,
+    pub ixml: Option<HIxml>,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum HMetadata {
     All((HTextMetadata, HVisualMetadata)),
     Text(HTextMetadata),
     Visual(HVisualMetadata),
+    Markers(HMarkers),
+    Broadcast(Box<HBroadcastMetadata>),
 }
 
 pub enum HMetadataType {
     All,
     Text,
     Visual,
+    Markers,
+    Broadcast,
+}
+
+/// A position or a length in an audio stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HTime {
+    /// Time in seconds.
+    Seconds(f64),
+    /// Number of frames.
+    Frames(u64),
+}
+
+impl HTime {
+    /// Converts to a number of frames given the sampling rate. Seconds are rounded to the nearest frame.
+    pub fn to_frames(&self, sr: u32) -> HResult<u64> {
+        match *self {
+            HTime::Frames(frames) => Ok(frames),
+            HTime::Seconds(seconds) if seconds >= 0. && seconds.is_finite() => {
+                Ok((seconds * sr as f64).round() as u64)
+            }
+            HTime::Seconds(_) => Err(HError::OutOfSpecError(
+                "the time must be a non-negative finite number of seconds".into(),
+            )),
+        }
+    }
+}
+
+/// A track in an audio input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HTrack {
+    /// The track id, to be used to select the track when decoding.
+    pub id: u32,
+    /// Short name of the codec.
+    pub codec: String,
+    pub sr: Option<u32>,
+    pub nchannels: Option<usize>,
+    pub nframes: Option<u64>,
+    pub language: Option<String>,
+}
+
+/// Audio parameters of a track.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HAudioInfo {
+    /// Sampling rate in Hz.
+    pub sr: u32,
+    pub nframes: u64,
+    pub nchannels: usize,
+    /// Name of each channel, in the order they are stored. E.g. `["FRONT_LEFT", "FRONT_RIGHT"]`.
+    pub channel_layout: Vec<String>,
+    /// Short name of the codec.
+    pub codec: String,
+    pub bits_per_sample: Option<u32>,
+    /// Sample format of the encoded samples, if known. One of `u8`, `u16`, `u24`, `u32`, `s8`, `s16`, `s24`, `s32`,
+    /// `f32` or `f64`.
+    pub sample_format: Option<String>,
+    /// Average bitrate in bits per second, computed from the size of the input. Includes the container overhead.
+    pub bitrate: Option<u64>,
+    /// Duration in seconds.
+    pub duration: f64,
+}
+
+/// A packet that failed to decode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HPacketError {
+    /// Index of the packet in the track, starting at 0.
+    pub packet: u64,
+    /// The first frame of the packet.
+    pub frame: u64,
+    /// The error returned by the decoder or the format reader.
+    pub error: String,
+}
+
+/// The outcome of `verify_file_report`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HVerifyReport {
+    /// `Failed` if a packet failed to decode or the checksum does not match. `NotSupported` if the file has no
+    /// checksum and all the packets were decoded.
+    pub result: HVerifyDecode,
+    /// Number of packets of the track that were read.
+    pub npackets: u64,
+    /// Number of frames decoded without errors.
+    pub nframes: u64,
+    /// The packets that failed to decode. Unless decoding is lenient, it stops at the first error.
+    pub errors: Vec<HPacketError>,
+    /// The checksum algorithm of the track, if any. One of `md5`, `crc8`, `crc16`, `crc32` or `other`.
+    pub checksum_type: Option<String>,
+    /// The checksum stored in the file, as a hexadecimal string.
+    pub expected_checksum: Option<String>,
+    /// The checksum of the decoded samples, as a hexadecimal string. Only computed for MD5, which is used by FLAC.
+    pub computed_checksum: Option<String>,
+}
+
+/// Encoder delay and padding trimmed by a gapless decode, in frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HGapless {
+    /// Number of frames trimmed from the start.
+    pub delay: u32,
+    /// Number of frames trimmed from the end.
+    pub padding: u32,
+}
+
+/// How `decode_tolerant` fills the frames of corrupt packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HConcealment {
+    /// Fill with silence.
+    #[default]
+    Zeros,
+    /// Repeat the frames of the last packet decoded without errors. Falls back to silence at the start of the audio.
+    Repeat,
+}
+
+/// The frame ranges concealed by `decode_tolerant`, in increasing order.
+pub type HConcealed = Vec<Range<u64>>;
+
+/// Options for `decode_tolerant`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HTolerantOptions {
+    pub concealment: HConcealment,
+    /// Maximum number of corrupt packets before giving up with an error. If `None`, there is no limit.
+    pub max_errors: Option<usize>,
+}
+
+/// An audio input to be decoded. It can be a file, an in-memory buffer or any `Read + Seek` source.
+/// An optional file extension or MIME type can be given as a hint to help guess the format. The format is always
+/// detected from the content when the hint is missing or wrong.
+pub struct HInput {
+    source: Box<dyn MediaSource>,
+    hint: Hint,
+    gapless: bool,
+    raw_format: Option<HRawFormat>,
+}
+
+impl HInput {
+    /// Creates an input from a file. The file extension, if any, is used as a hint.
+    pub fn from_file(fpath: &str) -> HResult<Self> {
+        let fpath = Path::new(fpath);
+        // Create a media source. Note that the MediaSource trait is automatically implemented for File, among other types.
+        let file = File::open(fpath)?;
+        // Create a hint to help the format registry guess what format reader is appropriate.
+        let mut hint = Hint::new();
+        if let Some(ext) = fpath.extension().and_then(|x| x.to_str()) {
+            hint.with_extension(ext);
+        }
+        Ok(HInput {
+            source: Box::new(file),
+            hint,
+            gapless: false,
+            raw_format: None,
+        })
+    }
+
+    /// Creates an input from an in-memory buffer. A `&[u8]` is copied into a new buffer.
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        HInput {
+            source: Box::new(Cursor::new(bytes.into())),
+            hint: Hint::new(),
+            gapless: false,
+            raw_format: None,
+        }
+    }
+
+    /// Creates an input from any `Read + Seek` source.
+    pub fn from_reader<R>(reader: R) -> HResult<Self>
+    where
+        R: Read + Seek + Send + Sync + 'static,
+    {
+        Ok(HInput {
+            source: Box::new(ReadSeekSource::try_new(reader)?),
+            hint: Hint::new(),
+            gapless: false,
+            raw_format: None,
+        })
+    }
+
+    /// Creates an input from a symphonia `MediaSource`.
+    pub fn from_media_source(source: Box<dyn MediaSource>) -> Self {
+        HInput {
+            source,
+            hint: Hint::new(),
+            gapless: false,
+            raw_format: None,
+        }
+    }
+
+    /// Uses a file extension, such as `"wav"`, as a hint to help guess the format.
+    pub fn with_extension(mut self, extension: &str) -> Self {
+        self.hint.with_extension(extension);
+        self
+    }
+
+    /// Uses a MIME type, such as `"audio/flac"`, as a hint to help guess the format.
+    pub fn with_mime_type(mut self, mime_type: &str) -> Self {
+        self.hint.mime_type(mime_type);
+        self
+    }
+
+    /// Enables or disables gapless decoding. When enabled, the encoder delay and padding are trimmed from the decoded
+    /// audio, as given by the LAME/Xing header of MP3 files or by the iTunSMPB tag. Disabled by default.
+    pub fn with_gapless(mut self, gapless: bool) -> Self {
+        self.gapless = gapless;
+        self
+    }
+
+    /// Reads the input as headerless PCM with the given layout, instead of probing it for a format.
+    pub fn with_raw_format(mut self, raw_format: HRawFormat) -> Self {
+        self.raw_format = Some(raw_format);
+        self
+    }
+
+    /// Probes the input for a format.
+    pub(crate) fn probe(self) -> HResult<Probed> {
+        // Create the media source stream using the boxed media source from above.
+        let mss = MediaSourceStream::new(self.source, Default::default());
+        if let Some(raw_format) = self.raw_format {
+            return Ok(Probed {
+                format: Box::new(RawReader::try_new(mss, raw_format)?),
+                metadata: None,
+            });
+        }
+        // Use the default options when reading, apart from gapless.
+        let format_opts = FormatOptions {
+            enable_gapless: self.gapless,
+            ..Default::default()
+        };
+        let metadata_opts: MetadataOptions = Default::default();
+        // Probe the media source stream for a format.
+        let probed = symphonia::default::get_probe().format(
+            &self.hint,
+            mss,
+            &format_opts,
+            &metadata_opts,
+        )?;
+        Ok(Probed {
+            format: probed.format,
+            metadata: probed.metadata.into_inner(),
+        })
+    }
+}
+
+/// The format reader of an input and the metadata found while probing it, outside of the container.
+pub(crate) struct Probed {
+    pub(crate) format: Box<dyn FormatReader>,
+    pub(crate) metadata: Option<MetadataLog>,
+}
+
+/// A `MediaSource` over any `Read + Seek` source.
+struct ReadSeekSource<R> {
+    inner: R,
+    byte_len: u64,
+}
+
+impl<R: Read + Seek> ReadSeekSource<R> {
+    fn try_new(mut inner: R) -> HResult<Self> {
+        let pos = inner.stream_position()?;
+        let byte_len = inner.seek(SeekFrom::End(0))?;
+        inner.seek(SeekFrom::Start(pos))?;
+        Ok(ReadSeekSource { inner, byte_len })
+    }
+}
+
+impl<R: Read> Read for ReadSeekSource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for ReadSeekSource<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl<R: Read + Seek + Send + Sync> MediaSource for ReadSeekSource<R> {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        Some(self.byte_len)
+    }
 }
 
 /// Decode an audio file as an HArray.
@@ -66,42 +438,162 @@ pub enum HMetadataType {
 /// # Arguments
 ///
 /// * `fpath` - The input file.
+/// * `track_id` - The track to decode. If `None`, the default track is used.
 ///
 /// # Examples
 ///
 /// ```
 /// //let test_file = "../testfiles/gs-16b-2c-44100hz.wav";
-/// //load(test_file, Some(1_f64), Some(1_f64))
+/// //decode::<f32>(test_file, None)
 /// ```
-pub fn decode<T>(fpath: &str) -> HResult<(HArray<T, Ix2>, u32)>
+pub fn decode<T>(fpath: &str, track_id: Option<u32>) -> HResult<(HArray<T, Ix2>, u32)>
 where
     T: Float + FloatConst + ConvertibleSample,
 {
-    let fpath = Path::new(fpath);
-    let ext = Path::extension(fpath)
-        .ok_or_else(|| HError::IoError("couldn't extract the file extension".into()))?
-        .to_str()
-        .ok_or_else(|| HError::IoError("cannot convert from &OsStr to &str".into()))?;
-    // Create a media source. Note that the MediaSource trait is automatically implemented for File, among other types.
-    let file = Box::new(File::open(fpath)?);
-    // Create the media source stream using the boxed media source from above.
-    let mss = MediaSourceStream::new(file, Default::default());
-    // Create a hint to help the format registry guess what format reader is appropriate.
-    let mut hint = Hint::new();
-    hint.with_extension(ext);
-    // Use the default options when reading and decoding.
-    let format_opts: FormatOptions = Default::default();
-    let metadata_opts: MetadataOptions = Default::default();
+    decode_from_input(HInput::from_file(fpath)?, track_id)
+}
+
+/// Decode an audio input as an HArray.
+/// The decoded samples are normalized to fit in the range of \[-1.0, 1.0\].
+///
+/// # Arguments
+///
+/// * `input` - The input file, buffer or reader.
+/// * `track_id` - The track to decode. If `None`, the default track is used.
+///
+/// # Examples
+///
+/// ```
+/// //let bytes = std::fs::read("../testfiles/gs-16b-2c-44100hz.wav").unwrap();
+/// //decode_from_input::<f32>(HInput::from_bytes(bytes).with_extension("wav"), None)
+/// ```
+pub fn decode_from_input<T>(input: HInput, track_id: Option<u32>) -> HResult<(HArray<T, Ix2>, u32)>
+where
+    T: Float + FloatConst + ConvertibleSample,
+{
+    let (harray, sr, _) = decode_input(input, track_id, None)?;
+    Ok((harray, sr))
+}
+
+/// Decode an audio file as an HArray of integer samples, without converting them to float.
+/// The samples are scaled to the full range of `T`, as symphonia does: 8-bit samples are shifted left by 8 bits in an
+/// `i16` and 24-bit samples by 8 bits in an `i32`. `IntoFloat::into_float` converts the output to the same values as
+/// `decode` returns.
+/// Returns an error if the samples cannot be represented exactly, which is the case of lossy codecs, float samples and
+/// bit depths larger than the bits of `T`.
+///
+/// # Arguments
+///
+/// * `fpath` - The input file.
+/// * `track_id` - The track to decode. If `None`, the default track is used.
+///
+/// # Examples
+///
+/// ```
+/// //let test_file = "../testfiles/gs-16b-2c-44100hz.flac";
+/// //decode_int::<i16>(test_file, None)
+/// ```
+pub fn decode_int<T>(fpath: &str, track_id: Option<u32>) -> HResult<(HArray<T, Ix2>, u32)>
+where
+    T: PrimInt + Signed + ConvertibleSample,
+{
+    decode_int_from_input(HInput::from_file(fpath)?, track_id)
+}
+
+/// Decode an audio input as an HArray of integer samples.
+/// See `decode_int` for the details.
+pub fn decode_int_from_input<T>(
+    input: HInput,
+    track_id: Option<u32>,
+) -> HResult<(HArray<T, Ix2>, u32)>
+where
+    T: PrimInt + Signed + ConvertibleSample,
+{
+    let bits = T::zero().count_zeros();
+    let (harray, sr, _) = decode_input(input, track_id, Some(bits))?;
+    Ok((harray, sr))
+}
+
+/// Decode an audio file as an HArray, trimming the encoder delay and padding.
+/// Lossy encoders add silent frames at the start and at the end of the audio. The amount is read from the LAME/Xing
+/// header of MP3 files or from the iTunSMPB tag, and is returned together with the trimmed audio. Nothing is trimmed
+/// when the file has no such information.
+/// The decoded samples are normalized to fit in the range of \[-1.0, 1.0\].
+///
+/// # Arguments
+///
+/// * `fpath` - The input file.
+/// * `track_id` - The track to decode. If `None`, the default track is used.
+///
+/// # Examples
+///
+/// ```
+/// //let test_file = "../testfiles/gs-16b-2c-44100hz.mp3";
+/// //decode_gapless::<f32>(test_file, None)
+/// ```
+pub fn decode_gapless<T>(
+    fpath: &str,
+    track_id: Option<u32>,
+) -> HResult<(HArray<T, Ix2>, u32, HGapless)>
+where
+    T: Float + FloatConst + ConvertibleSample,
+{
+    decode_gapless_from_input(HInput::from_file(fpath)?, track_id)
+}
+
+/// Decode an audio input as an HArray, trimming the encoder delay and padding.
+/// See `decode_gapless` for the details.
+pub fn decode_gapless_from_input<T>(
+    input: HInput,
+    track_id: Option<u32>,
+) -> HResult<(HArray<T, Ix2>, u32, HGapless)>
+where
+    T: Float + FloatConst + ConvertibleSample,
+{
+    decode_input(input.with_gapless(true), track_id, None)
+}
+
+/// Decodes the whole track. If `max_bits` is given, returns an error unless the samples are integers of at most
+/// `max_bits` bits, so that they are not altered when decoding to an integer type.
+fn decode_input<T>(
+    input: HInput,
+    track_id: Option<u32>,
+    max_bits: Option<u32>,
+) -> HResult<(HArray<T, Ix2>, u32, HGapless)>
+where
+    T: ConvertibleSample + Zero,
+{
+    let gapless = input.gapless;
+    // Use the default options when decoding.
     let decoder_opts: DecoderOptions = Default::default();
-    // Probe the media source stream for a format.
-    let probed =
-        symphonia::default::get_probe().format(&hint, mss, &format_opts, &metadata_opts)?;
+    // Probe the input for a format.
+    let mut probed = input.probe()?;
+    // The iTunSMPB tag is not handled by the format readers, so it is looked up here.
+    let itunsmpb = if gapless {
+        probed
+            .format
+            .metadata()
+            .current()
+            .and_then(itunsmpb_from_metadata_rev)
+            .or_else(|| {
+                probed
+                    .metadata
+                    .as_mut()
+                    .map(|m| m.metadata())
+                    .as_ref()
+                    .and_then(|m| m.current())
+                    .and_then(itunsmpb_from_metadata_rev)
+            })
+    } else {
+        None
+    };
     // Get the format reader yielded by the probe operation.
     let mut reader = probed.format;
-    // Get the default track.
-    let track = reader
-        .default_track()
-        .ok_or_else(|| HError::DecodeError("no tracks were detected".into()))?;
+    // Get the selected track.
+    let track = select_track(reader.as_ref(), track_id)?;
+    if let Some(max_bits) = max_bits {
+        check_lossless_int(&track.codec_params, max_bits)?;
+    }
     // Create a decoder for the track.
     let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &decoder_opts)?;
     let codec_params = decoder.codec_params();
@@ -117,14 +609,494 @@ where
         .sample_rate
         .ok_or_else(|| HError::DecodeError("cannot retrieve the sample rate".into()))?;
 
-    let track_id = track.id;
+    let track_id = track.id;
+    // The format reader already trimmed the delay and padding if it knows about them.
+    let mut trimmed = HGapless {
+        delay: track.codec_params.delay.unwrap_or(0),
+        padding: track.codec_params.padding.unwrap_or(0),
+    };
+    let mut sample_buf: Option<SampleBuffer<T>> = None;
+
+    let mut ndarray = ArcArray2::zeros((channels, usize::try_from(nframes).unwrap()));
+    let mut ndarray_t = ndarray.view_mut().reversed_axes();
+    let mut ndarray_iter = ndarray_t.iter_mut();
+    let ndarray_iter_by_ref = ndarray_iter.by_ref();
+
+    loop {
+        // Get the next packet from the format reader.
+        let packet = match reader.next_packet() {
+            Ok(packet_ok) => packet_ok,
+            Err(symphonia::core::errors::Error::IoError(ref packet_err))
+                if packet_err.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                break;
+            }
+            Err(packet_err) => Err(packet_err)?,
+        };
+
+        // If the packet does not belong to the selected track, skip it.
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        // Decode the packet into audio samples.
+        match decoder.decode(&packet) {
+            Ok(audio_buf) => {
+                // The decoded audio samples may now be accessed via the audio buffer if per-channel
+                // slices of samples in their native decoded format is desired. Use-cases where
+                // the samples need to be accessed in an interleaved order or converted into
+                // another sample format, or a byte buffer is required, are covered by copying the
+                // audio buffer into a sample buffer or raw sample buffer, respectively. In the
+                // example below, we will copy the audio buffer into a sample buffer in an
+                // interleaved order.
+
+                // If this is the *first* decoded packet, create a sample buffer matching the
+                // decoded audio buffer format.
+                if sample_buf.is_none() {
+                    // Get the audio buffer specification.
+                    let spec = *audio_buf.spec();
+                    // Get the capacity of the decoded buffer.
+                    let cap = audio_buf.capacity() as u64;
+                    // Create the sample buffer.
+                    sample_buf = Some(SampleBuffer::<T>::new(cap, spec));
+                }
+
+                if let Some(buf) = &mut sample_buf {
+                    // Copy the decoded audio buffer into the sample buffer in an interleaved format.
+                    buf.copy_interleaved_ref(audio_buf);
+
+                    // The samples may now be access via the `samples()` function.
+                    let samples = buf.samples();
+
+                    for (sample, elem) in samples.iter().zip(&mut *ndarray_iter_by_ref) {
+                        *elem = *sample;
+                    }
+                }
+            }
+            Err(symphonia::core::errors::Error::DecodeError(err_str)) => {
+                Err(symphonia::core::errors::Error::DecodeError(err_str))?
+            }
+            Err(_) => break,
+        }
+    }
+
+    if let Some((delay, padding, original_nframes)) = itunsmpb {
+        if trimmed == HGapless::default() {
+            let start = u64::from(delay).min(nframes);
+            let end = if original_nframes > 0 {
+                (start + original_nframes).min(nframes)
+            } else {
+                nframes.saturating_sub(u64::from(padding)).max(start)
+            };
+            ndarray = ndarray.slice_move(s![.., start as usize..end as usize]);
+            trimmed = HGapless {
+                delay: start as u32,
+                padding: (nframes - end) as u32,
+            };
+        }
+    }
+
+    let harray = HArray(ndarray);
+
+    Ok((harray, sr, trimmed))
+}
+
+/// Parses the iTunSMPB tag, if any, into the encoder delay, the padding and the original number of frames.
+fn itunsmpb_from_metadata_rev(metadata_rev: &MetadataRevision) -> Option<(u32, u32, u64)> {
+    metadata_rev
+        .tags()
+        .iter()
+        .find(|tag| tag.key == "iTunSMPB" || tag.key.ends_with(":iTunSMPB"))
+        .and_then(|tag| match &tag.value {
+            Value::String(s) => parse_itunsmpb(s),
+            _ => None,
+        })
+}
+
+/// The iTunSMPB value is a list of hexadecimal fields. The second, third and fourth fields are the encoder delay, the
+/// padding and the original number of frames.
+fn parse_itunsmpb(value: &str) -> Option<(u32, u32, u64)> {
+    let mut fields = value.split_whitespace().skip(1);
+    let delay = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let padding = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let original_nframes = u64::from_str_radix(fields.next()?, 16).ok()?;
+    Some((delay, padding, original_nframes))
+}
+
+/// Decode an audio file as an HArray, concealing the packets that fail to decode instead of returning an error.
+/// The frames of a corrupt packet are filled according to `HConcealment`, and so are the frames of packets skipped by
+/// the format reader, which are found from the gaps in the packet timestamps. If the format reader or the decoder
+/// cannot go on, decoding stops and the remaining frames are concealed, as are the frames missing from a truncated
+/// input. Errors found before decoding starts are returned as in `decode`.
+/// The output has the same length as the output of `decode` and is the same outside the concealed frames.
+///
+/// # Arguments
+///
+/// * `fpath` - The input file.
+/// * `track_id` - The track to decode. If `None`, the default track is used.
+/// * `options` - The `HTolerantOptions`.
+///
+/// # Returns
+///
+/// The decoded audio, its sampling rate and the concealed frame ranges, in increasing order.
+///
+/// # Examples
+///
+/// ```
+/// //let test_file = "../testfiles/gs-16b-2c-44100hz.flac";
+/// //decode_tolerant::<f32>(test_file, None, HTolerantOptions::default())
+/// ```
+pub fn decode_tolerant<T>(
+    fpath: &str,
+    track_id: Option<u32>,
+    options: HTolerantOptions,
+) -> HResult<(HArray<T, Ix2>, u32, HConcealed)>
+where
+    T: ConvertibleSample + Zero,
+{
+    decode_tolerant_from_input(HInput::from_file(fpath)?, track_id, options)
+}
+
+/// Decode an audio input as an HArray, concealing the packets that fail to decode.
+/// See `decode_tolerant` for the details.
+pub fn decode_tolerant_from_input<T>(
+    input: HInput,
+    track_id: Option<u32>,
+    options: HTolerantOptions,
+) -> HResult<(HArray<T, Ix2>, u32, HConcealed)>
+where
+    T: ConvertibleSample + Zero,
+{
+    let probed = input.probe()?;
+    let mut reader = probed.format;
+    let track = select_track(reader.as_ref(), track_id)?;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &Default::default())?;
+    let track_id = track.id;
+    let codec_params = decoder.codec_params().clone();
+    let channels = codec_params
+        .channels
+        .ok_or_else(|| HError::DecodeError("cannot retrieve the number of channels".into()))?
+        .count();
+    let nframes = codec_params
+        .n_frames
+        .ok_or_else(|| HError::DecodeError("cannot retrieve the number of frames".into()))?;
+    let sr = codec_params
+        .sample_rate
+        .ok_or_else(|| HError::DecodeError("cannot retrieve the sample rate".into()))?;
+
+    let mut concealer = Concealer::new(channels, nframes, options);
+    let mut sample_buf: Option<SampleBuffer<T>> = None;
+    // The timestamp after the last packet decoded without errors, where the next packet should start.
+    let mut end_ts = codec_params.start_ts;
+
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet_ok) => packet_ok,
+            Err(symphonia::core::errors::Error::IoError(ref packet_err))
+                if packet_err.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                break;
+            }
+            // The format reader cannot go on after an error, so the rest of the audio is concealed.
+            Err(_) => {
+                concealer.add_error()?;
+                break;
+            }
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        match decoder.decode(&packet) {
+            Ok(audio_buf) => {
+                // Conceal the packets that failed to decode or were skipped by the format reader.
+                if packet.ts() > end_ts {
+                    if !concealer.pending {
+                        concealer.add_error()?;
+                    }
+                    let start = ts_to_frames(end_ts, codec_params.time_base, sr);
+                    let end = ts_to_frames(packet.ts(), codec_params.time_base, sr);
+                    concealer.conceal(end - start);
+                } else {
+                    // The corrupt packet had no duration, so there is nothing to conceal.
+                    concealer.pending = false;
+                }
+                end_ts = packet.ts() + packet.dur();
+
+                let buf = sample_buf.get_or_insert_with(|| {
+                    SampleBuffer::<T>::new(audio_buf.capacity() as u64, *audio_buf.spec())
+                });
+                buf.copy_interleaved_ref(audio_buf);
+                concealer.push(buf.samples());
+            }
+            Err(
+                symphonia::core::errors::Error::DecodeError(_)
+                | symphonia::core::errors::Error::IoError(_),
+            ) => {
+                concealer.add_error()?;
+            }
+            // The decoder cannot go on after other errors, so the rest of the audio is concealed.
+            Err(_) => {
+                concealer.add_error()?;
+                break;
+            }
+        }
+    }
+
+    // Conceal the corrupt packets at the end of the audio, or the frames missing from a truncated input.
+    let nconcealed = nframes.saturating_sub(concealer.nframes());
+    concealer.conceal(nconcealed);
+
+    let (harray, concealed) = concealer.finish(nframes)?;
+
+    Ok((harray, sr, concealed))
+}
+
+/// Collects the decoded samples of `decode_tolerant` and fills the gaps left by corrupt packets.
+struct Concealer<T> {
+    channels: usize,
+    options: HTolerantOptions,
+    /// Interleaved samples.
+    samples: Vec<T>,
+    /// Start and length of the last packet decoded without errors, in samples.
+    last: Range<usize>,
+    concealed: HConcealed,
+    nerrors: usize,
+    /// Whether the last error has not been concealed yet.
+    pending: bool,
+}
+
+impl<T> Concealer<T>
+where
+    T: Copy + Zero,
+{
+    fn new(channels: usize, nframes: u64, options: HTolerantOptions) -> Self {
+        // Reserve the expected size, up to a limit in case the number of frames is wrong.
+        let capacity = usize::try_from(nframes)
+            .unwrap_or(usize::MAX)
+            .min(1 << 24)
+            .saturating_mul(channels);
+        Concealer {
+            channels,
+            options,
+            samples: Vec::with_capacity(capacity),
+            last: 0..0,
+            concealed: Vec::new(),
+            nerrors: 0,
+            pending: false,
+        }
+    }
+
+    fn nframes(&self) -> u64 {
+        (self.samples.len() / self.channels) as u64
+    }
+
+    fn add_error(&mut self) -> HResult<()> {
+        self.nerrors += 1;
+        self.pending = true;
+        match self.options.max_errors {
+            Some(max_errors) if self.nerrors > max_errors => Err(HError::DecodeError(format!(
+                "more than {} packets failed to decode",
+                max_errors
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn push(&mut self, samples: &[T]) {
+        let start = self.samples.len();
+        self.samples.extend_from_slice(samples);
+        self.last = start..self.samples.len();
+    }
+
+    /// Appends `nframes` concealed frames.
+    fn conceal(&mut self, nframes: u64) {
+        self.pending = false;
+        if nframes == 0 {
+            return;
+        }
+
+        let start = self.nframes();
+        let nsamples = usize::try_from(nframes).unwrap() * self.channels;
+        match self.options.concealment {
+            HConcealment::Repeat if !self.last.is_empty() => {
+                for i in 0..nsamples {
+                    let sample = self.samples[self.last.start + i % self.last.len()];
+                    self.samples.push(sample);
+                }
+            }
+            _ => self
+                .samples
+                .resize(self.samples.len() + nsamples, T::zero()),
+        }
+
+        // Merge with the previous range if they are contiguous.
+        match self.concealed.last_mut() {
+            Some(range) if range.end == start => range.end += nframes,
+            _ => self.concealed.push(start..start + nframes),
+        }
+    }
+
+    /// Returns the samples with `nframes` frames, like `decode`, and the concealed ranges.
+    fn finish(mut self, nframes: u64) -> HResult<(HArray<T, Ix2>, HConcealed)> {
+        let nframes = usize::try_from(nframes).unwrap();
+        let mut samples = std::mem::take(&mut self.samples);
+        samples.resize(nframes * self.channels, T::zero());
+        self.concealed.retain_mut(|range| {
+            range.end = range.end.min(nframes as u64);
+            !range.is_empty()
+        });
+        let harray = HArray(
+            ArcArray2::from_shape_vec((nframes, self.channels), samples)
+                .map_err(|_| HError::DecodeError("cannot build the decoded array".into()))?
+                .reversed_axes()
+                .as_standard_layout()
+                .into_shared(),
+        );
+        Ok((harray, self.concealed))
+    }
+}
+
+/// Decode part of an audio file as an HArray, starting at `offset` and lasting `duration`.
+/// The reader seeks to the offset whenever the format supports it, so only the packets needed are decoded. The result
+/// is trimmed to the exact frames requested and is the same as slicing the output of `decode`.
+/// The decoded samples are normalized to fit in the range of \[-1.0, 1.0\].
+///
+/// # Arguments
+///
+/// * `fpath` - The input file.
+/// * `offset` - Start of the range, in seconds or frames.
+/// * `duration` - Length of the range, in seconds or frames. If `None`, decodes until the end of the file.
+/// * `track_id` - The track to decode. If `None`, the default track is used.
+///
+/// # Examples
+///
+/// ```
+/// //let test_file = "../testfiles/gs-16b-2c-44100hz.wav";
+/// //decode_range::<f32>(test_file, HTime::Seconds(1.), Some(HTime::Seconds(3.)), None)
+/// ```
+pub fn decode_range<T>(
+    fpath: &str,
+    offset: HTime,
+    duration: Option<HTime>,
+    track_id: Option<u32>,
+) -> HResult<(HArray<T, Ix2>, u32)>
+where
+    T: Float + FloatConst + ConvertibleSample,
+{
+    decode_range_from_input(HInput::from_file(fpath)?, offset, duration, track_id)
+}
+
+/// Decode part of an audio input as an HArray, starting at `offset` and lasting `duration`.
+/// See `decode_range` for the details.
+pub fn decode_range_from_input<T>(
+    input: HInput,
+    offset: HTime,
+    duration: Option<HTime>,
+    track_id: Option<u32>,
+) -> HResult<(HArray<T, Ix2>, u32)>
+where
+    T: Float + FloatConst + ConvertibleSample,
+{
+    let (samples_vec, channels, sr) = decode_range_with(
+        input,
+        offset,
+        duration,
+        track_id,
+        |channels, sr, nframes| {
+            let capacity = nframes
+                .map_or(usize::MAX, |x| usize::try_from(x).unwrap_or(usize::MAX))
+                .min(1 << 24)
+                * channels;
+            Ok((Vec::with_capacity(capacity), channels, sr))
+        },
+        |(samples_vec, _, _), samples| {
+            samples_vec.extend_from_slice(samples);
+            Ok(())
+        },
+    )?;
+
+    let nframes = samples_vec.len() / channels;
+    let mut ndarray = ArcArray2::zeros((channels, nframes));
+    for (sample, elem) in samples_vec
+        .iter()
+        .zip(ndarray.view_mut().reversed_axes().iter_mut())
+    {
+        *elem = *sample;
+    }
+
+    let harray = HArray(ndarray);
+
+    Ok((harray, sr))
+}
+
+/// Decodes the frames of a range and passes them to `process`, interleaved, one packet at a time.
+/// `init` is called once the track is known, with the number of channels, the sampling rate and the number of frames
+/// of the range, if known, and creates the state given to `process`.
+pub(crate) fn decode_range_with<T, S>(
+    input: HInput,
+    offset: HTime,
+    duration: Option<HTime>,
+    track_id: Option<u32>,
+    init: impl FnOnce(usize, u32, Option<u64>) -> HResult<S>,
+    mut process: impl FnMut(&mut S, &[T]) -> HResult<()>,
+) -> HResult<S>
+where
+    T: Float + FloatConst + ConvertibleSample,
+{
+    // Use the default options when decoding.
+    let decoder_opts: DecoderOptions = Default::default();
+    // Probe the input for a format.
+    let probed = input.probe()?;
+    // Get the format reader yielded by the probe operation.
+    let mut reader = probed.format;
+    // Get the selected track.
+    let track = select_track(reader.as_ref(), track_id)?;
+    let track_id = track.id;
+    let time_base = track.codec_params.time_base;
+    // Create a decoder for the track.
+    let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &decoder_opts)?;
+    let codec_params = decoder.codec_params();
+    let channels = codec_params
+        .channels
+        .ok_or_else(|| HError::DecodeError("cannot retrieve the number of channels".into()))?
+        .count();
+    let sr = codec_params
+        .sample_rate
+        .ok_or_else(|| HError::DecodeError("cannot retrieve the sample rate".into()))?;
+
+    let start = offset.to_frames(sr)?;
+    let end = match duration {
+        Some(duration) => start.saturating_add(duration.to_frames(sr)?),
+        None => u64::MAX,
+    };
+    let end = match codec_params.n_frames {
+        Some(nframes) => end.min(nframes),
+        None => end,
+    };
+
+    // Some codecs need the previous packets to fully reconstruct the current one, so the reader seeks to a point a
+    // bit earlier than the offset and the extra frames are discarded.
+    let preroll = 2 * codec_params.max_frames_per_packet.unwrap_or(2048);
+    let seek_frame = start.saturating_sub(preroll);
+
+    if seek_frame > 0 {
+        let seek_to = SeekTo::Time {
+            time: Time::from(seek_frame as f64 / sr as f64),
+            track_id: Some(track_id),
+        };
+        // If the format doesn't support seeking, the packets before the offset are decoded and discarded.
+        if reader.seek(SeekMode::Accurate, seek_to).is_ok() {
+            decoder.reset();
+        }
+    }
+
+    let nframes = (end != u64::MAX).then(|| end.saturating_sub(start));
+    let mut state = init(channels, sr, nframes)?;
     let mut sample_buf: Option<SampleBuffer<T>> = None;
 
-    let mut ndarray = ArcArray2::zeros((channels, usize::try_from(nframes).unwrap()));
-    let mut ndarray_t = ndarray.view_mut().reversed_axes();
-    let mut ndarray_iter = ndarray_t.iter_mut();
-    let ndarray_iter_by_ref = ndarray_iter.by_ref();
-
     loop {
         // Get the next packet from the format reader.
         let packet = match reader.next_packet() {
@@ -142,17 +1114,14 @@ where
             continue;
         }
 
+        let packet_start = ts_to_frames(packet.ts(), time_base, sr);
+        if packet_start >= end {
+            break;
+        }
+
         // Decode the packet into audio samples.
         match decoder.decode(&packet) {
             Ok(audio_buf) => {
-                // The decoded audio samples may now be accessed via the audio buffer if per-channel
-                // slices of samples in their native decoded format is desired. Use-cases where
-                // the samples need to be accessed in an interleaved order or converted into
-                // another sample format, or a byte buffer is required, are covered by copying the
-                // audio buffer into a sample buffer or raw sample buffer, respectively. In the
-                // example below, we will copy the audio buffer into a sample buffer in an
-                // interleaved order.
-
                 // If this is the *first* decoded packet, create a sample buffer matching the
                 // decoded audio buffer format.
                 if sample_buf.is_none() {
@@ -167,12 +1136,17 @@ where
                 if let Some(buf) = &mut sample_buf {
                     // Copy the decoded audio buffer into the sample buffer in an interleaved format.
                     buf.copy_interleaved_ref(audio_buf);
-
-                    // The samples may now be access via the `samples()` function.
                     let samples = buf.samples();
 
-                    for (sample, elem) in samples.iter().zip(&mut *ndarray_iter_by_ref) {
-                        *elem = *sample;
+                    // Keep only the frames inside the range.
+                    let packet_frames = (samples.len() / channels) as u64;
+                    let first = start.saturating_sub(packet_start).min(packet_frames);
+                    let last = end.saturating_sub(packet_start).min(packet_frames);
+                    if first < last {
+                        process(
+                            &mut state,
+                            &samples[first as usize * channels..last as usize * channels],
+                        )?;
                     }
                 }
             }
@@ -183,9 +1157,7 @@ where
         }
     }
 
-    let harray = HArray(ndarray);
-
-    Ok((harray, sr))
+    Ok(state)
 }
 
 /// stream an audio file as an iterator.
@@ -195,42 +1167,52 @@ where
 ///
 /// `fpath` - The input file.
 /// `frames` - Number of frames to decode per iteration.
+/// `track_id` - The track to decode. If `None`, the default track is used.
 ///
 /// # Examples
 ///
 /// ```
 /// //let test_file = "../testfiles/gs-16b-2c-44100hz.wav";
-/// //stream(test_file, 1000)
+/// //stream(test_file, 1000, None)
+/// ```
+pub fn stream<T>(fpath: &str, frames: usize, track_id: Option<u32>) -> HResult<DecoderStream<T>>
+where
+    T: Float + FloatConst + ConvertibleSample,
+{
+    stream_from_input(HInput::from_file(fpath)?, frames, track_id)
+}
+
+/// stream an audio input as an iterator.
+/// The samples are normalized to fit in the range of \[-1.0, 1.0\].
+///
+/// # Arguments
+///
+/// `input` - The input file, buffer or reader.
+/// `frames` - Number of frames to decode per iteration.
+/// `track_id` - The track to decode. If `None`, the default track is used.
+///
+/// # Examples
+///
+/// ```
+/// //let bytes = std::fs::read("../testfiles/gs-16b-2c-44100hz.wav").unwrap();
+/// //stream_from_input::<f32>(HInput::from_bytes(bytes), 1000, None)
 /// ```
-pub fn stream<T>(fpath: &str, frames: usize) -> HResult<DecoderStream<T>>
+pub fn stream_from_input<T>(
+    input: HInput,
+    frames: usize,
+    track_id: Option<u32>,
+) -> HResult<DecoderStream<T>>
 where
     T: Float + FloatConst + ConvertibleSample,
 {
-    let fpath = Path::new(fpath);
-    let ext = Path::extension(fpath)
-        .ok_or_else(|| HError::IoError("couldn't extract the file extension".into()))?
-        .to_str()
-        .ok_or_else(|| HError::IoError("cannot convert from &OsStr to &str".into()))?;
-    // Create a media source. Note that the MediaSource trait is automatically implemented for File, among other types.
-    let file = Box::new(File::open(fpath)?);
-    // Create the media source stream using the boxed media source from above.
-    let mss = MediaSourceStream::new(file, Default::default());
-    // Create a hint to help the format registry guess what format reader is appropriate.
-    let mut hint = Hint::new();
-    hint.with_extension(ext);
-    // Use the default options when reading and decoding.
-    let format_opts: FormatOptions = Default::default();
-    let metadata_opts: MetadataOptions = Default::default();
+    // Use the default options when decoding.
     let decoder_opts: DecoderOptions = Default::default();
-    // Probe the media source stream for a format.
-    let probed =
-        symphonia::default::get_probe().format(&hint, mss, &format_opts, &metadata_opts)?;
+    // Probe the input for a format.
+    let probed = input.probe()?;
     // Get the format reader yielded by the probe operation.
     let reader = probed.format;
-    // Get the default track.
-    let track = reader
-        .default_track()
-        .ok_or_else(|| HError::DecodeError("no tracks were detected".into()))?;
+    // Get the selected track.
+    let track = select_track(reader.as_ref(), track_id)?;
     // Create a decoder for the track.
     let decoder = symphonia::default::get_codecs().make(&track.codec_params, &decoder_opts)?;
     let codec_params = decoder.codec_params();
@@ -242,24 +1224,71 @@ where
     let nframes = codec_params
         .n_frames
         .ok_or_else(|| HError::DecodeError("cannot retrieve the number of frames".into()))?;
+    let sr = codec_params
+        .sample_rate
+        .ok_or_else(|| HError::DecodeError("cannot retrieve the sample rate".into()))?;
+    let preroll = 2 * codec_params.max_frames_per_packet.unwrap_or(2048);
     let track_id = track.id;
-    let sample_buf: Option<SampleBuffer<T>> = None;
-    let last_idx = 0;
+    let time_base = track.codec_params.time_base;
 
     let stream_struct = DecoderStream::new(
-        reader,
-        decoder,
-        track_id,
-        sample_buf,
-        channels,
-        nframes.try_into().unwrap(),
-        frames,
-        last_idx,
+        reader, decoder, track_id, time_base, sr, preroll, channels, nframes, frames,
     );
 
     Ok(stream_struct)
 }
 
+/// stream overlapping frames of an audio file as an iterator.
+/// Each iteration returns `frame_length` frames and the next one starts `hop_length` frames later. The overlap
+/// between iterations is kept internally, so the frames are the same as the sliding windows used by `Stft`.
+/// The samples are normalized to fit in the range of \[-1.0, 1.0\].
+///
+/// # Arguments
+///
+/// `fpath` - The input file.
+/// `frame_length` - Number of frames returned per iteration.
+/// `hop_length` - Number of frames between the start of two consecutive iterations.
+/// `track_id` - The track to decode. If `None`, the default track is used.
+///
+/// # Examples
+///
+/// ```
+/// //let test_file = "../testfiles/gs-16b-2c-44100hz.wav";
+/// //stream_frames::<f32>(test_file, NonZero::new(2048).unwrap(), NonZero::new(512).unwrap(), None)
+/// ```
+pub fn stream_frames<T>(
+    fpath: &str,
+    frame_length: NonZero<usize>,
+    hop_length: NonZero<usize>,
+    track_id: Option<u32>,
+) -> HResult<DecoderStream<T>>
+where
+    T: Float + FloatConst + ConvertibleSample,
+{
+    stream_frames_from_input(
+        HInput::from_file(fpath)?,
+        frame_length,
+        hop_length,
+        track_id,
+    )
+}
+
+/// stream overlapping frames of an audio input as an iterator.
+/// See `stream_frames` for the details.
+pub fn stream_frames_from_input<T>(
+    input: HInput,
+    frame_length: NonZero<usize>,
+    hop_length: NonZero<usize>,
+    track_id: Option<u32>,
+) -> HResult<DecoderStream<T>>
+where
+    T: Float + FloatConst + ConvertibleSample,
+{
+    let mut stream_struct = stream_from_input(input, frame_length.get(), track_id)?;
+    stream_struct.hop_length = hop_length.get();
+    Ok(stream_struct)
+}
+
 pub struct DecoderStream<T>
 where
     T: Float + FloatConst + ConvertibleSample,
@@ -270,16 +1299,49 @@ where
     decoder: Box<dyn Decoder>,
     // Track id.
     track_id: u32,
+    // Time base of the track, used to convert the packets' timestamps into frames.
+    time_base: Option<TimeBase>,
+    // Sampling rate.
+    sr: u32,
+    // Number of frames to decode before the target frame when seeking.
+    preroll: u64,
     // Buffer to allocate the samples read in a packet.
     sample_buf: Option<SampleBuffer<T>>,
+    // Interleaved samples decoded but not yet returned.
+    buffer: Vec<T>,
     // Number of channels.
     channels: usize,
-    // Number of frames.
-    nframes: usize,
+    // Total number of frames.
+    nframes: u64,
     // Number of frames to be returned in each iteration.
     frames: usize,
-    // Last index read in the packet. Needed to keep reading from the following sample when in a new iteration but in the same packet.
-    last_idx: usize,
+    // Number of frames between the start of two consecutive iterations.
+    hop_length: usize,
+    // Number of samples still to be discarded when the hop is longer than the frames returned.
+    skip: usize,
+    // Index of the frame after the last frame returned.
+    emitted_end: u64,
+    // What to do with the last frames, when there are fewer than `frames` left.
+    tail: HStreamTail,
+    // Index of the first frame in `buffer`.
+    position: u64,
+    // Frame to discard the decoded samples up to, after seeking.
+    skip_to: Option<u64>,
+    // Whether the end of the stream was reached.
+    eof: bool,
+}
+
+/// What a `DecoderStream` does with the last frames of a stream, when there are fewer than the number of frames
+/// requested per iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HStreamTail {
+    /// The last frames are not returned.
+    #[default]
+    Discard,
+    /// The last frames are returned in a shorter `HArray`.
+    Partial,
+    /// The last frames are returned padded with zeros.
+    ZeroPad,
 }
 
 impl<T> DecoderStream<T>
@@ -291,83 +1353,107 @@ where
         reader: Box<dyn FormatReader>,
         decoder: Box<dyn Decoder>,
         track_id: u32,
-        sample_buf: Option<SampleBuffer<T>>,
+        time_base: Option<TimeBase>,
+        sr: u32,
+        preroll: u64,
         channels: usize,
-        nframes: usize,
+        nframes: u64,
         frames: usize,
-        last_idx: usize,
     ) -> Self {
         DecoderStream {
             reader,
             decoder,
             track_id,
-            sample_buf,
+            time_base,
+            sr,
+            preroll,
+            sample_buf: None,
+            buffer: Vec::new(),
             channels,
             nframes,
             frames,
-            last_idx,
+            hop_length: frames,
+            skip: 0,
+            emitted_end: 0,
+            tail: HStreamTail::default(),
+            position: 0,
+            skip_to: None,
+            eof: false,
         }
     }
-}
 
-impl<T> Iterator for DecoderStream<T>
-where
-    T: Float + FloatConst + ConvertibleSample,
-{
-    type Item = HArray<T, Ix2>;
+    /// Sets what to do with the last frames of the stream, when there are fewer than the number of frames requested per
+    /// iteration. By default they are discarded.
+    /// When streaming overlapping frames, a last frame is only returned if it has frames not returned before.
+    pub fn with_tail(mut self, tail: HStreamTail) -> Self {
+        self.tail = tail;
+        self
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.nframes < self.frames {
-            return None;
-        }
+    /// Returns the index of the first frame to be returned in the next iteration.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
 
-        let mut ndarray = ArcArray2::zeros((self.channels, self.frames));
-        let mut ndarray_t = ndarray.view_mut().reversed_axes();
-        let mut ndarray_iter = ndarray_t.iter_mut();
-        let ndarray_iter_by_ref = ndarray_iter.by_ref();
+    /// Returns the sampling rate of the stream.
+    pub fn sr(&self) -> u32 {
+        self.sr
+    }
 
-        let nsamples = self.frames * self.channels;
-        let mut samples_used = 0_usize;
+    /// Returns the number of channels of the stream.
+    pub fn nchannels(&self) -> usize {
+        self.channels
+    }
 
-        // If samples reminiscent from last read packet.
-        if self.last_idx != 0 {
-            if let Some(audio_buf) = &mut self.sample_buf {
-                // The samples may now be access via the `samples()` function.
-                let mut samples = audio_buf.samples();
+    /// Returns the total number of frames of the stream.
+    pub fn nframes(&self) -> u64 {
+        self.nframes
+    }
 
-                samples = &samples[self.last_idx..];
+    /// Seeks to a frame, so it is the first frame returned in the next iteration.
+    /// The seek is sample-exact: the stream continues exactly as if all the frames before `frame` had been streamed.
+    ///
+    /// # Arguments
+    ///
+    /// `frame` - The frame index to seek to.
+    pub fn seek(&mut self, frame: u64) -> HResult<()> {
+        if frame > self.nframes {
+            return Err(HError::OutOfSpecError(format!(
+                "cannot seek to frame {frame}, the stream has {} frames",
+                self.nframes
+            )));
+        }
 
-                let samples_len = samples.len();
-                let remaining_samples = nsamples - samples_used;
+        // Some codecs need the previous packets to fully reconstruct the current one, so the reader seeks to a point a
+        // bit earlier than the target and the extra frames are discarded.
+        let seek_frame = frame.saturating_sub(self.preroll);
+        let seek_to = SeekTo::Time {
+            time: Time::from(seek_frame as f64 / self.sr as f64),
+            track_id: Some(self.track_id),
+        };
+        self.reader.seek(SeekMode::Accurate, seek_to)?;
+        self.decoder.reset();
 
-                if remaining_samples > samples_len {
-                    for (sample, elem) in samples.iter().zip(&mut *ndarray_iter_by_ref) {
-                        *elem = *sample;
-                        samples_used += 1;
-                    }
-                } else {
-                    for (sample, elem) in samples.iter().zip(&mut *ndarray_iter_by_ref) {
-                        *elem = *sample;
-                        self.last_idx += 1;
-                    }
+        self.buffer.clear();
+        self.skip = 0;
+        self.position = frame;
+        self.emitted_end = frame;
+        self.skip_to = Some(frame);
+        self.eof = false;
 
-                    self.nframes -= self.frames;
-                    let harray = HArray(ndarray);
-                    return Some(harray);
-                }
-            };
-        }
+        Ok(())
+    }
 
+    // Decodes the next packet of the track into the buffer.
+    fn decode_next_packet(&mut self) {
         loop {
             // Get the next packet from the format reader.
             let packet = match self.reader.next_packet() {
                 Ok(packet_ok) => packet_ok,
-                Err(symphonia::core::errors::Error::IoError(ref packet_err))
-                    if packet_err.kind() == std::io::ErrorKind::UnexpectedEof =>
-                {
-                    return None;
+                Err(_) => {
+                    self.eof = true;
+                    return;
                 }
-                Err(_) => return None,
             };
 
             // If the packet does not belong to the selected track, skip it.
@@ -378,22 +1464,13 @@ where
             // Decode the packet into audio samples.
             match self.decoder.decode(&packet) {
                 Ok(audio_buf) => {
-                    // The decoded audio samples may now be accessed via the audio buffer if per-channel
-                    // slices of samples in their native decoded format is desired. Use-cases where
-                    // the samples need to be accessed in an interleaved order or converted into
-                    // another sample format, or a byte buffer is required, are covered by copying the
-                    // audio buffer into a sample buffer or raw sample buffer, respectively. In the
-                    // example below, we will copy the audio buffer into a sample buffer in an
-                    // interleaved order.
-
                     // If this is the *first* decoded packet, create a sample buffer matching the
                     // decoded audio buffer format.
-                    if (self.sample_buf).is_none() {
+                    if self.sample_buf.is_none() {
                         // Get the audio buffer specification.
                         let spec = *audio_buf.spec();
                         // Get the capacity of the decoded buffer.
                         let cap = audio_buf.capacity() as u64;
-
                         // Create the sample buffer.
                         self.sample_buf = Some(SampleBuffer::<T>::new(cap, spec));
                     }
@@ -401,41 +1478,114 @@ where
                     if let Some(buf) = &mut self.sample_buf {
                         // Copy the decoded audio buffer into the sample buffer in an interleaved format.
                         buf.copy_interleaved_ref(audio_buf);
-
-                        // The samples may now be access via the `samples()` function.
                         let samples = buf.samples();
-                        self.last_idx = 0;
 
-                        let samples_len = samples.len();
-                        let remaining_samples = nsamples - samples_used;
-
-                        if remaining_samples > samples_len {
-                            for (sample, elem) in samples.iter().zip(&mut *ndarray_iter_by_ref) {
-                                *elem = *sample;
-                                samples_used += 1;
-                            }
-                        } else {
-                            for (sample, elem) in samples.iter().zip(&mut *ndarray_iter_by_ref) {
-                                *elem = *sample;
-                                self.last_idx += 1;
+                        // After seeking, discard the frames before the target frame.
+                        let skip = match self.skip_to {
+                            Some(target) => {
+                                let packet_start =
+                                    ts_to_frames(packet.ts(), self.time_base, self.sr);
+                                let packet_frames = (samples.len() / self.channels) as u64;
+                                if packet_start + packet_frames <= target {
+                                    return;
+                                }
+                                self.skip_to = None;
+                                target.saturating_sub(packet_start) as usize
                             }
+                            None => 0,
+                        };
 
-                            self.nframes -= self.frames;
-                            let harray = HArray(ndarray);
-                            return Some(harray);
-                        }
+                        self.buffer
+                            .extend_from_slice(&samples[skip * self.channels..]);
                     }
+                    return;
                 }
-                Err(symphonia::core::errors::Error::DecodeError(_)) => {
+                Err(_) => {
+                    self.eof = true;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl<T> Iterator for DecoderStream<T>
+where
+    T: Float + FloatConst + ConvertibleSample,
+{
+    type Item = HArray<T, Ix2>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let nsamples = self.frames * self.channels;
+
+        loop {
+            // Discard the samples between two iterations when the hop is longer than the frames returned.
+            if self.skip > 0 {
+                let n = self.skip.min(self.buffer.len());
+                self.buffer.drain(..n);
+                self.skip -= n;
+            }
+            if (self.skip == 0 && self.buffer.len() >= nsamples) || self.eof {
+                break;
+            }
+            self.decode_next_packet();
+        }
+
+        let frames = if self.skip == 0 {
+            (self.buffer.len() / self.channels).min(self.frames)
+        } else {
+            0
+        };
+
+        if frames == 0 {
+            return None;
+        }
+
+        let ncols = if frames < self.frames {
+            // Only the frames already returned in the previous iteration are left.
+            let already_returned = self.position + frames as u64 <= self.emitted_end;
+            match self.tail {
+                HStreamTail::Partial if !already_returned => frames,
+                HStreamTail::ZeroPad if !already_returned => self.frames,
+                _ => {
+                    self.position += frames as u64;
+                    self.buffer.clear();
                     return None;
                 }
-                Err(_) => return None,
             }
+        } else {
+            self.frames
+        };
+
+        let mut ndarray = ArcArray2::zeros((self.channels, ncols));
+        for (sample, elem) in self.buffer[..frames * self.channels]
+            .iter()
+            .zip(ndarray.view_mut().reversed_axes().iter_mut())
+        {
+            *elem = *sample;
+        }
+
+        self.emitted_end = self.position + frames as u64;
+
+        // This was the last frame of the stream.
+        if frames < self.frames {
+            self.position = self.emitted_end;
+            self.buffer.clear();
+            return Some(HArray(ndarray));
         }
+
+        // Move to the start of the next iteration.
+        let hop_samples = self.hop_length * self.channels;
+        let n = hop_samples.min(self.buffer.len());
+        self.buffer.drain(..n);
+        self.skip = hop_samples - n;
+        self.position += self.hop_length as u64;
+
+        Some(HArray(ndarray))
     }
 }
 
-/// Extract text and visual metadata from a file.
+/// Extract text and visual metadata, markers or broadcast metadata from a file.
 /// Tags that are part of the container format are preferentially extracted. Additional tags that were found while probing will not be extracted.
 /// The following metadata tagging formats are supported.
 ///
@@ -464,9 +1614,9 @@ where
 /// * media_type
 ///     The Media Type (MIME Type) used to encode the Visual.
 /// * dimensions
-///     The dimensions (width and height) of the Visual, represented in pixels. Note: This value may not be accurate as it comes from metadata, not the
+///     The dimensions (width, height) of the Visual, represented in pixels. Note: This value may not be accurate as it comes from metadata, not the
 ///     embedded graphic itself. Consider it only a hint.
-/// * bits_per_pixel:
+/// * bpp:
 ///     The number of bits-per-pixel (aka bit-depth) of the unencoded image.
 /// * color_mode:
 ///     Indicates how the color of a pixel is encoded in a Visual. Variants:
@@ -475,8 +1625,8 @@ where
 ///     **Indexed(NonZeroU32)
 ///         Each pixel in the Visual stores an index into a color palette containing the color information. The value stored by this variant indicates the number
 ///         of colors in the color palette.
-/// * size:
-///     Size of the image in bytes.
+/// * data:
+///     The encoded image, in the format given by the media type.
 /// * tag:
 ///     `Tag` with the following fields:
 ///         ** tag_key
@@ -489,30 +1639,57 @@ where
 ///         ** tag_value
 ///             The value of the Tag.
 ///
+/// # Markers
+///
+/// `HMetadataType::Markers` returns the chapters, cue points and loops, sorted by their start. Each `HMarker` has a label,
+/// start and end frames and, for loops, the `HLoopType`. The following sources are supported.
+///
+/// * WAV `cue ` points, labelled by the `LIST`/`adtl` chunk, and `smpl` loops
+/// * FLAC cuesheet tracks
+/// * ID3v2 `CHAP` frames (in MP3)
+/// * MP4 QuickTime chapters (a text track referenced by `tref`/`chap`, as in iTunes audiobooks), or else Nero chapters
+///   (`chpl` atom)
+/// * MKV/WebM chapters of the default edition
+/// * Vorbis Comment chapters (`CHAPTER001=00:00:00.000` and `CHAPTER001NAME`)
+///
+/// Markers are only extracted from seekable inputs, except the ones read by the format reader.
+///
+/// # Broadcast metadata
+///
+/// `HMetadataType::Broadcast` returns the `bext` and `iXML` chunks of a Broadcast Wave file as an `HBroadcastMetadata`.
+/// The `HBext` holds the originator, the origination date and time and the time reference, and the `HIxml` holds the
+/// project, scene, take, tape and note of the recording. Only seekable inputs are supported.
+///
 /// # Examples
 ///
 /// ```
 /// //metadata_from_file(../testfiles/gs-16b-1c-44100hz.mp3, HMetadataType::All).unwrap();
 /// ```
 pub fn metadata_from_file(fpath: &str, metadata_type: HMetadataType) -> HResult<Option<HMetadata>> {
-    let fpath = Path::new(fpath);
-    let ext = Path::extension(fpath)
-        .ok_or_else(|| HError::IoError("couldn't extract the file extension".into()))?
-        .to_str()
-        .ok_or_else(|| HError::IoError("cannot convert from &OsStr to &str".into()))?;
-    // Create a media source. Note that the MediaSource trait is automatically implemented for File, among other types.
-    let file = Box::new(File::open(fpath)?);
-    // Create the media source stream using the boxed media source from above.
-    let mss = MediaSourceStream::new(file, Default::default());
-    // Create a hint to help the format registry guess what format reader is appropriate.
-    let mut hint = Hint::new();
-    hint.with_extension(ext);
-    // Use the default options when reading and decoding.
-    let format_opts: FormatOptions = Default::default();
-    let metadata_opts: MetadataOptions = Default::default();
-    // Probe the media source stream for a format.
-    let mut probed =
-        symphonia::default::get_probe().format(&hint, mss, &format_opts, &metadata_opts)?;
+    metadata_from_input(HInput::from_file(fpath)?, metadata_type)
+}
+
+/// Extract text and visual metadata, markers or broadcast metadata from an audio input.
+/// See `metadata_from_file` for the details.
+///
+/// # Examples
+///
+/// ```
+/// //let bytes = std::fs::read("../testfiles/gs-16b-1c-44100hz.mp3").unwrap();
+/// //metadata_from_input(HInput::from_bytes(bytes), HMetadataType::All).unwrap();
+/// ```
+pub fn metadata_from_input(
+    input: HInput,
+    metadata_type: HMetadataType,
+) -> HResult<Option<HMetadata>> {
+    match metadata_type {
+        HMetadataType::Markers => return markers_from_input(input),
+        HMetadataType::Broadcast => return broadcast_from_input(input),
+        _ => (),
+    }
+
+    // Probe the input for a format.
+    let mut probed = input.probe()?;
 
     // Prefer metadata that's provided in the container format, over other tags found during the probe operation.
     let metadata = if let Some(metadata_rev) = probed.format.metadata().current() {
@@ -520,7 +1697,8 @@ pub fn metadata_from_file(fpath: &str, metadata_type: HMetadataType) -> HResult<
     } else {
         probed
             .metadata
-            .get()
+            .as_mut()
+            .map(|m| m.metadata())
             .as_ref()
             .and_then(|m| m.current())
             .map(|metadata_rev| process_metadata_rev(metadata_rev, metadata_type))
@@ -529,6 +1707,58 @@ pub fn metadata_from_file(fpath: &str, metadata_type: HMetadataType) -> HResult<
     Ok(metadata)
 }
 
+fn markers_from_input(mut input: HInput) -> HResult<Option<HMetadata>> {
+    let mut raw_markers = if input.source.is_seekable() {
+        markers::read_markers(&mut input.source)?
+    } else {
+        Vec::new()
+    };
+
+    let mut probed = input.probe()?;
+    raw_markers.extend(markers::cuesheet_markers(probed.format.cues()));
+    if let Some(metadata_rev) = probed.format.metadata().current() {
+        raw_markers.extend(markers::vorbis_chapters(metadata_rev.tags()));
+    }
+
+    if raw_markers.is_empty() {
+        return Ok(None);
+    }
+
+    let track = probed
+        .format
+        .default_track()
+        .ok_or_else(|| HError::Unsupported {
+            what: "no supported audio tracks".into(),
+        })?;
+    let sr = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| HError::DecodeError("the sampling rate is unknown".into()))?;
+
+    let mut markers = raw_markers
+        .into_iter()
+        .map(|x| {
+            Ok(HMarker {
+                label: x.label,
+                start: x.start.to_frames(sr)?,
+                end: x.end.map(|end| end.to_frames(sr)).transpose()?,
+                loop_type: x.loop_type,
+            })
+        })
+        .collect::<HResult<Vec<HMarker>>>()?;
+    markers.sort_by_key(|x| x.start);
+
+    Ok(Some(HMetadata::Markers(HMarkers(Some(markers)))))
+}
+
+fn broadcast_from_input(mut input: HInput) -> HResult<Option<HMetadata>> {
+    if !input.source.is_seekable() {
+        return Ok(None);
+    }
+
+    Ok(bwf::read_broadcast_metadata(&mut input.source)?.map(|x| HMetadata::Broadcast(Box::new(x))))
+}
+
 fn process_metadata_rev(
     metadata_rev: &MetadataRevision,
     metadata_type: HMetadataType,
@@ -543,6 +1773,9 @@ fn process_metadata_rev(
             );
             HMetadata::All((text, visual))
         }
+        HMetadataType::Markers | HMetadataType::Broadcast => {
+            unreachable!("markers and broadcast metadata are not part of a metadata revision")
+        }
     }
 }
 
@@ -561,7 +1794,8 @@ fn process_text_metadata(metadata_rev: &MetadataRevision) -> HTextMetadata {
                 None => "None".to_string(),
             };
 
-            let tag_value = tag.value.to_string();
+            // RIFF INFO values are NUL-terminated.
+            let tag_value = tag.value.to_string().trim_end_matches('\0').to_string();
 
             let tag_struct = HTag {
                 tag_key,
@@ -591,22 +1825,16 @@ fn process_visual_metadata(metadata_rev: &MetadataRevision) -> HVisualMetadata {
 
             let media_type = visual.media_type.clone();
 
-            let dimensions = match visual.dimensions {
-                Some(x) => format!("(width, heigth): ({}, {})", x.width, x.height),
-                None => "None".to_string(),
-            };
+            let dimensions = visual.dimensions.map(|x| (x.width, x.height));
 
-            let bpp = match visual.bits_per_pixel {
-                Some(x) => x.to_string(),
-                None => "None".to_string(),
-            };
+            let bpp = visual.bits_per_pixel.map(|x| x.get());
 
             let color_mode = match visual.color_mode {
                 Some(x) => format!("{:?}", x),
                 None => "None".to_string(),
             };
 
-            let size = visual.data.len().to_string();
+            let data = visual.data.to_vec();
 
             let tags = &visual.tags;
             let tags_vec = if tags.is_empty() {
@@ -640,109 +1868,298 @@ fn process_visual_metadata(metadata_rev: &MetadataRevision) -> HVisualMetadata {
                 dimensions,
                 bpp,
                 color_mode,
-                size,
+                data,
                 tags_vec,
             };
             metadata.push(visualmetadata);
         }
 
-        HVisualMetadata(Some(metadata))
+        HVisualMetadata(Some(metadata))
+    }
+}
+
+/// Get audio parameters from a file.
+/// Note that this avoids loading the contents into memory, and is therefore useful for querying these parameters from long files.
+/// When the number of frames is missing from the header, the packets are scanned, without being decoded, to compute it
+/// from their timestamps.
+///
+/// # Arguments
+/// `fpath` - The path to the input file.
+/// `track_id` - The track to query. If `None`, the default track is used.
+///
+/// # Examples
+///
+/// ```
+/// //let fname = "../testfiles/gs-16b-2c-44100hz.wav";
+/// //get_params_from_file(fname, None)
+/// ```
+pub fn get_params_from_file(fpath: &str, track_id: Option<u32>) -> HResult<HAudioInfo> {
+    get_params_from_input(HInput::from_file(fpath)?, track_id)
+}
+
+/// Get audio parameters from an audio input.
+/// See `get_params_from_file` for the details.
+///
+/// # Examples
+///
+/// ```
+/// //let bytes = std::fs::read("../testfiles/gs-16b-2c-44100hz.wav").unwrap();
+/// //get_params_from_input(HInput::from_bytes(bytes), None)
+/// ```
+pub fn get_params_from_input(input: HInput, track_id: Option<u32>) -> HResult<HAudioInfo> {
+    let byte_len = input.source.byte_len();
+    let probed = input.probe()?;
+    let mut reader = probed.format;
+    let track = select_track(reader.as_ref(), track_id)?;
+    let track_id = track.id;
+    let codec_params = track.codec_params.clone();
+
+    let sr = codec_params
+        .sample_rate
+        .ok_or_else(|| HError::DecodeError("cannot retrieve the sample rate".into()))?;
+    let mut channels = codec_params.channels.or_else(|| {
+        codec_params
+            .channel_layout
+            .map(|layout| layout.into_channels())
+    });
+    let mut nframes = codec_params.n_frames;
+
+    // The header is missing some parameters. Scan the packets to compute the number of frames from their timestamps
+    // and decode the first packet to get the channels.
+    if channels.is_none() || nframes.is_none() {
+        let mut decoder = match channels {
+            Some(_) => None,
+            None => {
+                Some(symphonia::default::get_codecs().make(&codec_params, &Default::default())?)
+            }
+        };
+        let mut end_ts = 0;
+
+        loop {
+            let packet = match reader.next_packet() {
+                Ok(packet_ok) => packet_ok,
+                Err(symphonia::core::errors::Error::IoError(ref packet_err))
+                    if packet_err.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    break;
+                }
+                Err(packet_err) => Err(packet_err)?,
+            };
+
+            if packet.track_id() != track_id {
+                continue;
+            }
+
+            end_ts = end_ts.max(packet.ts() + packet.dur());
+
+            if let Some(dec) = &mut decoder {
+                if let Ok(audio_buf) = dec.decode(&packet) {
+                    channels = Some(audio_buf.spec().channels);
+                    decoder = None;
+                }
+            }
+
+            if nframes.is_some() && decoder.is_none() {
+                break;
+            }
+        }
+
+        if nframes.is_none() {
+            let end_frame = ts_to_frames(end_ts, codec_params.time_base, sr);
+            let start_frame = ts_to_frames(codec_params.start_ts, codec_params.time_base, sr);
+            nframes = Some(end_frame.saturating_sub(start_frame));
+        }
+    }
+
+    let channels = channels
+        .ok_or_else(|| HError::DecodeError("cannot retrieve the number of channels".into()))?;
+    // Should not panic since nframes was computed above if missing.
+    let nframes = nframes.unwrap();
+    let duration = nframes as f64 / sr as f64;
+    let bitrate = match byte_len {
+        Some(byte_len) if duration > 0. => Some((byte_len as f64 * 8. / duration).round() as u64),
+        _ => None,
+    };
+
+    Ok(HAudioInfo {
+        sr,
+        nframes,
+        nchannels: channels.count(),
+        channel_layout: channels
+            .iter()
+            .map(|channel| format!("{:?}", channel))
+            .collect(),
+        codec: codec_name(codec_params.codec),
+        bits_per_sample: codec_params.bits_per_sample,
+        sample_format: codec_params
+            .sample_format
+            .or_else(|| pcm_sample_format(codec_params.codec))
+            .map(|sample_format| format!("{:?}", sample_format).to_lowercase()),
+        bitrate,
+        duration,
+    })
+}
+
+/// List all the tracks in an audio file.
+/// Each track has an id that can be used to select it when decoding. Tracks whose codec is not supported are also
+/// listed, with the codec id as the codec name.
+///
+/// # Arguments
+/// `fpath` - The path to the input file.
+///
+/// # Examples
+///
+/// ```
+/// //let fname = "../testfiles/gs-16b-2c-44100hz.mp4";
+/// //tracks_from_file(fname)
+/// ```
+pub fn tracks_from_file(fpath: &str) -> HResult<Vec<HTrack>> {
+    tracks_from_input(HInput::from_file(fpath)?)
+}
+
+/// List all the tracks in an audio input.
+/// See `tracks_from_file` for the details.
+pub fn tracks_from_input(input: HInput) -> HResult<Vec<HTrack>> {
+    let probed = input.probe()?;
+    let reader = probed.format;
+
+    let tracks = reader
+        .tracks()
+        .iter()
+        .map(|track| {
+            let codec_params = &track.codec_params;
+            HTrack {
+                id: track.id,
+                codec: codec_name(codec_params.codec),
+                sr: codec_params.sample_rate,
+                nchannels: codec_params.channels.map(|channels| channels.count()),
+                nframes: codec_params.n_frames,
+                language: track.language.clone(),
+            }
+        })
+        .collect();
+
+    Ok(tracks)
+}
+
+/// Converts a timestamp in the track's time base to a number of frames.
+pub(crate) fn ts_to_frames(ts: u64, time_base: Option<TimeBase>, sr: u32) -> u64 {
+    match time_base {
+        Some(time_base) => {
+            let time = time_base.calc_time(ts);
+            ((time.seconds as f64 + time.frac) * sr as f64).round() as u64
+        }
+        None => ts,
+    }
+}
+
+/// Returns the short name of a codec, or its id if the codec is not supported.
+fn codec_name(codec: CodecType) -> String {
+    match symphonia::default::get_codecs().get_codec(codec) {
+        Some(descriptor) => descriptor.short_name.to_string(),
+        None => codec.to_string(),
     }
 }
 
-/// Get audio parameters from a file.
-///Returns a tuple `(sr, nframes, nchannels, duration)`.
-/// `sr` will be in Hz and `duration` in seconds.
-/// Note that this avoids loading the contents into memory, and is therefore useful for querying these parameters from long files.
+/// Returns the sample format of a PCM codec. PCM readers don't always fill it in the codec parameters.
+fn pcm_sample_format(codec: CodecType) -> Option<SampleFormat> {
+    let sample_format = match codec {
+        CODEC_TYPE_PCM_U8 => SampleFormat::U8,
+        CODEC_TYPE_PCM_U16LE | CODEC_TYPE_PCM_U16BE => SampleFormat::U16,
+        CODEC_TYPE_PCM_U24LE | CODEC_TYPE_PCM_U24BE => SampleFormat::U24,
+        CODEC_TYPE_PCM_U32LE | CODEC_TYPE_PCM_U32BE => SampleFormat::U32,
+        CODEC_TYPE_PCM_S8 => SampleFormat::S8,
+        CODEC_TYPE_PCM_S16LE | CODEC_TYPE_PCM_S16BE => SampleFormat::S16,
+        CODEC_TYPE_PCM_S24LE | CODEC_TYPE_PCM_S24BE => SampleFormat::S24,
+        CODEC_TYPE_PCM_S32LE | CODEC_TYPE_PCM_S32BE => SampleFormat::S32,
+        CODEC_TYPE_PCM_F32LE | CODEC_TYPE_PCM_F32BE => SampleFormat::F32,
+        CODEC_TYPE_PCM_F64LE | CODEC_TYPE_PCM_F64BE => SampleFormat::F64,
+        _ => return None,
+    };
+    Some(sample_format)
+}
+
+/// Returns an error if the samples of a track are not integers that fit in `max_bits` bits.
+fn check_lossless_int(codec_params: &CodecParameters, max_bits: u32) -> HResult<()> {
+    let sample_format = codec_params
+        .sample_format
+        .or_else(|| pcm_sample_format(codec_params.codec));
+    if matches!(sample_format, Some(SampleFormat::F32 | SampleFormat::F64)) {
+        return Err(HError::OutOfSpecError(
+            "float samples cannot be decoded to an integer type without loss".into(),
+        ));
+    }
+
+    match codec_params.bits_per_sample {
+        Some(bits) if bits <= max_bits => Ok(()),
+        Some(bits) => Err(HError::OutOfSpecError(format!(
+            "{}-bit samples cannot be decoded to a {}-bit integer type without loss",
+            bits, max_bits
+        ))),
+        None => Err(HError::OutOfSpecError(format!(
+            "the bit depth of {} is unknown, so it cannot be decoded to an integer type without loss",
+            codec_name(codec_params.codec)
+        ))),
+    }
+}
+
+/// Returns the track with the given id, or the default track if `track_id` is `None`.
+pub(crate) fn select_track(reader: &dyn FormatReader, track_id: Option<u32>) -> HResult<&Track> {
+    match track_id {
+        Some(id) => reader
+            .tracks()
+            .iter()
+            .find(|track| track.id == id)
+            .ok_or_else(|| HError::OutOfSpecError(format!("track {id} was not found"))),
+        None => reader
+            .default_track()
+            .ok_or_else(|| HError::DecodeError("no tracks were detected".into())),
+    }
+}
+
+/// Verify an audio file, if supported by the decoder.
+/// The verification is done after the decoding process is finished.
 ///
 /// # Arguments
-/// `fpath` - The path to the input file.
+///
+/// * `fpath` - The input file.
+/// * `track_id` - The track to verify. If `None`, the default track is used.
 ///
 /// # Examples
 ///
 /// ```
-/// //let fname = "../testfiles/gs-16b-2c-44100hz.wav";
-/// //get_params_from_file(fname)
-/// ```
-pub fn get_params_from_file(fpath: &str) -> HResult<(u32, u64, usize, f64)> {
-    let fpath = Path::new(fpath);
-    let ext = Path::extension(fpath)
-        .ok_or_else(|| HError::IoError("couldn't extract the file extension".into()))?
-        .to_str()
-        .ok_or_else(|| HError::IoError("cannot convert from &OsStr to &str".into()))?;
-    let file = Box::new(File::open(fpath)?);
-    let mss = MediaSourceStream::new(file, Default::default());
-    let mut hint = Hint::new();
-    hint.with_extension(ext);
-    let format_opts: FormatOptions = Default::default();
-    let metadata_opts: MetadataOptions = Default::default();
-    let probed =
-        symphonia::default::get_probe().format(&hint, mss, &format_opts, &metadata_opts)?;
-    let reader = probed.format;
-    let track = reader
-        .default_track()
-        .ok_or_else(|| HError::DecodeError("no tracks were detected".into()))?;
-    let sr = track
-        .codec_params
-        .sample_rate
-        .ok_or_else(|| HError::DecodeError("cannot retrieve the sample rate".into()))?;
-    let nframes = track
-        .codec_params
-        .n_frames
-        .ok_or_else(|| HError::DecodeError("cannot retrieve the number of frames".into()))?;
-    let nchannels = track
-        .codec_params
-        .channels
-        .ok_or_else(|| HError::DecodeError("cannot retrieve the number of channels".into()))?
-        .count();
-    let duration = nframes as f64 / sr as f64;
-
-    Ok((sr, nframes, nchannels, duration))
+/// //verify_file("../testfiles/test.wav", None);
+/// ```
+pub fn verify_file(fpath: &str, track_id: Option<u32>) -> HResult<HVerifyDecode> {
+    verify_input(HInput::from_file(fpath)?, track_id)
 }
 
-/// Verify an audio file, if supported by the decoder.
+/// Verify an audio input, if supported by the decoder.
 /// The verification is done after the decoding process is finished.
 ///
 /// # Arguments
 ///
-/// * `fpath` - The input file.
+/// * `input` - The input file, buffer or reader.
+/// * `track_id` - The track to verify. If `None`, the default track is used.
 ///
 /// # Examples
 ///
 /// ```
-/// //verify("../testfiles/test.wav");
-/// ```
-pub fn verify_file(fpath: &str) -> HResult<HVerifyDecode> {
-    let fpath = Path::new(fpath);
-    let ext = Path::extension(fpath)
-        .ok_or_else(|| HError::IoError("couldn't extract the file extension".into()))?
-        .to_str()
-        .ok_or_else(|| HError::IoError("cannot convert from &OsStr to &str".into()))?;
-    // Create a media source. Note that the MediaSource trait is automatically implemented for File, among other types.
-    let file = Box::new(File::open(fpath)?);
-    // Create the media source stream using the boxed media source from above.
-    let mss = MediaSourceStream::new(file, Default::default());
-    // Create a hint to help the format registry guess what format reader is appropriate.
-    let mut hint = Hint::new();
-    hint.with_extension(ext);
-    // Use the default options when reading and decoding.
-    let format_opts: FormatOptions = Default::default();
-    let metadata_opts: MetadataOptions = Default::default();
-
+/// //let bytes = std::fs::read("../testfiles/gs-16b-2c-44100hz.flac").unwrap();
+/// //verify_input(HInput::from_bytes(bytes), None);
+/// ```
+pub fn verify_input(input: HInput, track_id: Option<u32>) -> HResult<HVerifyDecode> {
     #[allow(clippy::needless_update)]
     let decoder_opts: DecoderOptions = DecoderOptions {
         verify: true,
         ..Default::default() // in case of future options for DecoderOptions in Symphonia
     };
-    // Probe the media source stream for a format.
-    let probed =
-        symphonia::default::get_probe().format(&hint, mss, &format_opts, &metadata_opts)?;
+    // Probe the input for a format.
+    let probed = input.probe()?;
     // Get the format reader yielded by the probe operation.
     let mut reader = probed.format;
-    // Get the default track.
-    let track = reader
-        .default_track()
-        .ok_or_else(|| HError::DecodeError("no tracks were detected".into()))?;
+    // Get the selected track.
+    let track = select_track(reader.as_ref(), track_id)?;
     // Create a decoder for the track.
     let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &decoder_opts)?;
     let track_id = track.id;
@@ -790,17 +2207,187 @@ pub fn verify_file(fpath: &str) -> HResult<HVerifyDecode> {
     }
 }
 
+/// Verify an audio file and report the packets that failed to decode and the checksums.
+/// Unlike `verify_file`, a corrupt packet is not an error but is reported together with its position and the error
+/// returned by the decoder. In lenient mode, decoding goes on past corrupt packets so that all of them are reported.
+///
+/// # Arguments
+///
+/// * `fpath` - The input file.
+/// * `track_id` - The track to verify. If `None`, the default track is used.
+/// * `lenient` - Whether to keep decoding after a corrupt packet.
+///
+/// # Examples
+///
+/// ```
+/// //let report = verify_file_report("../testfiles/gs-16b-2c-44100hz.flac", None, true).unwrap();
+/// ```
+pub fn verify_file_report(
+    fpath: &str,
+    track_id: Option<u32>,
+    lenient: bool,
+) -> HResult<HVerifyReport> {
+    verify_input_report(HInput::from_file(fpath)?, track_id, lenient)
+}
+
+/// Verify an audio input and report the packets that failed to decode and the checksums.
+/// See `verify_file_report` for the details.
+pub fn verify_input_report(
+    input: HInput,
+    track_id: Option<u32>,
+    lenient: bool,
+) -> HResult<HVerifyReport> {
+    #[allow(clippy::needless_update)]
+    let decoder_opts: DecoderOptions = DecoderOptions {
+        verify: true,
+        ..Default::default()
+    };
+    let probed = input.probe()?;
+    let mut reader = probed.format;
+    let track = select_track(reader.as_ref(), track_id)?;
+    let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &decoder_opts)?;
+    let track_id = track.id;
+    let codec_params = track.codec_params.clone();
+
+    let (checksum_type, expected_checksum) = match codec_params.verification_check {
+        Some(VerificationCheck::Md5(x)) => (Some("md5"), Some(to_hex(&x))),
+        Some(VerificationCheck::Crc8(x)) => (Some("crc8"), Some(to_hex(&[x]))),
+        Some(VerificationCheck::Crc16(x)) => (Some("crc16"), Some(to_hex(&x))),
+        Some(VerificationCheck::Crc32(x)) => (Some("crc32"), Some(to_hex(&x))),
+        Some(VerificationCheck::Other(x)) => (Some("other"), Some(to_hex(&x))),
+        None => (None, None),
+    };
+    // The MD5 of FLAC is computed over the samples in little-endian order, using the least number of bytes that fit
+    // the bits per sample.
+    let mut md5 = match (
+        &codec_params.verification_check,
+        codec_params.bits_per_sample,
+    ) {
+        (Some(VerificationCheck::Md5(_)), Some(bits)) if (1..=32).contains(&bits) => {
+            Some((Md5::default(), bits))
+        }
+        _ => None,
+    };
+    let mut sample_buf: Option<SampleBuffer<i32>> = None;
+    let sr = codec_params.sample_rate.unwrap_or(1);
+
+    let mut npackets = 0;
+    let mut nframes = 0;
+    // The timestamp after the last packet read, where the next packet should start.
+    let mut end_ts = codec_params.start_ts;
+    let mut errors = Vec::new();
+
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet_ok) => packet_ok,
+            Err(symphonia::core::errors::Error::IoError(ref packet_err))
+                if packet_err.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                break;
+            }
+            // The format reader cannot go on after an error.
+            Err(packet_err) => {
+                errors.push(HPacketError {
+                    packet: npackets,
+                    frame: ts_to_frames(end_ts, codec_params.time_base, sr),
+                    error: packet_err.to_string(),
+                });
+                break;
+            }
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+        // Some format readers skip the packets they cannot read, which leaves a gap in the timestamps.
+        if packet.ts() > end_ts {
+            let frame = ts_to_frames(end_ts, codec_params.time_base, sr);
+            let nmissing = ts_to_frames(packet.ts(), codec_params.time_base, sr) - frame;
+            errors.push(HPacketError {
+                packet: npackets,
+                frame,
+                error: format!("{} frames are missing", nmissing),
+            });
+            if !lenient {
+                break;
+            }
+        }
+        npackets += 1;
+        let frame = ts_to_frames(packet.ts(), codec_params.time_base, sr);
+        end_ts = packet.ts() + packet.dur();
+
+        match decoder.decode(&packet) {
+            Ok(audio_buf) => {
+                nframes += audio_buf.frames() as u64;
+
+                if let Some((md5, bits)) = &mut md5 {
+                    let buf = sample_buf.get_or_insert_with(|| {
+                        SampleBuffer::<i32>::new(audio_buf.capacity() as u64, *audio_buf.spec())
+                    });
+                    buf.copy_interleaved_ref(audio_buf);
+                    let nbytes = bits.div_ceil(8) as usize;
+                    for sample in buf.samples() {
+                        let sample = sample >> (32 - *bits);
+                        md5.process_buf_bytes(&sample.to_le_bytes()[..nbytes]);
+                    }
+                }
+            }
+            Err(err) => {
+                let corrupt = matches!(
+                    err,
+                    symphonia::core::errors::Error::DecodeError(_)
+                        | symphonia::core::errors::Error::IoError(_)
+                );
+                errors.push(HPacketError {
+                    packet: npackets - 1,
+                    frame,
+                    error: err.to_string(),
+                });
+                if !(lenient && corrupt) {
+                    break;
+                }
+            }
+        }
+    }
+
+    let verify_ok = decoder.finalize().verify_ok;
+    let result = match verify_ok {
+        _ if !errors.is_empty() => HVerifyDecode::Failed,
+        Some(true) => HVerifyDecode::Passed,
+        Some(false) => HVerifyDecode::Failed,
+        None => HVerifyDecode::NotSupported,
+    };
+
+    Ok(HVerifyReport {
+        result,
+        npackets,
+        nframes,
+        errors,
+        checksum_type: checksum_type.map(String::from),
+        expected_checksum,
+        computed_checksum: md5.map(|(md5, _)| to_hex(&md5.md5())),
+    })
+}
+
+/// Formats bytes as a lowercase hexadecimal string.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use harmonium_core::audioop::AudioOp;
+    use harmonium_core::{
+        audioop::AudioOp,
+        conversions::{IntoFloat, IntoInt},
+    };
     use ndarray::s;
 
     macro_rules! decode_test {
             ($input: expr, $results: expr, $($t: ty),+ $(,)*) => {
                 let fpath = $input;
                 $(
-                    let (decoded_harray, sr) = decode::<$t>(fpath).unwrap();
+                    let (decoded_harray, sr) = decode::<$t>(fpath, None).unwrap();
                     let nframes = decoded_harray.nframes();
                     let nchannels = decoded_harray.nchannels();
                     let decoded_ndarray = decoded_harray.0;
@@ -884,11 +2471,11 @@ mod tests {
                 ($input: expr, $dimensions: expr, $($t: ty),+ $(,)*) => {
                     let fpath = $input;
                     $(
-                        let decoded_harray = decode::<$t>(fpath).unwrap().0;
+                        let decoded_harray = decode::<$t>(fpath, None).unwrap().0;
                         let nframes = decoded_harray.nframes();
                         let nchannels = $dimensions.0;
                         let frames = $dimensions.1;
-                        let mut stream_struct = stream::<$t>(fpath, frames).unwrap();
+                        let mut stream_struct = stream::<$t>(fpath, frames, None).unwrap();
 
                         // test first iteration
                         let harray_next = stream_struct.next().unwrap();
@@ -939,6 +2526,168 @@ mod tests {
         stream_test!(fpath, (nchannels, frames), f32, f64);
     }
 
+    #[test]
+    fn stream_tail_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
+        let frames = 1000;
+        let (harray, _) = decode::<f32>(fpath, None).unwrap();
+        let nframes = harray.0.ncols();
+        let remaining = nframes % frames;
+
+        let stream_struct = stream::<f32>(fpath, frames, None).unwrap();
+        assert_eq!(stream_struct.count(), nframes / frames);
+
+        let stream_struct = stream::<f32>(fpath, frames, None)
+            .unwrap()
+            .with_tail(HStreamTail::Partial);
+        let chunks: Vec<_> = stream_struct.collect();
+        assert_eq!(chunks.len(), nframes / frames + 1);
+        let last = chunks.last().unwrap();
+        assert_eq!(last.0, harray.0.slice(s![.., nframes - remaining..]));
+
+        let stream_struct = stream::<f32>(fpath, frames, None)
+            .unwrap()
+            .with_tail(HStreamTail::ZeroPad);
+        let last = stream_struct.last().unwrap();
+        assert_eq!(last.0.ncols(), frames);
+        assert_eq!(
+            last.0.slice(s![.., ..remaining]),
+            harray.0.slice(s![.., nframes - remaining..])
+        );
+        assert!(last.0.slice(s![.., remaining..]).iter().all(|x| *x == 0.));
+    }
+
+    #[test]
+    fn stream_frames_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
+        let (harray, _) = decode::<f32>(fpath, None).unwrap();
+        let nframes = harray.0.ncols();
+
+        for (frame_length, hop_length) in [(2048, 512), (1000, 1000), (300, 1000), (4096, 4095)] {
+            // The same framing used by `Stft`.
+            let windows: Vec<_> = harray
+                .0
+                .windows((2, frame_length))
+                .into_iter()
+                .step_by(hop_length)
+                .collect();
+
+            let stream_struct = stream_frames::<f32>(
+                fpath,
+                NonZero::new(frame_length).unwrap(),
+                NonZero::new(hop_length).unwrap(),
+                None,
+            )
+            .unwrap();
+            let chunks: Vec<_> = stream_struct.collect();
+            assert_eq!(chunks.len(), 1 + (nframes - frame_length) / hop_length);
+            assert_eq!(chunks.len(), windows.len());
+            for (chunk, window) in chunks.iter().zip(windows.iter()) {
+                assert_eq!(chunk.0, window);
+            }
+        }
+
+        // The last frame has the frames not returned before.
+        let (frame_length, hop_length) = (2048, 512);
+        let mut stream_struct = stream_frames::<f32>(
+            fpath,
+            NonZero::new(frame_length).unwrap(),
+            NonZero::new(hop_length).unwrap(),
+            None,
+        )
+        .unwrap()
+        .with_tail(HStreamTail::Partial);
+        let last_full = 1 + (nframes - frame_length) / hop_length;
+        let last = stream_struct.by_ref().nth(last_full).unwrap();
+        let start = last_full * hop_length;
+        assert_eq!(last.0, harray.0.slice(s![.., start..]));
+        assert!(stream_struct.next().is_none());
+
+        // Seeking keeps the hop.
+        let mut stream_struct = stream_frames::<f32>(
+            fpath,
+            NonZero::new(frame_length).unwrap(),
+            NonZero::new(hop_length).unwrap(),
+            None,
+        )
+        .unwrap();
+        stream_struct.seek(10000).unwrap();
+        stream_struct.next().unwrap();
+        assert_eq!(stream_struct.position(), 10000 + hop_length as u64);
+        let harray_next = stream_struct.next().unwrap();
+        assert_eq!(
+            harray_next.0,
+            harray.0.slice(s![.., 10512..10512 + frame_length])
+        );
+    }
+
+    #[test]
+    fn decode_gapless_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.mp3";
+        let (harray, sr) = decode::<f32>(fpath, None).unwrap();
+        let (gapless_harray, gapless_sr, trimmed) = decode_gapless::<f32>(fpath, None).unwrap();
+        assert_eq!(sr, gapless_sr);
+        assert!(trimmed.delay > 0);
+        let delay = trimmed.delay as usize;
+        let nframes = harray.nframes() - delay - trimmed.padding as usize;
+        assert_eq!(gapless_harray.nframes(), nframes);
+        assert_eq!(
+            gapless_harray.0,
+            harray.0.slice(s![.., delay..delay + nframes])
+        );
+
+        // Lossless formats have nothing to trim.
+        let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
+        let (harray, _) = decode::<f32>(fpath, None).unwrap();
+        let (gapless_harray, _, trimmed) = decode_gapless::<f32>(fpath, None).unwrap();
+        assert_eq!(trimmed, HGapless::default());
+        assert_eq!(harray, gapless_harray);
+
+        assert_eq!(
+            parse_itunsmpb(" 00000000 00000840 000001CA 00000000003F31F6 00000000 00000000"),
+            Some((2112, 458, 4141558))
+        );
+        assert_eq!(parse_itunsmpb("00000000 zz"), None);
+    }
+
+    #[test]
+    fn stream_seek_test() {
+        for fpath in [
+            "../testfiles/gs-16b-2c-44100hz.wav",
+            "../testfiles/gs-16b-2c-44100hz.flac",
+            "../testfiles/gs-16b-2c-44100hz.ogg",
+            "../testfiles/gs-16b-2c-44100hz.mp3",
+        ] {
+            let frames = 1000;
+            let (harray, _) = decode::<f32>(fpath, None).unwrap();
+            let nframes = harray.0.ncols();
+            let mut stream_struct = stream::<f32>(fpath, frames, None)
+                .unwrap()
+                .with_tail(HStreamTail::Partial);
+            assert_eq!(stream_struct.position(), 0);
+
+            stream_struct.next().unwrap();
+            assert_eq!(stream_struct.position(), frames as u64);
+
+            for frame in [123456, 5, 0, 400000, nframes - 10] {
+                stream_struct.seek(frame as u64).unwrap();
+                assert_eq!(stream_struct.position(), frame as u64);
+                let harray_next = stream_struct.next().unwrap();
+                let end = (frame + frames).min(nframes);
+                assert_eq!(harray_next.0, harray.0.slice(s![.., frame..end]));
+                assert_eq!(stream_struct.position(), end as u64);
+            }
+            assert!(stream_struct.next().is_none());
+
+            // Seeking back after the end of the stream.
+            stream_struct.seek(1000).unwrap();
+            let harray_next = stream_struct.next().unwrap();
+            assert_eq!(harray_next.0, harray.0.slice(s![.., 1000..2000]));
+
+            assert!(stream_struct.seek(nframes as u64 + 1).is_err());
+        }
+    }
+
     #[test]
     fn metadata_from_file_test() {
         let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
@@ -985,17 +2734,518 @@ mod tests {
         };
     }
 
+    #[test]
+    fn metadata_markers_test() {
+        // A cue point and a loop, inserted before the data chunk.
+        let wav = std::fs::read("../testfiles/gs-16b-2c-44100hz.wav").unwrap();
+        let data = wav.windows(4).position(|x| x == b"data").unwrap();
+        let mut cue = 1_u32.to_le_bytes().to_vec();
+        for x in [1_u32, 4410, u32::from_le_bytes(*b"data"), 0, 0, 4410] {
+            cue.extend_from_slice(&x.to_le_bytes());
+        }
+        let mut smpl = vec![0; 28];
+        for x in [1_u32, 0, 2, 0, 44100, 88199, 0, 0] {
+            smpl.extend_from_slice(&x.to_le_bytes());
+        }
+        let mut chunks = Vec::new();
+        for (id, body) in [(b"cue ", cue), (b"smpl", smpl)] {
+            chunks.extend_from_slice(id);
+            chunks.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            chunks.extend(body);
+        }
+        let mut wav = [&wav[..data], &chunks, &wav[data..]].concat();
+        let riff_len = wav.len() as u32 - 8;
+        wav[4..8].copy_from_slice(&riff_len.to_le_bytes());
+
+        let meta = metadata_from_input(HInput::from_bytes(wav), HMetadataType::Markers)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            meta,
+            HMetadata::Markers(HMarkers(Some(vec![
+                HMarker {
+                    label: String::new(),
+                    start: 4410,
+                    end: None,
+                    loop_type: None,
+                },
+                HMarker {
+                    label: String::new(),
+                    start: 44100,
+                    end: Some(88200),
+                    loop_type: Some(HLoopType::Forward),
+                },
+            ])))
+        );
+
+        // ID3v2 chapters, replacing the ID3v2 tag of the file.
+        let mp3 = std::fs::read("../testfiles/gs-16b-2c-44100hz.mp3").unwrap();
+        let tag_len = 10 + crate::tags::syncsafe_to_u32(mp3[6..10].try_into().unwrap()) as usize;
+        let mut chap = b"ch0\0".to_vec();
+        chap.extend_from_slice(&250_u32.to_be_bytes());
+        chap.extend_from_slice(&1000_u32.to_be_bytes());
+        chap.extend_from_slice(&[0xff; 8]);
+        chap.extend_from_slice(b"TIT2\0\0\0\x06\0\0\x03Intro");
+        let mut frames = b"CHAP".to_vec();
+        frames.extend_from_slice(&(chap.len() as u32).to_be_bytes());
+        frames.extend_from_slice(&[0, 0]);
+        frames.extend(chap);
+        let mut mp3 = [b"ID3\x03\0\0\0\0\0\0".as_slice(), &frames, &mp3[tag_len..]].concat();
+        mp3[9] = frames.len() as u8;
+
+        let meta = metadata_from_input(HInput::from_bytes(mp3), HMetadataType::Markers)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            meta,
+            HMetadata::Markers(HMarkers(Some(vec![HMarker {
+                label: "Intro".into(),
+                start: 11025,
+                end: Some(44100),
+                loop_type: None,
+            }])))
+        );
+
+        let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
+        assert_eq!(
+            metadata_from_file(fpath, HMetadataType::Markers).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn metadata_broadcast_test() {
+        let wav = std::fs::read("../testfiles/gs-16b-2c-44100hz.wav").unwrap();
+        let data = wav.windows(4).position(|x| x == b"data").unwrap();
+        let mut bext = vec![0; 602];
+        bext[..11].copy_from_slice(b"Scene 12A-3");
+        bext[256..264].copy_from_slice(b"Recorder");
+        bext[320..330].copy_from_slice(b"2024-05-01");
+        bext[330..338].copy_from_slice(b"10:00:00");
+        // 10 hours after midnight, at 48 kHz.
+        let time_reference = 10 * 3600 * 48000_u64;
+        bext[338..346].copy_from_slice(&time_reference.to_le_bytes());
+        bext[346..348].copy_from_slice(&1_u16.to_le_bytes());
+        bext.extend_from_slice(b"A=PCM,F=48000,W=24,M=stereo\r\n");
+        let ixml = b"<BWFXML><SCENE>12A</SCENE><TAKE>3</TAKE></BWFXML>".to_vec();
+        let mut chunks = Vec::new();
+        for (id, body) in [(b"bext", bext), (b"iXML", ixml)] {
+            chunks.extend_from_slice(id);
+            chunks.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            // Chunks are padded to an even length.
+            let pad = body.len() % 2;
+            chunks.extend(body);
+            chunks.extend(std::iter::repeat_n(0, pad));
+        }
+        let mut wav = [&wav[..data], &chunks, &wav[data..]].concat();
+        let riff_len = wav.len() as u32 - 8;
+        wav[4..8].copy_from_slice(&riff_len.to_le_bytes());
+
+        let meta = metadata_from_input(HInput::from_bytes(wav), HMetadataType::Broadcast)
+            .unwrap()
+            .unwrap();
+        let HMetadata::Broadcast(broadcast) = meta else {
+            panic!("expected broadcast metadata");
+        };
+        assert_eq!(
+            broadcast.bext.unwrap(),
+            HBext {
+                description: "Scene 12A-3".into(),
+                originator: "Recorder".into(),
+                originator_reference: String::new(),
+                origination_date: "2024-05-01".into(),
+                origination_time: "10:00:00".into(),
+                time_reference,
+                version: 1,
+                coding_history: "A=PCM,F=48000,W=24,M=stereo".into(),
+            }
+        );
+        let ixml = broadcast.ixml.unwrap();
+        assert_eq!(ixml.scene.as_deref(), Some("12A"));
+        assert_eq!(ixml.take.as_deref(), Some("3"));
+
+        let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
+        assert_eq!(
+            metadata_from_file(fpath, HMetadataType::Broadcast).unwrap(),
+            None
+        );
+        let fpath = "../testfiles/gs-16b-2c-44100hz.wav";
+        assert_eq!(
+            metadata_from_file(fpath, HMetadataType::Broadcast).unwrap(),
+            None
+        );
+    }
+
     #[test]
     fn get_params_from_file_test() {
         let fpath = "../testfiles/gs-16b-2c-44100hz.wav";
-        let params = get_params_from_file(fpath).unwrap();
-        assert_eq!(params, (44100, 698194, 2, 15.832063492063492));
+        let params = get_params_from_file(fpath, None).unwrap();
+        assert_eq!(params.sr, 44100);
+        assert_eq!(params.nframes, 698194);
+        assert_eq!(params.nchannels, 2);
+        assert_eq!(params.channel_layout, ["FRONT_LEFT", "FRONT_RIGHT"]);
+        assert_eq!(params.codec, "pcm_s16le");
+        assert_eq!(params.bits_per_sample, Some(16));
+        assert_eq!(params.sample_format.as_deref(), Some("s16"));
+        assert_eq!(params.duration, 15.832063492063492);
+        // 16 bits * 2 channels * 44100 Hz plus the header.
+        assert!(params.bitrate.unwrap().abs_diff(1411200) < 1000);
+
+        // The number of frames computed from the packets is the same as the one decoded.
+        for fpath in [
+            "../testfiles/gs-16b-2c-44100hz.flac",
+            "../testfiles/gs-16b-2c-44100hz.ogg",
+            "../testfiles/gs-16b-1c-44100hz.mp3",
+        ] {
+            let params = get_params_from_file(fpath, None).unwrap();
+            let (harray, sr) = decode::<f32>(fpath, None).unwrap();
+            assert_eq!(params.sr, sr);
+            assert_eq!(params.nchannels, harray.0.nrows());
+            assert_eq!(params.nframes, harray.0.ncols() as u64);
+        }
+
+        // No number of frames nor channels in the header.
+        let params = get_params_from_file("../testfiles/gs-16b-2c-44100hz.mp4", None).unwrap();
+        assert_eq!(params.codec, "aac");
+        assert_eq!(params.nchannels, 2);
+        assert!((params.duration - 15.83).abs() < 0.1);
     }
 
     #[test]
     fn verify_test() {
         let fpath = "../testfiles/gs-16b-2c-44100hz.wav";
-        let verify_decode = verify_file(fpath).unwrap();
+        let verify_decode = verify_file(fpath, None).unwrap();
         assert_eq!(verify_decode, HVerifyDecode::NotSupported);
     }
+
+    #[test]
+    fn verify_report_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.wav";
+        let report = verify_file_report(fpath, None, false).unwrap();
+        assert_eq!(report.result, HVerifyDecode::NotSupported);
+        assert_eq!(report.nframes, 698194);
+        assert!(report.errors.is_empty());
+        assert_eq!(report.checksum_type, None);
+        assert_eq!(report.computed_checksum, None);
+
+        let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
+        let report = verify_file_report(fpath, None, false).unwrap();
+        assert_eq!(report.result, HVerifyDecode::Passed);
+        assert_eq!(report.nframes, 698194);
+        assert_eq!(report.checksum_type.as_deref(), Some("md5"));
+        assert!(report.expected_checksum.is_some());
+        assert_eq!(report.expected_checksum, report.computed_checksum);
+
+        // Corrupt a few packets in the middle of the file.
+        let mut bytes = std::fs::read(fpath).unwrap();
+        let len = bytes.len();
+        for i in [len / 3, len / 2] {
+            bytes[i..i + 64].iter_mut().for_each(|x| *x = !*x);
+        }
+
+        let report = verify_input_report(HInput::from_bytes(bytes.clone()), None, false).unwrap();
+        assert_eq!(report.result, HVerifyDecode::Failed);
+        assert_eq!(report.errors.len(), 1);
+        let error = &report.errors[0];
+        assert_eq!(report.npackets, error.packet);
+        assert_eq!(report.nframes, error.frame);
+
+        let lenient = verify_input_report(HInput::from_bytes(bytes), None, true).unwrap();
+        assert_eq!(lenient.result, HVerifyDecode::Failed);
+        assert_eq!(lenient.errors[0], *error);
+        assert!(lenient.errors.len() >= 2);
+        assert!(lenient.nframes > report.nframes);
+        assert_ne!(lenient.expected_checksum, lenient.computed_checksum);
+    }
+
+    #[test]
+    fn decode_from_input_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
+        let (harray, sr) = decode::<f32>(fpath, None).unwrap();
+        let bytes = std::fs::read(fpath).unwrap();
+
+        // No hint, the format is guessed from the content.
+        let input = HInput::from_bytes(bytes.as_slice());
+        assert_eq!(
+            decode_from_input::<f32>(input, None).unwrap(),
+            (harray.clone(), sr)
+        );
+
+        let input = HInput::from_bytes(bytes.clone()).with_mime_type("audio/flac");
+        assert_eq!(
+            decode_from_input::<f32>(input, None).unwrap(),
+            (harray.clone(), sr)
+        );
+
+        // A wrong hint falls back to the content.
+        let input = HInput::from_bytes(bytes.clone()).with_extension("wav");
+        assert_eq!(
+            decode_from_input::<f32>(input, None).unwrap(),
+            (harray.clone(), sr)
+        );
+
+        let input = HInput::from_reader(std::io::Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(
+            decode_from_input::<f32>(input, None).unwrap(),
+            (harray.clone(), sr)
+        );
+
+        let input = HInput::from_bytes(bytes.clone());
+        let harray_next = stream_from_input::<f32>(input, 1000, None)
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(harray_next.0, harray.0.slice(s![.., ..1000]));
+
+        let input = HInput::from_bytes(bytes.clone());
+        assert_eq!(
+            get_params_from_input(input, None).unwrap(),
+            get_params_from_file(fpath, None).unwrap()
+        );
+
+        let input = HInput::from_bytes(bytes.clone());
+        assert_eq!(
+            metadata_from_input(input, HMetadataType::All).unwrap(),
+            metadata_from_file(fpath, HMetadataType::All).unwrap()
+        );
+
+        let input = HInput::from_bytes(bytes);
+        assert_eq!(
+            verify_input(input, None).unwrap(),
+            verify_file(fpath, None).unwrap()
+        );
+
+        assert!(decode_from_input::<f32>(HInput::from_bytes(vec![0; 100]), None).is_err());
+    }
+
+    #[test]
+    fn decode_int_test() {
+        for fpath in [
+            "../testfiles/gs-16b-2c-44100hz.wav",
+            "../testfiles/gs-16b-2c-44100hz.flac",
+            "../testfiles/gs-16b-1c-44100hz.wav",
+        ] {
+            let (harray_f32, sr_f32) = decode::<f32>(fpath, None).unwrap();
+            let (harray, sr) = decode_int::<i16>(fpath, None).unwrap();
+            assert_eq!(sr, sr_f32);
+            assert_eq!(
+                IntoFloat::<f32, Ix2>::into_float(harray.clone()),
+                harray_f32
+            );
+            assert_eq!(harray_f32.into_int().unwrap(), harray);
+
+            let (harray_f64, _) = decode::<f64>(fpath, None).unwrap();
+            let (harray, _) = decode_int::<i32>(fpath, None).unwrap();
+            assert_eq!(IntoFloat::<f64, Ix2>::into_float(harray), harray_f64);
+        }
+
+        // 8-bit samples are scaled to the range of i16.
+        let raw_format = HRawFormat {
+            sample_format: crate::encode::HSampleFormat::UInt8,
+            endianness: Default::default(),
+            nchannels: 1,
+            interleaved: true,
+            sr: 8000,
+        };
+        let input = HInput::from_bytes(vec![0, 128, 255]).with_raw_format(raw_format);
+        let (harray, _) = decode_int_from_input::<i16>(input, None).unwrap();
+        assert_eq!(harray.as_slice().unwrap(), &[-32768, 0, 32512]);
+
+        // Lossy codecs and 16-bit samples in an i8 are rejected.
+        assert!(decode_int::<i16>("../testfiles/gs-16b-2c-44100hz.mp3", None).is_err());
+        assert!(decode_int::<i8>("../testfiles/gs-16b-2c-44100hz.wav", None).is_err());
+    }
+
+    #[test]
+    fn decode_tolerant_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
+        let (harray, sr) = decode::<f32>(fpath, None).unwrap();
+        let options = HTolerantOptions::default();
+        assert_eq!(
+            decode_tolerant::<f32>(fpath, None, options).unwrap(),
+            (harray.clone(), sr, Vec::new())
+        );
+
+        // Corrupt a few packets in the middle of the file.
+        let mut bytes = std::fs::read(fpath).unwrap();
+        let len = bytes.len();
+        for i in [len / 3, len / 2] {
+            bytes[i..i + 64].iter_mut().for_each(|x| *x = !*x);
+        }
+        assert!(
+            decode_from_input::<f32>(HInput::from_bytes(bytes.clone()), None)
+                .map_or(true, |(lhs, _)| lhs != harray)
+        );
+
+        let input = HInput::from_bytes(bytes.clone());
+        let (lhs, _, concealed) = decode_tolerant_from_input::<f32>(input, None, options).unwrap();
+        assert_eq!(lhs.shape(), harray.shape());
+        assert_eq!(concealed.len(), 2);
+        let mut start = 0;
+        for range in concealed.iter() {
+            let range = range.start as usize..range.end as usize;
+            assert!(range.start > start);
+            assert!(lhs.0.slice(s![.., range.clone()]).iter().all(|x| *x == 0.));
+            assert_eq!(
+                lhs.0.slice(s![.., start..range.start]),
+                harray.0.slice(s![.., start..range.start])
+            );
+            start = range.end;
+        }
+        assert_eq!(
+            lhs.0.slice(s![.., start..]),
+            harray.0.slice(s![.., start..])
+        );
+
+        let options = HTolerantOptions {
+            concealment: HConcealment::Repeat,
+            max_errors: Some(2),
+        };
+        let input = HInput::from_bytes(bytes.clone());
+        let (lhs, _, concealed_repeat) =
+            decode_tolerant_from_input::<i16>(input, None, options).unwrap();
+        assert_eq!(concealed_repeat, concealed);
+        let range = concealed[0].start as usize..concealed[0].end as usize;
+        // The packets of this file have 4608 frames.
+        let n = range.len().min(4608);
+        assert_eq!(
+            lhs.0.slice(s![.., range.start..range.start + n]),
+            lhs.0
+                .slice(s![.., range.start - 4608..range.start - 4608 + n])
+        );
+
+        let options = HTolerantOptions {
+            max_errors: Some(1),
+            ..Default::default()
+        };
+        let input = HInput::from_bytes(bytes);
+        assert!(decode_tolerant_from_input::<f32>(input, None, options).is_err());
+
+        // The frames missing from a truncated file are reported as concealed.
+        let fpath = "../testfiles/gs-16b-2c-44100hz.wav";
+        let (harray, _) = decode::<f32>(fpath, None).unwrap();
+        let nframes = harray.nframes() as u64;
+        let mut bytes = std::fs::read(fpath).unwrap();
+        // Keep the header and all but the last 10000 frames of the data chunk.
+        bytes.truncate(44 + 4 * (harray.nframes() - 10000));
+        let input = HInput::from_bytes(bytes);
+        let (lhs, _, concealed) =
+            decode_tolerant_from_input::<f32>(input, None, HTolerantOptions::default()).unwrap();
+        assert_eq!(lhs.shape(), harray.shape());
+        assert_eq!(concealed.len(), 1);
+        assert_eq!(concealed[0].end, nframes);
+        assert!(concealed[0].start <= nframes - 10000);
+        let start = concealed[0].start as usize;
+        assert_eq!(
+            lhs.0.slice(s![.., ..start]),
+            harray.0.slice(s![.., ..start])
+        );
+    }
+
+    #[test]
+    fn decode_range_test() {
+        for fpath in [
+            "../testfiles/gs-16b-2c-44100hz.wav",
+            "../testfiles/gs-16b-2c-44100hz.flac",
+            "../testfiles/gs-16b-1c-44100hz.flac",
+            "../testfiles/gs-16b-1c-44100hz.wav",
+            "../testfiles/gs-16b-2c-44100hz.ogg",
+            "../testfiles/gs-16b-2c-44100hz.mp3",
+        ] {
+            let (harray, sr) = decode::<f32>(fpath, None).unwrap();
+            let nframes = harray.0.ncols();
+
+            let (range, sr_range) = decode_range::<f32>(
+                fpath,
+                HTime::Frames(12345),
+                Some(HTime::Frames(50000)),
+                None,
+            )
+            .unwrap();
+            assert_eq!(sr_range, sr);
+            assert_eq!(range.0, harray.0.slice(s![.., 12345..62345]));
+
+            let (range, _) =
+                decode_range::<f32>(fpath, HTime::Seconds(1.), Some(HTime::Seconds(0.5)), None)
+                    .unwrap();
+            assert_eq!(range.0, harray.0.slice(s![.., 44100..66150]));
+
+            let (range, _) = decode_range::<f32>(fpath, HTime::Seconds(2.), None, None).unwrap();
+            assert_eq!(range.0, harray.0.slice(s![.., 88200..]));
+
+            let (range, _) =
+                decode_range::<f32>(fpath, HTime::Frames(0), Some(HTime::Frames(1000)), None)
+                    .unwrap();
+            assert_eq!(range.0, harray.0.slice(s![.., ..1000]));
+
+            // The range is clamped to the end of the file.
+            let (range, _) = decode_range::<f32>(
+                fpath,
+                HTime::Frames(nframes as u64 - 10),
+                Some(HTime::Seconds(1.)),
+                None,
+            )
+            .unwrap();
+            assert_eq!(range.0, harray.0.slice(s![.., nframes - 10..]));
+
+            let (range, _) =
+                decode_range::<f32>(fpath, HTime::Frames(nframes as u64 + 10), None, None).unwrap();
+            assert_eq!(range.0.ncols(), 0);
+        }
+
+        assert!(decode_range::<f32>(
+            "../testfiles/gs-16b-2c-44100hz.wav",
+            HTime::Seconds(-1.),
+            None,
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn tracks_test() {
+        for fpath in [
+            "../testfiles/gs-16b-2c-44100hz.wav",
+            "../testfiles/gs-16b-2c-44100hz.flac",
+            "../testfiles/gs-16b-2c-44100hz.ogg",
+        ] {
+            let tracks = tracks_from_file(fpath).unwrap();
+            assert!(!tracks.is_empty());
+            let track = &tracks[0];
+            assert_eq!(track.sr, Some(44100));
+            assert!(!track.codec.is_empty());
+
+            // Selecting the track explicitly is the same as using the default track.
+            let (harray, sr) = decode::<f32>(fpath, None).unwrap();
+            if let Some(nchannels) = track.nchannels {
+                assert_eq!(nchannels, harray.0.nrows());
+            }
+            assert_eq!(
+                decode::<f32>(fpath, Some(track.id)).unwrap(),
+                (harray.clone(), sr)
+            );
+            let harray_next = stream::<f32>(fpath, 1000, Some(track.id))
+                .unwrap()
+                .next()
+                .unwrap();
+            assert_eq!(harray_next.0, harray.0.slice(s![.., ..1000]));
+            assert_eq!(
+                get_params_from_file(fpath, Some(track.id)).unwrap(),
+                get_params_from_file(fpath, None).unwrap()
+            );
+
+            let missing_id = tracks.iter().map(|track| track.id).max().unwrap() + 1;
+            assert!(decode::<f32>(fpath, Some(missing_id)).is_err());
+            assert!(stream::<f32>(fpath, 1000, Some(missing_id)).is_err());
+            assert!(get_params_from_file(fpath, Some(missing_id)).is_err());
+        }
+
+        let tracks = tracks_from_file("../testfiles/gs-16b-2c-44100hz.flac").unwrap();
+        assert_eq!(tracks[0].codec, "flac");
+
+        let tracks = tracks_from_file("../testfiles/gs-16b-2c-44100hz.mp4").unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].codec, "aac");
+        assert_eq!(tracks[0].sr, Some(44100));
+    }
 }
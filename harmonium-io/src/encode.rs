@@ -0,0 +1,947 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use harmonium_core::{
+    array::HArray,
+    audioop::AudioOp,
+    errors::{HError, HResult},
+};
+use ndarray::Ix2;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+use crate::{decode::HTextMetadata, tags};
+
+/// The sample format used to store the samples in the output file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HSampleFormat {
+    /// 8-bit unsigned integer PCM.
+    UInt8,
+    /// 16-bit signed integer PCM.
+    Int16,
+    /// 24-bit signed integer PCM.
+    Int24,
+    /// 32-bit signed integer PCM.
+    Int32,
+    /// 32-bit IEEE float.
+    Float32,
+    /// 64-bit IEEE float.
+    Float64,
+}
+
+impl HSampleFormat {
+    /// The number of bits used to store each sample.
+    pub fn bits_per_sample(&self) -> u16 {
+        match self {
+            HSampleFormat::UInt8 => 8,
+            HSampleFormat::Int16 => 16,
+            HSampleFormat::Int24 => 24,
+            HSampleFormat::Int32 | HSampleFormat::Float32 => 32,
+            HSampleFormat::Float64 => 64,
+        }
+    }
+
+    /// Returns true if the samples are stored as floats.
+    pub fn is_float(&self) -> bool {
+        matches!(self, HSampleFormat::Float32 | HSampleFormat::Float64)
+    }
+}
+
+/// What to do when a sample lies outside the range of \[-1.0, 1.0\] while encoding to an integer sample format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HOutOfRange {
+    /// Clip the sample to the nearest bound. `NaN` is written as zero.
+    Clip,
+    /// Return an error.
+    Error,
+}
+
+/// Encode an HArray as a WAV file.
+/// The samples are expected to be normalized in the range of \[-1.0, 1.0\], as returned by `decode`.
+/// Samples outside this range are handled according to `out_of_range` when encoding to an integer sample format. Float
+/// sample formats store the samples as they are.
+///
+/// # Arguments
+///
+/// * `fpath` - The output file.
+/// * `harray` - The samples, with shape `(nchannels, nframes)`.
+/// * `sr` - The sampling rate in Hz.
+/// * `sample_format` - The sample format of the output file.
+/// * `out_of_range` - How to handle samples outside the range of \[-1.0, 1.0\].
+///
+/// # Examples
+///
+/// ```
+/// //let (harray, sr) = decode::<f32>("../testfiles/gs-16b-2c-44100hz.flac", None).unwrap();
+/// //write_wav("out.wav", &harray, sr, HSampleFormat::Int16, HOutOfRange::Clip).unwrap();
+/// ```
+pub fn write_wav<T>(
+    fpath: &str,
+    harray: &HArray<T, Ix2>,
+    sr: u32,
+    sample_format: HSampleFormat,
+    out_of_range: HOutOfRange,
+) -> HResult<()>
+where
+    T: Float + FloatConst + FromPrimitive,
+{
+    // Check the samples before creating the file, so an existing file is left untouched on error. The other parameters
+    // are checked by `stream_wav` before it creates the file.
+    wav_data_len(harray.nchannels(), harray.nframes(), sample_format)?;
+    if out_of_range == HOutOfRange::Error && !sample_format.is_float() {
+        check_range(harray)?;
+    }
+
+    let mut encoder = stream_wav(fpath, harray.nchannels(), sr, sample_format, out_of_range)?;
+    encoder.write(harray)?;
+    encoder.finalize()
+}
+
+/// Creates an `EncoderStream`, used to write a WAV file incrementally.
+/// The samples are expected to be normalized in the range of \[-1.0, 1.0\], as returned by `stream`.
+///
+/// # Arguments
+///
+/// * `fpath` - The output file.
+/// * `nchannels` - The number of channels of each block to be written.
+/// * `sr` - The sampling rate in Hz.
+/// * `sample_format` - The sample format of the output file.
+/// * `out_of_range` - How to handle samples outside the range of \[-1.0, 1.0\].
+///
+/// # Examples
+///
+/// ```
+/// //let decoder = stream::<f32>("../testfiles/gs-16b-2c-44100hz.flac", 1000, None).unwrap();
+/// //let mut encoder = stream_wav("out.wav", 2, 44100, HSampleFormat::Int16, HOutOfRange::Clip).unwrap();
+/// //for harray in decoder {
+/// //    encoder.write(&harray).unwrap();
+/// //}
+/// //encoder.finalize().unwrap();
+/// ```
+pub fn stream_wav(
+    fpath: &str,
+    nchannels: usize,
+    sr: u32,
+    sample_format: HSampleFormat,
+    out_of_range: HOutOfRange,
+) -> HResult<EncoderStream> {
+    // The chunk lengths are unknown at this point and are fixed up by `flush` and `finalize`. The header is built before
+    // creating the file, so invalid parameters leave an existing file untouched.
+    let mut header = Vec::with_capacity(WAV_HEADER_LEN as usize);
+    write_wav_header(&mut header, nchannels, sr, sample_format, 0)?;
+
+    let file = File::create(Path::new(fpath))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(&header)?;
+
+    Ok(EncoderStream {
+        writer,
+        nchannels,
+        sample_format,
+        out_of_range,
+        data_len: 0,
+        finalized: false,
+    })
+}
+
+/// A writer that encodes audio in blocks, mirroring `DecoderStream`.
+/// The WAV header is fixed up when the stream is flushed or finalized. If the stream is dropped without being finalized, it is
+/// finalized on a best-effort basis, so the samples written up to that point can still be decoded.
+pub struct EncoderStream {
+    // Writer.
+    writer: BufWriter<File>,
+    // Number of channels.
+    nchannels: usize,
+    // Sample format of the output file.
+    sample_format: HSampleFormat,
+    // How to handle samples out of range.
+    out_of_range: HOutOfRange,
+    // Number of bytes written to the data chunk.
+    data_len: u32,
+    // Whether the header has already been fixed up for the last time.
+    finalized: bool,
+}
+
+impl EncoderStream {
+    /// Encodes a block of samples, with shape `(nchannels, nframes)`, appending it to the file.
+    pub fn write<T>(&mut self, harray: &HArray<T, Ix2>) -> HResult<()>
+    where
+        T: Float + FloatConst + FromPrimitive,
+    {
+        if harray.nchannels() != self.nchannels {
+            return Err(HError::ShapeMismatch {
+                expected: vec![self.nchannels, harray.nframes()],
+                got: harray.shape().to_vec(),
+            });
+        }
+
+        let block_len = wav_data_len(self.nchannels, harray.nframes(), self.sample_format)?;
+        let data_len = self
+            .data_len
+            .checked_add(block_len)
+            .filter(|x| (*x as u64) + WAV_HEADER_LEN <= u32::MAX as u64)
+            .ok_or_else(|| HError::OutOfSpecError("the data is too large for a WAV file".into()))?;
+
+        // Check the whole block before writing, so a failed write does not leave a partial block in the file.
+        if self.out_of_range == HOutOfRange::Error && !self.sample_format.is_float() {
+            check_range(harray)?;
+        }
+
+        // Iterate in interleaved order.
+        for sample in harray.0.view().reversed_axes().iter() {
+            write_sample(&mut self.writer, *sample, self.sample_format)?;
+        }
+
+        self.data_len = data_len;
+
+        Ok(())
+    }
+
+    /// Flushes the buffered samples and fixes up the header, so the file written so far is a valid WAV file.
+    pub fn flush(&mut self) -> HResult<()> {
+        self.update_header()
+    }
+
+    /// Number of frames written so far.
+    pub fn nframes(&self) -> usize {
+        self.data_len as usize
+            / (self.nchannels * (self.sample_format.bits_per_sample() / 8) as usize)
+    }
+
+    /// Pads the data chunk, fixes up the header and flushes the file.
+    pub fn finalize(mut self) -> HResult<()> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> HResult<()> {
+        self.finalized = true;
+        // The RIFF chunks must have an even length.
+        if !self.data_len.is_multiple_of(2) {
+            self.writer.write_all(&[0])?;
+        }
+        self.update_header()?;
+        self.writer.get_ref().sync_all()?;
+        Ok(())
+    }
+
+    fn update_header(&mut self) -> HResult<()> {
+        let riff_len = (WAV_HEADER_LEN - 8) as u32 + self.data_len + self.data_len % 2;
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer.write_all(&riff_len.to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(WAV_HEADER_LEN - 4))?;
+        self.writer.write_all(&self.data_len.to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for EncoderStream {
+    fn drop(&mut self) {
+        if !self.finalized {
+            let _ = self.finish();
+        }
+    }
+}
+
+/// Options used when encoding a FLAC file.
+#[cfg(feature = "flac-encode")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HFlacOptions {
+    /// The bit depth of the encoded samples. One of 8, 12, 16, 20 or 24.
+    pub bits_per_sample: u32,
+    /// The compression level, from 0 (fastest) to 8 (smallest file).
+    pub compression_level: u8,
+    /// Whether to store the MD5 signature of the samples in the STREAMINFO block, so the file can be checked with `verify_file`.
+    pub md5: bool,
+}
+
+#[cfg(feature = "flac-encode")]
+impl Default for HFlacOptions {
+    fn default() -> Self {
+        HFlacOptions {
+            bits_per_sample: 16,
+            compression_level: 5,
+            md5: true,
+        }
+    }
+}
+
+/// Encode an HArray as a FLAC file.
+/// The samples are expected to be normalized in the range of \[-1.0, 1.0\], as returned by `decode`.
+/// Samples outside this range are handled according to `out_of_range`.
+///
+/// # Arguments
+///
+/// * `fpath` - The output file.
+/// * `harray` - The samples, with shape `(nchannels, nframes)`.
+/// * `sr` - The sampling rate in Hz.
+/// * `options` - The bit depth, compression level and whether to store the MD5 signature.
+/// * `out_of_range` - How to handle samples outside the range of \[-1.0, 1.0\].
+///
+/// # Examples
+///
+/// ```
+/// //let (harray, sr) = decode::<f32>("../testfiles/gs-16b-2c-44100hz.wav", None).unwrap();
+/// //write_flac("out.flac", &harray, sr, &HFlacOptions::default(), HOutOfRange::Clip).unwrap();
+/// ```
+#[cfg(feature = "flac-encode")]
+pub fn write_flac<T>(
+    fpath: &str,
+    harray: &HArray<T, Ix2>,
+    sr: u32,
+    options: &HFlacOptions,
+    out_of_range: HOutOfRange,
+) -> HResult<()>
+where
+    T: Float + FloatConst + FromPrimitive,
+{
+    let encoder = crate::flac::FlacEncoder::try_new(
+        harray.nchannels(),
+        sr,
+        options.bits_per_sample,
+        options.compression_level,
+        options.md5,
+    )?;
+
+    if out_of_range == HOutOfRange::Error {
+        check_range(harray)?;
+    }
+
+    let max = ((1_i64 << (options.bits_per_sample - 1)) - 1) as f64;
+    let channels: Vec<Vec<i32>> = harray
+        .0
+        .outer_iter()
+        .map(|channel| {
+            channel
+                .iter()
+                // This should not panic since it is a conversion from f32 or f64.
+                .map(|x| quantize(unsafe { x.to_f64().unwrap_unchecked() }, -max - 1., max) as i32)
+                .collect()
+        })
+        .collect();
+
+    let file = File::create(Path::new(fpath))?;
+    let mut writer = BufWriter::new(file);
+    encoder.encode(&mut writer, &channels)
+}
+
+/// A cover art to be written with `write_metadata`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HCoverArt {
+    /// The MIME type of the image, such as `"image/png"`.
+    pub media_type: String,
+    /// A description of the image. Can be empty.
+    pub description: String,
+    /// The encoded image.
+    pub data: Vec<u8>,
+}
+
+/// Write tags, and optionally a cover art, to an existing audio file.
+/// The format is detected from the content of the file. The tags are stored as:
+/// * WAV - a RIFF `INFO` chunk, and an ID3v2.4 tag in the `id3 ` chunk if the file has one. The cover art is stored in
+///   the `id3 ` chunk, which is added if missing.
+/// * FLAC - a Vorbis comment block. The cover art is stored in a `PICTURE` block.
+/// * OGG (Vorbis or Opus) - the comment header. The cover art is stored as a `METADATA_BLOCK_PICTURE` comment.
+/// * MP3 - an ID3v2.4 tag. Existing ID3v2.2 tags, and tags with compressed or encrypted frames, are not supported.
+///
+/// The existing tags are replaced by the new ones. The existing cover art is kept unless a new one is given.
+/// The file is rewritten to a temporary file in the same directory, which is synced to disk and then replaces the
+/// original, keeping its permissions.
+/// Each tag is stored under the field matching its `tag_std_key`, such as `TrackTitle`, when there is one, and under its
+/// `tag_key` otherwise. RIFF INFO only accepts 4-character ids, such as `ICMT`, as `tag_key`. Tags without a matching ID3v2
+/// frame are stored in `TXXX` frames.
+///
+/// # Arguments
+///
+/// * `fpath` - The file to be tagged.
+/// * `metadata` - The tags, as returned by `metadata_from_file`.
+/// * `cover_art` - An optional cover art.
+///
+/// # Examples
+///
+/// ```
+/// //let tag = HTag { tag_key: "source".into(), tag_std_key: "None".into(), tag_value: "gs-16b-2c-44100hz.flac".into() };
+/// //write_metadata("out.flac", &HTextMetadata(Some(vec![tag])), None).unwrap();
+/// ```
+pub fn write_metadata(
+    fpath: &str,
+    metadata: &HTextMetadata,
+    cover_art: Option<&HCoverArt>,
+) -> HResult<()> {
+    let bytes = std::fs::read(Path::new(fpath))?;
+    let tags = metadata.0.as_deref().unwrap_or_default();
+
+    // A leading ID3v2 tag is also found in FLAC files, where it is kept as it is.
+    let id3_len = match bytes.as_slice() {
+        [b'I', b'D', b'3', _, _, flags, s0, s1, s2, s3, ..] => {
+            let size = [*s0, *s1, *s2, *s3]
+                .iter()
+                .fold(0, |acc, x| (acc << 7) | (*x & 0x7f) as usize);
+            10 + size + if flags & 0x10 != 0 { 10 } else { 0 }
+        }
+        _ => 0,
+    };
+    let after_id3 = bytes.get(id3_len..).unwrap_or_default();
+
+    let out = if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE".as_slice()) {
+        tags::write_wav_tags(&bytes, tags, cover_art)?
+    } else if after_id3.starts_with(b"fLaC") {
+        let mut out = bytes[..id3_len].to_vec();
+        out.extend(tags::write_flac_tags(after_id3, tags, cover_art)?);
+        out
+    } else if bytes.starts_with(b"OggS") {
+        tags::write_ogg_tags(&bytes, tags, cover_art)?
+    } else if id3_len > 0 || matches!(bytes.as_slice(), [0xff, x, ..] if x & 0xe0 == 0xe0) {
+        tags::write_id3v2_tags(&bytes, tags, cover_art)?
+    } else {
+        return Err(HError::Unsupported {
+            what: "writing tags is only supported for WAV, FLAC, OGG and MP3 files".into(),
+        });
+    };
+
+    // Write to a temporary file first, so the original is left untouched if writing fails. The file is synced before
+    // replacing the original, which would otherwise be left empty by a crash. The counter keeps the name unique when
+    // several threads tag the same file.
+    static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let path = Path::new(fpath);
+    let permissions = std::fs::metadata(path)?.permissions();
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp_path = path.with_file_name(tmp_name);
+    let mut file = File::create_new(&tmp_path)?;
+    let res = file
+        .write_all(&out)
+        .and_then(|_| file.set_permissions(permissions))
+        .and_then(|_| file.sync_all())
+        .and_then(|_| {
+            drop(file);
+            std::fs::rename(&tmp_path, path)
+        });
+    if let Err(err) = res {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(err.into());
+    }
+
+    Ok(())
+}
+
+/// Size in bytes of the `RIFF` and `fmt ` headers, plus the `data` chunk header.
+pub(crate) const WAV_HEADER_LEN: u64 = 44;
+
+/// Computes the length in bytes of the `data` chunk, checking it fits in a WAV file.
+pub(crate) fn wav_data_len(
+    nchannels: usize,
+    nframes: usize,
+    sample_format: HSampleFormat,
+) -> HResult<u32> {
+    let bytes_per_sample = (sample_format.bits_per_sample() / 8) as u64;
+    (nchannels as u64)
+        .checked_mul(nframes as u64)
+        .and_then(|x| x.checked_mul(bytes_per_sample))
+        .filter(|x| x + WAV_HEADER_LEN <= u32::MAX as u64)
+        .and_then(|x| u32::try_from(x).ok())
+        .ok_or_else(|| HError::OutOfSpecError("the data is too large for a WAV file".into()))
+}
+
+/// Writes the `RIFF`, `fmt ` and `data` chunk headers.
+pub(crate) fn write_wav_header<W: Write>(
+    writer: &mut W,
+    nchannels: usize,
+    sr: u32,
+    sample_format: HSampleFormat,
+    data_len: u32,
+) -> HResult<()> {
+    // The definition of these format identifiers can be found in mmreg.h of the Microsoft Windows Platform SDK.
+    const WAVE_FORMAT_PCM: u16 = 0x0001;
+    const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+
+    let format = if sample_format.is_float() {
+        WAVE_FORMAT_IEEE_FLOAT
+    } else {
+        WAVE_FORMAT_PCM
+    };
+    let bits_per_sample = sample_format.bits_per_sample();
+    let (nchannels, block_align) = u16::try_from(nchannels)
+        .ok()
+        .filter(|x| *x > 0)
+        .and_then(|x| Some((x, x.checked_mul(bits_per_sample / 8)?)))
+        .ok_or_else(|| HError::OutOfSpecError("invalid number of channels".into()))?;
+    let avg_bytes_per_sec = sr
+        .checked_mul(block_align as u32)
+        .filter(|_| sr > 0)
+        .ok_or_else(|| HError::OutOfSpecError("invalid sampling rate".into()))?;
+    let riff_len = (WAV_HEADER_LEN - 8) as u32 + data_len + data_len % 2;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&riff_len.to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16_u32.to_le_bytes())?;
+    writer.write_all(&format.to_le_bytes())?;
+    writer.write_all(&nchannels.to_le_bytes())?;
+    writer.write_all(&sr.to_le_bytes())?;
+    writer.write_all(&avg_bytes_per_sec.to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&bits_per_sample.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())?;
+
+    Ok(())
+}
+
+/// Writes a single sample in little-endian byte order.
+/// Integer sample formats clip the samples outside the range of \[-1.0, 1.0\].
+pub(crate) fn write_sample<T, W>(
+    writer: &mut W,
+    sample: T,
+    sample_format: HSampleFormat,
+) -> HResult<()>
+where
+    T: Float,
+    W: Write,
+{
+    // This should not panic since it is a conversion from f32 or f64.
+    let x = unsafe { sample.to_f64().unwrap_unchecked() };
+
+    match sample_format {
+        HSampleFormat::UInt8 => {
+            let x = quantize(x, i8::MIN as f64, i8::MAX as f64) as i16 + 128;
+            writer.write_all(&[x as u8])?;
+        }
+        HSampleFormat::Int16 => {
+            let x = quantize(x, i16::MIN as f64, i16::MAX as f64) as i16;
+            writer.write_all(&x.to_le_bytes())?;
+        }
+        HSampleFormat::Int24 => {
+            let x = quantize(x, -8_388_608., 8_388_607.) as i32;
+            writer.write_all(&x.to_le_bytes()[..3])?;
+        }
+        HSampleFormat::Int32 => {
+            let x = quantize(x, i32::MIN as f64, i32::MAX as f64) as i32;
+            writer.write_all(&x.to_le_bytes())?;
+        }
+        HSampleFormat::Float32 => writer.write_all(&(x as f32).to_le_bytes())?,
+        HSampleFormat::Float64 => writer.write_all(&x.to_le_bytes())?,
+    }
+
+    Ok(())
+}
+
+/// Returns an error if any sample lies outside the range of \[-1.0, 1.0\].
+pub(crate) fn check_range<T>(harray: &HArray<T, Ix2>) -> HResult<()>
+where
+    T: Float + FloatConst,
+{
+    match harray
+        .0
+        .iter()
+        .find(|x| !(-T::one()..=T::one()).contains(*x))
+    {
+        Some(x) => Err(HError::OutOfSpecError(format!(
+            "sample {} is out of the range [-1.0, 1.0]",
+            // This should not panic since it is a conversion from f32 or f64.
+            unsafe { x.to_f64().unwrap_unchecked() }
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Scales a sample in the range of \[-1.0, 1.0\] to the integer range \[min, max\], clipping it if needed.
+/// The scaling is the inverse of the one used by the decoder, so decoded integer samples are written back unchanged.
+fn quantize(x: f64, min: f64, max: f64) -> f64 {
+    if x.is_nan() {
+        return 0.;
+    }
+    (x * -min).round().clamp(min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{
+        decode, metadata_from_file, stream, HMetadata, HMetadataType, HTag, HVisualMetadata,
+    };
+    use ndarray::s;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("harmonium_{}_{}", std::process::id(), name))
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    macro_rules! write_wav_test {
+        ($input: expr, $sample_format: expr, $t: ty) => {
+            let (harray, sr) = decode::<$t>($input, None).unwrap();
+            let fpath = temp_path(&format!("{:?}_{}.wav", $sample_format, stringify!($t)));
+            write_wav(&fpath, &harray, sr, $sample_format, HOutOfRange::Error).unwrap();
+            let (harray_rt, sr_rt) = decode::<$t>(&fpath, None).unwrap();
+            std::fs::remove_file(&fpath).unwrap();
+            assert_eq!(sr, sr_rt);
+            assert_eq!(harray, harray_rt);
+        };
+    }
+
+    #[test]
+    fn write_wav_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.wav";
+        write_wav_test!(fpath, HSampleFormat::Int16, f32);
+        write_wav_test!(fpath, HSampleFormat::Int16, f64);
+        write_wav_test!(fpath, HSampleFormat::Int24, f32);
+        write_wav_test!(fpath, HSampleFormat::Int32, f64);
+        write_wav_test!(fpath, HSampleFormat::Float32, f32);
+        write_wav_test!(fpath, HSampleFormat::Float64, f64);
+
+        let fpath = "../testfiles/gs-16b-1c-44100hz.flac";
+        write_wav_test!(fpath, HSampleFormat::Int16, f32);
+        write_wav_test!(fpath, HSampleFormat::Int24, f64);
+
+        // 8-bit samples lose precision, so the round trip is checked on the quantized samples.
+        let (harray, sr) = decode::<f32>(fpath, None).unwrap();
+        let fpath_u8 = temp_path("UInt8.wav");
+        write_wav(
+            &fpath_u8,
+            &harray,
+            sr,
+            HSampleFormat::UInt8,
+            HOutOfRange::Error,
+        )
+        .unwrap();
+        let (harray_u8, _) = decode::<f32>(&fpath_u8, None).unwrap();
+        assert!(harray
+            .0
+            .iter()
+            .zip(harray_u8.0.iter())
+            .all(|(x, y)| (x - y).abs() <= 1. / 256.));
+        write_wav_test!(fpath_u8.as_str(), HSampleFormat::UInt8, f32);
+        std::fs::remove_file(&fpath_u8).unwrap();
+    }
+
+    #[test]
+    fn write_wav_out_of_range_test() {
+        let harray =
+            HArray::new_from_shape_vec((2, 3), vec![0.5, 1.5, -1.0, 1.0, -2.0, 0.]).unwrap();
+        let fpath = temp_path("out_of_range.wav");
+
+        assert!(write_wav(
+            &fpath,
+            &harray,
+            8000,
+            HSampleFormat::Int16,
+            HOutOfRange::Error
+        )
+        .is_err());
+
+        write_wav(
+            &fpath,
+            &harray,
+            8000,
+            HSampleFormat::Int16,
+            HOutOfRange::Clip,
+        )
+        .unwrap();
+        let (lhs, _) = decode::<f64>(&fpath, None).unwrap();
+        let rhs = HArray::new_from_shape_vec(
+            (2, 3),
+            vec![0.5, 32767. / 32768., -1.0, 32767. / 32768., -1.0, 0.],
+        )
+        .unwrap();
+        assert_eq!(lhs, rhs);
+
+        // Float formats store the samples as they are.
+        write_wav(
+            &fpath,
+            &harray,
+            8000,
+            HSampleFormat::Float32,
+            HOutOfRange::Error,
+        )
+        .unwrap();
+        let (lhs, _) = decode::<f64>(&fpath, None).unwrap();
+        assert_eq!(lhs, harray);
+
+        // An out-of-range sample leaves an existing file untouched.
+        let bytes = std::fs::read(&fpath).unwrap();
+        assert!(write_wav(
+            &fpath,
+            &harray,
+            8000,
+            HSampleFormat::Int16,
+            HOutOfRange::Error
+        )
+        .is_err());
+        assert_eq!(std::fs::read(&fpath).unwrap(), bytes);
+        std::fs::remove_file(&fpath).unwrap();
+    }
+
+    #[test]
+    fn stream_wav_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
+        let frames = 1000;
+        let (harray, sr) = decode::<f32>(fpath, None).unwrap();
+        let decoder = stream::<f32>(fpath, frames, None).unwrap();
+
+        let fpath_out = temp_path("stream.wav");
+        let mut encoder =
+            stream_wav(&fpath_out, 2, sr, HSampleFormat::Int16, HOutOfRange::Error).unwrap();
+        for harray_next in decoder {
+            encoder.write(&harray_next).unwrap();
+        }
+        let nframes = encoder.nframes();
+        assert_eq!(nframes, harray.nframes() / frames * frames);
+        encoder.finalize().unwrap();
+
+        let (lhs, _) = decode::<f32>(&fpath_out, None).unwrap();
+        let rhs = HArray(harray.0.slice(s![.., ..nframes]).to_shared());
+        assert_eq!(lhs, rhs);
+
+        // Wrong number of channels.
+        let mut encoder =
+            stream_wav(&fpath_out, 1, sr, HSampleFormat::Int16, HOutOfRange::Error).unwrap();
+        assert!(encoder.write(&harray).is_err());
+
+        // Dropping without finalizing still leaves a valid file.
+        let mut encoder =
+            stream_wav(&fpath_out, 2, sr, HSampleFormat::Int24, HOutOfRange::Error).unwrap();
+        let block = HArray(harray.0.slice(s![.., ..frames + 1]).to_shared());
+        encoder.write(&block).unwrap();
+        encoder.write(&block).unwrap();
+        drop(encoder);
+
+        let (lhs, _) = decode::<f32>(&fpath_out, None).unwrap();
+        assert_eq!(lhs.nframes(), 2 * (frames + 1));
+        assert_eq!(lhs.0.slice(s![.., frames + 1..]), block.0);
+
+        // Invalid parameters leave an existing file untouched.
+        let bytes = std::fs::read(&fpath_out).unwrap();
+        for (nchannels, sr) in [(0, sr), (70000, sr), (2, 0), (2, u32::MAX)] {
+            let res = stream_wav(
+                &fpath_out,
+                nchannels,
+                sr,
+                HSampleFormat::Int16,
+                HOutOfRange::Error,
+            );
+            assert!(res.is_err());
+        }
+        assert_eq!(std::fs::read(&fpath_out).unwrap(), bytes);
+        std::fs::remove_file(&fpath_out).unwrap();
+    }
+
+    #[cfg(feature = "flac-encode")]
+    #[test]
+    fn write_flac_test() {
+        use crate::decode::{verify_file, HVerifyDecode};
+
+        for (fpath, compression_level) in [
+            ("../testfiles/gs-16b-2c-44100hz.wav", 0),
+            ("../testfiles/gs-16b-2c-44100hz.wav", 8),
+            ("../testfiles/gs-16b-1c-44100hz.wav", 5),
+        ] {
+            let (harray, sr) = decode::<f32>(fpath, None).unwrap();
+            let fpath_out = temp_path(&format!("level_{}.flac", compression_level));
+            let options = HFlacOptions {
+                compression_level,
+                ..Default::default()
+            };
+            write_flac(&fpath_out, &harray, sr, &options, HOutOfRange::Error).unwrap();
+
+            let (harray_rt, sr_rt) = decode::<f32>(&fpath_out, None).unwrap();
+            assert_eq!(sr, sr_rt);
+            assert_eq!(harray, harray_rt);
+            assert_eq!(
+                verify_file(&fpath_out, None).unwrap(),
+                HVerifyDecode::Passed
+            );
+            assert!(
+                std::fs::metadata(&fpath_out).unwrap().len()
+                    < std::fs::metadata(fpath).unwrap().len()
+            );
+            std::fs::remove_file(&fpath_out).unwrap();
+        }
+
+        // 24-bit from 16-bit samples keeps the values.
+        let (harray, sr) = decode::<f64>("../testfiles/gs-16b-1c-44100hz.flac", None).unwrap();
+        let fpath_out = temp_path("24bit.flac");
+        let options = HFlacOptions {
+            bits_per_sample: 24,
+            ..Default::default()
+        };
+        write_flac(&fpath_out, &harray, sr, &options, HOutOfRange::Error).unwrap();
+        let (harray_rt, _) = decode::<f64>(&fpath_out, None).unwrap();
+        assert_eq!(harray, harray_rt);
+        assert_eq!(
+            verify_file(&fpath_out, None).unwrap(),
+            HVerifyDecode::Passed
+        );
+
+        // Without MD5 the file can't be verified.
+        let options = HFlacOptions {
+            md5: false,
+            ..Default::default()
+        };
+        write_flac(&fpath_out, &harray, sr, &options, HOutOfRange::Error).unwrap();
+        assert_eq!(
+            verify_file(&fpath_out, None).unwrap(),
+            HVerifyDecode::NotSupported
+        );
+        std::fs::remove_file(&fpath_out).unwrap();
+
+        // Invalid options.
+        let options = HFlacOptions {
+            bits_per_sample: 17,
+            ..Default::default()
+        };
+        assert!(write_flac(&fpath_out, &harray, sr, &options, HOutOfRange::Error).is_err());
+        let options = HFlacOptions {
+            compression_level: 9,
+            ..Default::default()
+        };
+        assert!(write_flac(&fpath_out, &harray, sr, &options, HOutOfRange::Error).is_err());
+    }
+
+    #[test]
+    fn write_metadata_test() {
+        let tag = |key: &str, std_key: &str, value: &str| HTag {
+            tag_key: key.into(),
+            tag_std_key: std_key.into(),
+            tag_value: value.into(),
+        };
+        let cover_art = HCoverArt {
+            media_type: "image/png".into(),
+            description: "cover".into(),
+            data: vec![0x89, b'P', b'N', b'G', 1, 2, 3, 4, 5, 6],
+        };
+
+        for (fpath, custom_key) in [
+            ("../testfiles/gs-16b-2c-44100hz.wav", "ISBJ"),
+            ("../testfiles/gs-16b-1c-44100hz.wav", "ISBJ"),
+            ("../testfiles/gs-16b-2c-44100hz.flac", "SOURCE"),
+            ("../testfiles/gs-16b-2c-44100hz.ogg", "SOURCE"),
+            ("../testfiles/gs-16b-2c-44100hz.mp3", "SOURCE"),
+        ] {
+            let ext = fpath.rsplit('.').next().unwrap();
+            let fpath_out = temp_path(&format!(
+                "write_metadata_{}",
+                fpath.rsplit('/').next().unwrap()
+            ));
+            std::fs::copy(fpath, &fpath_out).unwrap();
+
+            let tags = vec![
+                tag("title", "TrackTitle", "Processed"),
+                tag(custom_key, "None", "gs-16b-2c-44100hz.flac"),
+            ];
+            let cover_art = (ext != "wav").then_some(&cover_art);
+            write_metadata(&fpath_out, &HTextMetadata(Some(tags)), cover_art).unwrap();
+
+            let metadata = metadata_from_file(&fpath_out, HMetadataType::All).unwrap();
+            let Some(HMetadata::All((HTextMetadata(Some(text)), visual))) = metadata else {
+                panic!("no metadata in {}", fpath);
+            };
+            assert_eq!(text.len(), 2, "{}", fpath);
+            assert_eq!(text[0].tag_std_key, "TrackTitle");
+            assert_eq!(text[0].tag_value, "Processed");
+            assert!(text[1].tag_key.ends_with(custom_key));
+            assert_eq!(text[1].tag_value, "gs-16b-2c-44100hz.flac");
+            if let Some(cover_art) = cover_art {
+                let HVisualMetadata(Some(visual)) = visual else {
+                    panic!("no cover art in {}", fpath);
+                };
+                assert_eq!(visual.len(), 1);
+                assert_eq!(visual[0].media_type, "image/png");
+                assert_eq!(visual[0].data, cover_art.data);
+                assert_eq!(visual[0].dimensions, None);
+                assert_eq!(visual[0].bpp, None);
+            }
+
+            // The audio is left untouched.
+            let (lhs, _) = decode::<f32>(fpath, None).unwrap();
+            let (rhs, _) = decode::<f32>(&fpath_out, None).unwrap();
+            assert_eq!(lhs, rhs);
+
+            // Writing again replaces the tags and keeps the cover art.
+            write_metadata(
+                &fpath_out,
+                &HTextMetadata(Some(vec![tag("title", "TrackTitle", "Again")])),
+                None,
+            )
+            .unwrap();
+            let metadata = metadata_from_file(&fpath_out, HMetadataType::All).unwrap();
+            let Some(HMetadata::All((HTextMetadata(Some(text)), visual))) = metadata else {
+                panic!("no metadata in {}", fpath);
+            };
+            assert_eq!(text.len(), 1);
+            assert_eq!(text[0].tag_value, "Again");
+            assert_eq!(cover_art.is_some(), visual.0.is_some());
+
+            std::fs::remove_file(&fpath_out).unwrap();
+        }
+
+        // Keys that can't be stored as RIFF INFO ids are rejected.
+        let fpath_out = temp_path("write_metadata_invalid.wav");
+        std::fs::copy("../testfiles/gs-16b-2c-44100hz.wav", &fpath_out).unwrap();
+        let tags = HTextMetadata(Some(vec![tag("source", "None", "x")]));
+        assert!(write_metadata(&fpath_out, &tags, None).is_err());
+
+        // The INFO chunk placed after the data chunk is removed too, and the id3 chunk is rewritten with the new tags.
+        let tags = HTextMetadata(Some(vec![tag("title", "TrackTitle", "Processed")]));
+        write_metadata(&fpath_out, &tags, None).unwrap();
+        let bytes = std::fs::read(&fpath_out).unwrap();
+        let contains = |x: &[u8]| bytes.windows(x.len()).any(|w| w == x);
+        assert!(!contains(b"Galway"));
+        assert!(!contains(b"Kevin MacLeod"));
+        assert!(contains(b"id3 "));
+        assert!(contains(b"TIT2\0\0\0\x0a\0\0\x03Processed"));
+
+        // The cover art is stored in the id3 chunk, and kept when it is rewritten.
+        write_metadata(&fpath_out, &tags, Some(&cover_art)).unwrap();
+        write_metadata(&fpath_out, &tags, None).unwrap();
+        let bytes = std::fs::read(&fpath_out).unwrap();
+        let contains = |x: &[u8]| bytes.windows(x.len()).any(|w| w == x);
+        assert!(contains(b"APIC"));
+        assert!(contains(&cover_art.data));
+        let (lhs, _) = decode::<f32>("../testfiles/gs-16b-2c-44100hz.wav", None).unwrap();
+        let (rhs, _) = decode::<f32>(&fpath_out, None).unwrap();
+        assert_eq!(lhs, rhs);
+        std::fs::remove_file(&fpath_out).unwrap();
+
+        // An id3 chunk is added before the data chunk when there is none.
+        std::fs::copy("../testfiles/gs-16b-1c-44100hz.wav", &fpath_out).unwrap();
+        write_metadata(&fpath_out, &HTextMetadata(None), Some(&cover_art)).unwrap();
+        let bytes = std::fs::read(&fpath_out).unwrap();
+        let find = |x: &[u8]| bytes.windows(x.len()).position(|w| w == x);
+        assert!(find(b"id3 ").unwrap() < find(b"data").unwrap());
+        assert!(find(&cover_art.data).is_some());
+
+        // Several threads can tag the same file.
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| write_metadata(&fpath_out, &tags, None).unwrap());
+            }
+        });
+
+        // The permissions of the original file are kept.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let permissions = std::fs::Permissions::from_mode(0o640);
+            std::fs::set_permissions(&fpath_out, permissions).unwrap();
+            write_metadata(&fpath_out, &tags, None).unwrap();
+            let mode = std::fs::metadata(&fpath_out).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+        std::fs::remove_file(&fpath_out).unwrap();
+    }
+}
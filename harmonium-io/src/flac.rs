@@ -0,0 +1,791 @@
+use std::io::{Seek, SeekFrom, Write};
+
+use harmonium_core::errors::{HError, HResult};
+use symphonia::core::{checksum::Md5, io::Monitor};
+
+/// Encoder parameters derived from the compression level.
+struct FlacParams {
+    block_size: usize,
+    stereo_decorrelation: bool,
+    max_fixed_order: usize,
+    max_lpc_order: usize,
+    lpc_precision: u32,
+    max_partition_order: u32,
+}
+
+impl FlacParams {
+    fn from_level(compression_level: u8) -> HResult<Self> {
+        let (block_size, stereo_decorrelation, max_lpc_order, max_partition_order) =
+            match compression_level {
+                0 => (1152, false, 0, 3),
+                1 | 2 => (1152, true, 0, 3),
+                3 => (4096, true, 6, 4),
+                4 | 5 => (4096, true, 8, 5),
+                6..=8 => (4096, true, 12, 6),
+                _ => {
+                    return Err(HError::OutOfSpecError(
+                        "the compression level must be between 0 and 8".into(),
+                    ))
+                }
+            };
+        let lpc_precision = if block_size <= 1152 { 10 } else { 12 };
+
+        Ok(FlacParams {
+            block_size,
+            stereo_decorrelation,
+            max_fixed_order: 4,
+            max_lpc_order,
+            lpc_precision,
+            max_partition_order,
+        })
+    }
+}
+
+/// A FLAC encoder writing a single STREAMINFO metadata block followed by fixed-blocksize frames.
+/// The samples are passed as integers, channel by channel, already quantized to `bits_per_sample` bits.
+pub(crate) struct FlacEncoder {
+    params: FlacParams,
+    sr: u32,
+    bits_per_sample: u32,
+    md5: Option<Md5>,
+}
+
+impl FlacEncoder {
+    pub(crate) fn try_new(
+        nchannels: usize,
+        sr: u32,
+        bits_per_sample: u32,
+        compression_level: u8,
+        md5: bool,
+    ) -> HResult<Self> {
+        if !(1..=8).contains(&nchannels) {
+            return Err(HError::OutOfSpecError(
+                "FLAC supports between 1 and 8 channels".into(),
+            ));
+        }
+        if ![8, 12, 16, 20, 24].contains(&bits_per_sample) {
+            return Err(HError::OutOfSpecError(
+                "the FLAC bit depth must be one of 8, 12, 16, 20 or 24".into(),
+            ));
+        }
+        if sr == 0 || sr >= 1 << 20 {
+            return Err(HError::Unsupported {
+                what: "the sampling rate is not supported by FLAC".into(),
+            });
+        }
+
+        Ok(FlacEncoder {
+            params: FlacParams::from_level(compression_level)?,
+            sr,
+            bits_per_sample,
+            md5: md5.then(Md5::default),
+        })
+    }
+
+    /// Encodes the whole signal and writes the file. `channels` holds one vector of samples per channel.
+    pub(crate) fn encode<W: Write + Seek>(
+        mut self,
+        writer: &mut W,
+        channels: &[Vec<i32>],
+    ) -> HResult<()> {
+        let nchannels = channels.len();
+        let nframes = channels.first().map_or(0, |x| x.len());
+
+        if let Some(md5) = &mut self.md5 {
+            update_md5(md5, channels, self.bits_per_sample);
+        }
+
+        writer.write_all(b"fLaC")?;
+        let streaminfo_pos = writer.stream_position()?;
+        // Written again at the end, when the frame sizes are known.
+        self.write_streaminfo(writer, nchannels, nframes as u64, 0, 0)?;
+
+        let mut min_frame_size = u32::MAX;
+        let mut max_frame_size = 0;
+
+        for (frame_idx, start) in (0..nframes).step_by(self.params.block_size).enumerate() {
+            let end = (start + self.params.block_size).min(nframes);
+            let block: Vec<&[i32]> = channels.iter().map(|x| &x[start..end]).collect();
+            let frame = self.encode_frame(frame_idx as u64, &block);
+            min_frame_size = min_frame_size.min(frame.len() as u32);
+            max_frame_size = max_frame_size.max(frame.len() as u32);
+            writer.write_all(&frame)?;
+        }
+
+        if nframes == 0 {
+            min_frame_size = 0;
+        }
+
+        let end_pos = writer.stream_position()?;
+        writer.seek(SeekFrom::Start(streaminfo_pos))?;
+        self.write_streaminfo(
+            writer,
+            nchannels,
+            nframes as u64,
+            min_frame_size,
+            max_frame_size,
+        )?;
+        writer.seek(SeekFrom::Start(end_pos))?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    fn write_streaminfo<W: Write>(
+        &self,
+        writer: &mut W,
+        nchannels: usize,
+        nframes: u64,
+        min_frame_size: u32,
+        max_frame_size: u32,
+    ) -> HResult<()> {
+        if nframes >= 1 << 36 {
+            return Err(HError::OutOfSpecError(
+                "the data is too large for a FLAC file".into(),
+            ));
+        }
+
+        let block_size = (self.params.block_size as u64).min(nframes.max(16));
+        let md5 = self.md5.as_ref().map_or([0; 16], |x| x.md5());
+
+        let mut bw = BitWriter::new();
+        // Metadata block header: last block flag, block type 0 (STREAMINFO) and length.
+        bw.write(1, 1);
+        bw.write(7, 0);
+        bw.write(24, 34);
+        // Minimum and maximum block sizes.
+        bw.write(16, block_size);
+        bw.write(16, block_size);
+        bw.write(24, min_frame_size as u64);
+        bw.write(24, max_frame_size as u64);
+        bw.write(20, self.sr as u64);
+        bw.write(3, nchannels as u64 - 1);
+        bw.write(5, self.bits_per_sample as u64 - 1);
+        bw.write(36, nframes);
+        for byte in md5 {
+            bw.write(8, byte as u64);
+        }
+
+        writer.write_all(&bw.into_bytes())?;
+
+        Ok(())
+    }
+
+    fn encode_frame(&self, frame_idx: u64, block: &[&[i32]]) -> Vec<u8> {
+        let bps = self.bits_per_sample;
+        let nframes = block[0].len();
+        let channels: Vec<Vec<i64>> = block
+            .iter()
+            .map(|x| x.iter().map(|x| *x as i64).collect())
+            .collect();
+
+        let side: Vec<i64>;
+        let mid: Vec<i64>;
+
+        // Pick the channel assignment and encode the subframes.
+        let (assignment, subframes) = if block.len() == 2 && self.params.stereo_decorrelation {
+            let (left, right) = (&channels[0], &channels[1]);
+            side = left.iter().zip(right).map(|(l, r)| l - r).collect();
+            mid = left.iter().zip(right).map(|(l, r)| (l + r) >> 1).collect();
+
+            let left = encode_subframe(left, bps, &self.params);
+            let right = encode_subframe(right, bps, &self.params);
+            let side = encode_subframe(&side, bps + 1, &self.params);
+            let mid = encode_subframe(&mid, bps, &self.params);
+
+            let candidates = [
+                (left.bits + right.bits, 0b0001),
+                (left.bits + side.bits, 0b1000),
+                (side.bits + right.bits, 0b1001),
+                (mid.bits + side.bits, 0b1010),
+            ];
+            // Ok to unwrap. The array is not empty.
+            let (_, assignment) = candidates.into_iter().min_by_key(|x| x.0).unwrap();
+
+            let subframes = match assignment {
+                0b0001 => vec![left, right],
+                0b1000 => vec![left, side],
+                0b1001 => vec![side, right],
+                _ => vec![mid, side],
+            };
+            (assignment, subframes)
+        } else {
+            let subframes = channels
+                .iter()
+                .map(|x| encode_subframe(x, bps, &self.params))
+                .collect();
+            (block.len() as u64 - 1, subframes)
+        };
+
+        let mut bw = BitWriter::new();
+
+        // Frame header.
+        let (block_size_code, block_size_extra) = match nframes {
+            192 => (1, None),
+            576 | 1152 | 2304 | 4608 => (2 + (nframes / 576).trailing_zeros() as u64, None),
+            256 | 512 | 1024 | 2048 | 4096 | 8192 | 16384 | 32768 => {
+                (8 + (nframes / 256).trailing_zeros() as u64, None)
+            }
+            1..=256 => (6, Some((8, nframes as u64 - 1))),
+            _ => (7, Some((16, nframes as u64 - 1))),
+        };
+        let sample_rate_code = match self.sr {
+            88200 => 1,
+            176400 => 2,
+            192000 => 3,
+            8000 => 4,
+            16000 => 5,
+            22050 => 6,
+            24000 => 7,
+            32000 => 8,
+            44100 => 9,
+            48000 => 10,
+            96000 => 11,
+            // Get the sampling rate from STREAMINFO.
+            _ => 0,
+        };
+        let sample_size_code = match bps {
+            8 => 1,
+            12 => 2,
+            16 => 4,
+            20 => 5,
+            _ => 6,
+        };
+
+        // Sync code, reserved bit and fixed blocksize strategy.
+        bw.write(14, 0b11_1111_1111_1110);
+        bw.write(1, 0);
+        bw.write(1, 0);
+        bw.write(4, block_size_code);
+        bw.write(4, sample_rate_code);
+        bw.write(4, assignment);
+        bw.write(3, sample_size_code);
+        bw.write(1, 0);
+        write_utf8(&mut bw, frame_idx);
+        if let Some((nbits, value)) = block_size_extra {
+            bw.write(nbits, value);
+        }
+        let crc = crc8(bw.bytes());
+        bw.write(8, crc as u64);
+
+        // Subframes. Side channels need an extra bit.
+        for (i, subframe) in subframes.iter().enumerate() {
+            let is_side = matches!((assignment, i), (0b1000, 1) | (0b1001, 0) | (0b1010, 1));
+            write_subframe(&mut bw, subframe, if is_side { bps + 1 } else { bps });
+        }
+
+        // Frame footer.
+        bw.align();
+        let crc = crc16(bw.bytes());
+        bw.write(16, crc as u64);
+
+        bw.into_bytes()
+    }
+}
+
+/// Feeds the samples to the MD5 state the same way the decoder does, interleaved and in little-endian byte order.
+fn update_md5(md5: &mut Md5, channels: &[Vec<i32>], bits_per_sample: u32) {
+    let bytes_per_sample = bits_per_sample.div_ceil(8) as usize;
+    let nframes = channels.first().map_or(0, |x| x.len());
+    let mut buf = Vec::with_capacity(channels.len() * bytes_per_sample * 4096);
+
+    for start in (0..nframes).step_by(4096) {
+        buf.clear();
+        for i in start..(start + 4096).min(nframes) {
+            for channel in channels {
+                buf.extend_from_slice(&channel[i].to_le_bytes()[..bytes_per_sample]);
+            }
+        }
+        md5.process_buf_bytes(&buf);
+    }
+}
+
+enum Predictor {
+    Constant,
+    Verbatim,
+    Fixed(usize),
+    Lpc {
+        coefs: Vec<i32>,
+        precision: u32,
+        shift: u32,
+    },
+}
+
+struct Rice {
+    partition_order: u32,
+    params: Vec<u32>,
+}
+
+struct Subframe<'a> {
+    samples: &'a [i64],
+    predictor: Predictor,
+    residual: Vec<i64>,
+    rice: Option<Rice>,
+    // Estimated size of the subframe in bits.
+    bits: u64,
+}
+
+fn encode_subframe<'a>(samples: &'a [i64], bps: u32, params: &FlacParams) -> Subframe<'a> {
+    let nframes = samples.len();
+    let header_bits = 8;
+
+    if samples.iter().all(|x| *x == samples[0]) {
+        return Subframe {
+            samples,
+            predictor: Predictor::Constant,
+            residual: Vec::new(),
+            rice: None,
+            bits: header_bits + bps as u64,
+        };
+    }
+
+    let mut best = Subframe {
+        samples,
+        predictor: Predictor::Verbatim,
+        residual: Vec::new(),
+        rice: None,
+        bits: header_bits + (nframes as u64) * bps as u64,
+    };
+
+    for order in 0..=params.max_fixed_order.min(nframes - 1) {
+        let residual = fixed_residual(samples, order);
+        if let Some((rice, rice_bits)) =
+            best_rice(&residual, nframes, order, params.max_partition_order)
+        {
+            let bits = header_bits + (order as u64) * bps as u64 + rice_bits;
+            if bits < best.bits {
+                best = Subframe {
+                    samples,
+                    predictor: Predictor::Fixed(order),
+                    residual,
+                    rice: Some(rice),
+                    bits,
+                };
+            }
+        }
+    }
+
+    let max_lpc_order = params.max_lpc_order.min(nframes - 1);
+    if max_lpc_order > 0 {
+        for coefs in lpc_coefs(samples, max_lpc_order) {
+            let order = coefs.len();
+            let Some((qcoefs, shift)) = quantize_coefs(&coefs, params.lpc_precision) else {
+                continue;
+            };
+            let residual = lpc_residual(samples, &qcoefs, shift);
+            // The decoder stores the residual as 32-bit integers.
+            if residual.iter().any(|x| i32::try_from(*x).is_err()) {
+                continue;
+            }
+            if let Some((rice, rice_bits)) =
+                best_rice(&residual, nframes, order, params.max_partition_order)
+            {
+                let bits = header_bits
+                    + (order as u64) * (bps + params.lpc_precision) as u64
+                    + 4
+                    + 5
+                    + rice_bits;
+                if bits < best.bits {
+                    best = Subframe {
+                        samples,
+                        predictor: Predictor::Lpc {
+                            coefs: qcoefs,
+                            precision: params.lpc_precision,
+                            shift,
+                        },
+                        residual,
+                        rice: Some(rice),
+                        bits,
+                    };
+                }
+            }
+        }
+    }
+
+    best
+}
+
+fn write_subframe(bw: &mut BitWriter, subframe: &Subframe, bps: u32) {
+    // Zero padding bit, subframe type and no wasted bits.
+    let subframe_type = match &subframe.predictor {
+        Predictor::Constant => 0,
+        Predictor::Verbatim => 1,
+        Predictor::Fixed(order) => 0b1000 | *order as u64,
+        Predictor::Lpc { coefs, .. } => 0b10_0000 | (coefs.len() as u64 - 1),
+    };
+    bw.write(1, 0);
+    bw.write(6, subframe_type);
+    bw.write(1, 0);
+
+    match &subframe.predictor {
+        Predictor::Constant => bw.write_signed(bps, subframe.samples[0]),
+        Predictor::Verbatim => {
+            for x in subframe.samples {
+                bw.write_signed(bps, *x);
+            }
+        }
+        Predictor::Fixed(order) => {
+            for x in &subframe.samples[..*order] {
+                bw.write_signed(bps, *x);
+            }
+        }
+        Predictor::Lpc {
+            coefs,
+            precision,
+            shift,
+        } => {
+            for x in &subframe.samples[..coefs.len()] {
+                bw.write_signed(bps, *x);
+            }
+            bw.write(4, *precision as u64 - 1);
+            bw.write(5, *shift as u64);
+            for c in coefs {
+                bw.write_signed(*precision, *c as i64);
+            }
+        }
+    }
+
+    if let Some(rice) = &subframe.rice {
+        write_residual(bw, &subframe.residual, subframe.samples.len(), rice);
+    }
+}
+
+fn write_residual(bw: &mut BitWriter, residual: &[i64], nframes: usize, rice: &Rice) {
+    // Rice2 is needed when any parameter does not fit in 4 bits.
+    let (method, param_bits) = if rice.params.iter().any(|x| *x >= 15) {
+        (1, 5)
+    } else {
+        (0, 4)
+    };
+    bw.write(2, method);
+    bw.write(4, rice.partition_order as u64);
+
+    let order = nframes - residual.len();
+    let partition_len = nframes >> rice.partition_order;
+    let mut start = 0;
+
+    for (i, k) in rice.params.iter().enumerate() {
+        let len = if i == 0 {
+            partition_len - order
+        } else {
+            partition_len
+        };
+        bw.write(param_bits, *k as u64);
+        for x in &residual[start..start + len] {
+            bw.write_rice(*k, zigzag(*x));
+        }
+        start += len;
+    }
+}
+
+fn zigzag(x: i64) -> u64 {
+    ((x << 1) ^ (x >> 63)) as u64
+}
+
+fn fixed_residual(samples: &[i64], order: usize) -> Vec<i64> {
+    let s = samples;
+    (order..s.len())
+        .map(|i| match order {
+            0 => s[i],
+            1 => s[i] - s[i - 1],
+            2 => s[i] - 2 * s[i - 1] + s[i - 2],
+            3 => s[i] - 3 * s[i - 1] + 3 * s[i - 2] - s[i - 3],
+            _ => s[i] - 4 * s[i - 1] + 6 * s[i - 2] - 4 * s[i - 3] + s[i - 4],
+        })
+        .collect()
+}
+
+fn lpc_residual(samples: &[i64], coefs: &[i32], shift: u32) -> Vec<i64> {
+    let order = coefs.len();
+    (order..samples.len())
+        .map(|i| {
+            let prediction: i64 = coefs
+                .iter()
+                .zip(samples[i - order..i].iter().rev())
+                .map(|(c, x)| *c as i64 * x)
+                .sum();
+            samples[i] - (prediction >> shift)
+        })
+        .collect()
+}
+
+/// Computes the linear predictor coefficients for every order up to `max_order`, using the autocorrelation of the
+/// signal windowed by a Tukey(0.5) window and the Levinson-Durbin recursion.
+fn lpc_coefs(samples: &[i64], max_order: usize) -> Vec<Vec<f64>> {
+    let n = samples.len();
+    let taper = (n / 4).max(1);
+    let windowed: Vec<f64> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let edge = i.min(n - 1 - i);
+            let w = if edge < taper {
+                0.5 - 0.5 * (std::f64::consts::PI * edge as f64 / taper as f64).cos()
+            } else {
+                1.0
+            };
+            *x as f64 * w
+        })
+        .collect();
+
+    let autoc: Vec<f64> = (0..=max_order)
+        .map(|lag| {
+            windowed[lag..]
+                .iter()
+                .zip(&windowed)
+                .map(|(a, b)| a * b)
+                .sum()
+        })
+        .collect();
+
+    let mut coefs_vec = Vec::with_capacity(max_order);
+    if autoc[0] == 0. {
+        return coefs_vec;
+    }
+
+    let mut err = autoc[0];
+    let mut coefs: Vec<f64> = Vec::with_capacity(max_order);
+
+    for i in 0..max_order {
+        let acc = autoc[i + 1]
+            - coefs
+                .iter()
+                .enumerate()
+                .map(|(j, c)| c * autoc[i - j])
+                .sum::<f64>();
+        let k = acc / err;
+        let prev = coefs.clone();
+        for (j, c) in coefs.iter_mut().enumerate() {
+            *c -= k * prev[i - 1 - j];
+        }
+        coefs.push(k);
+        err *= 1. - k * k;
+        coefs_vec.push(coefs.clone());
+        if err <= 0. {
+            break;
+        }
+    }
+
+    coefs_vec
+}
+
+/// Quantizes the coefficients to `precision` bits, returning them with the shift to be applied to the prediction.
+fn quantize_coefs(coefs: &[f64], precision: u32) -> Option<(Vec<i32>, u32)> {
+    let qmax = (1_i32 << (precision - 1)) - 1;
+    let qmin = -qmax - 1;
+    let cmax = coefs.iter().fold(0_f64, |acc, x| acc.max(x.abs()));
+    if cmax <= 0. || !cmax.is_finite() {
+        return None;
+    }
+
+    let shift = precision as i32 - 2 - cmax.log2().floor() as i32;
+    // Negative shifts are not supported by most decoders.
+    if shift < 0 {
+        return None;
+    }
+    let shift = shift.min(15) as u32;
+
+    let scale = (1_u32 << shift) as f64;
+    let mut error = 0.;
+    let qcoefs = coefs
+        .iter()
+        .map(|c| {
+            error += c * scale;
+            let q = (error.round() as i32).clamp(qmin, qmax);
+            error -= q as f64;
+            q
+        })
+        .collect();
+
+    Some((qcoefs, shift))
+}
+
+/// Finds the partition order and Rice parameters minimizing the estimated size of the residual.
+/// Returns `None` if there is no valid partitioning.
+fn best_rice(
+    residual: &[i64],
+    nframes: usize,
+    order: usize,
+    max_partition_order: u32,
+) -> Option<(Rice, u64)> {
+    // The partitions must divide the block evenly and the first one must hold at least the warm-up samples.
+    let max_partition_order = (0..=max_partition_order)
+        .rev()
+        .find(|p| nframes.is_multiple_of(1 << p) && (nframes >> p) >= order.max(1))?;
+
+    let partition_len = nframes >> max_partition_order;
+    let mut sums: Vec<(u64, u64)> = Vec::with_capacity(1 << max_partition_order);
+    let mut start = 0;
+    for i in 0..(1_usize << max_partition_order) {
+        let len = if i == 0 {
+            partition_len - order
+        } else {
+            partition_len
+        };
+        let sum = residual[start..start + len]
+            .iter()
+            .map(|x| zigzag(*x))
+            .sum();
+        sums.push((sum, len as u64));
+        start += len;
+    }
+
+    let mut best: Option<(Rice, u64)> = None;
+    let mut partition_order = max_partition_order;
+
+    loop {
+        let params: Vec<(u32, u64)> = sums
+            .iter()
+            .map(|(sum, len)| rice_param(*sum, *len))
+            .collect();
+        let param_bits = if params.iter().any(|x| x.0 >= 15) {
+            5
+        } else {
+            4
+        };
+        let bits = 6 + params.iter().map(|x| x.1 + param_bits).sum::<u64>();
+
+        if best.as_ref().is_none_or(|x| bits < x.1) {
+            let rice = Rice {
+                partition_order,
+                params: params.into_iter().map(|x| x.0).collect(),
+            };
+            best = Some((rice, bits));
+        }
+
+        if partition_order == 0 {
+            break;
+        }
+        partition_order -= 1;
+        sums = sums
+            .chunks(2)
+            .map(|x| (x[0].0 + x[1].0, x[0].1 + x[1].1))
+            .collect();
+    }
+
+    best
+}
+
+/// Estimates the best Rice parameter for a partition given the sum of its zigzag encoded residuals.
+fn rice_param(sum: u64, len: u64) -> (u32, u64) {
+    (0..=30)
+        .map(|k| (k, len * (k as u64 + 1) + (sum >> k)))
+        .min_by_key(|x| x.1)
+        // Ok to unwrap. The range is not empty.
+        .unwrap()
+}
+
+/// Writes the frame number using the UTF-8 like coding from the FLAC specification.
+fn write_utf8(bw: &mut BitWriter, value: u64) {
+    if value < 0x80 {
+        bw.write(8, value);
+        return;
+    }
+    let nbytes = match value {
+        0..=0x7ff => 2,
+        0x800..=0xffff => 3,
+        0x1_0000..=0x1f_ffff => 4,
+        0x20_0000..=0x3ff_ffff => 5,
+        0x400_0000..=0x7fff_ffff => 6,
+        _ => 7,
+    };
+    let lead = (0xff00_u64 >> nbytes) & 0xff;
+    bw.write(8, lead | (value >> (6 * (nbytes - 1))));
+    for i in (0..nbytes - 1).rev() {
+        bw.write(8, 0x80 | ((value >> (6 * i)) & 0x3f));
+    }
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0_u8, |mut crc, byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0_u16, |mut crc, byte| {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// Writes bits in big-endian order.
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    nbits: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter {
+            bytes: Vec::new(),
+            acc: 0,
+            nbits: 0,
+        }
+    }
+
+    /// Writes the `nbits` lowest bits of `value`. `nbits` must not exceed 56.
+    fn write(&mut self, nbits: u32, value: u64) {
+        if nbits == 0 {
+            return;
+        }
+        self.acc = (self.acc << nbits) | (value & ((1 << nbits) - 1));
+        self.nbits += nbits;
+        while self.nbits >= 8 {
+            self.nbits -= 8;
+            self.bytes.push((self.acc >> self.nbits) as u8);
+        }
+        self.acc &= (1 << self.nbits) - 1;
+    }
+
+    /// Writes a value in two's complement using `nbits` bits.
+    fn write_signed(&mut self, nbits: u32, value: i64) {
+        self.write(nbits, value as u64);
+    }
+
+    fn write_unary(&mut self, mut zeros: u64) {
+        while zeros >= 32 {
+            self.write(32, 0);
+            zeros -= 32;
+        }
+        self.write(zeros as u32 + 1, 1);
+    }
+
+    fn write_rice(&mut self, k: u32, value: u64) {
+        self.write_unary(value >> k);
+        self.write(k, value);
+    }
+
+    /// Pads with zeros up to the next byte boundary.
+    fn align(&mut self) {
+        if self.nbits > 0 {
+            self.write(8 - self.nbits, 0);
+        }
+    }
+
+    /// The complete bytes written so far.
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn into_bytes(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
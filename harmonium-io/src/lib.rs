@@ -1,2 +1,11 @@
+mod bwf;
 pub mod decode;
+pub mod encode;
+#[cfg(feature = "flac-encode")]
+mod flac;
+pub mod load;
+mod markers;
+pub mod mix;
 pub mod play;
+pub mod raw;
+mod tags;
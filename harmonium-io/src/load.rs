@@ -0,0 +1,326 @@
+use harmonium_core::{array::HArray, errors::HResult};
+use ndarray::Ix2;
+use num_traits::{Float, FloatConst};
+use rubato::{FftFixedIn, Resampler, Sample};
+use symphonia::core::conv::ConvertibleSample;
+
+use crate::decode::{decode_range_with, HInput, HTime};
+
+/// Number of input frames given to the resampler at a time.
+const CHUNK_SIZE: usize = 1024;
+
+/// Options for `load`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HLoadOptions {
+    /// Target sampling rate. If `None`, the native sampling rate is kept.
+    pub sr: Option<u32>,
+    /// Whether to average the channels into a single one.
+    pub mono: bool,
+    /// Start of the audio to load, in seconds or frames at the native sampling rate.
+    pub offset: HTime,
+    /// Length of the audio to load, in seconds or frames at the native sampling rate. If `None`, loads until the end
+    /// of the file.
+    pub duration: Option<HTime>,
+    /// The track to load. If `None`, the default track is used.
+    pub track_id: Option<u32>,
+}
+
+impl Default for HLoadOptions {
+    /// Loads the whole default track as mono, at the native sampling rate.
+    fn default() -> Self {
+        HLoadOptions {
+            sr: None,
+            mono: true,
+            offset: HTime::Frames(0),
+            duration: None,
+            track_id: None,
+        }
+    }
+}
+
+/// Load an audio file as an HArray, optionally downmixed to mono and resampled to a target sampling rate.
+/// The file is decoded, downmixed and resampled in a single streaming pass, so only the output is kept in memory.
+/// The output is the same as calling `decode_range`, `AudioOp::to_mono` and resampling, apart from the resampler,
+/// which is an FFT resampler whose delay is compensated. The number of output frames is
+/// `ceil(nframes * sr / native_sr)`.
+/// The samples are normalized to fit in the range of \[-1.0, 1.0\].
+///
+/// # Arguments
+///
+/// * `fpath` - The input file.
+/// * `options` - The `HLoadOptions`.
+///
+/// # Returns
+///
+/// The loaded audio, with shape (nchannels, nframes), and its sampling rate.
+///
+/// # Examples
+///
+/// ```
+/// //let options = HLoadOptions { sr: Some(22050), ..Default::default() };
+/// //load::<f32>("../testfiles/gs-16b-2c-44100hz.wav", options)
+/// ```
+pub fn load<T>(fpath: &str, options: HLoadOptions) -> HResult<(HArray<T, Ix2>, u32)>
+where
+    T: Float + FloatConst + ConvertibleSample + Sample,
+{
+    load_from_input(HInput::from_file(fpath)?, options)
+}
+
+/// Load an audio input as an HArray. See `load` for the details.
+pub fn load_from_input<T>(input: HInput, options: HLoadOptions) -> HResult<(HArray<T, Ix2>, u32)>
+where
+    T: Float + FloatConst + ConvertibleSample + Sample,
+{
+    let loader = decode_range_with(
+        input,
+        options.offset,
+        options.duration,
+        options.track_id,
+        |channels, sr, nframes| Loader::new(channels, sr, nframes, &options),
+        |loader, samples| loader.push(samples),
+    )?;
+
+    loader.finish()
+}
+
+/// Downmixes and resamples the decoded samples as they come.
+pub(crate) struct Loader<T>
+where
+    T: Sample,
+{
+    channels: usize,
+    mono: bool,
+    sr_in: u32,
+    sr_out: u32,
+    resampler: Option<FftFixedIn<T>>,
+    /// Frames waiting to be resampled, one `Vec` per output channel.
+    input: Vec<Vec<T>>,
+    /// Scratch output of the resampler.
+    buffer: Vec<Vec<T>>,
+    output: Vec<Vec<T>>,
+    /// Number of frames read from the file.
+    nframes_in: u64,
+    /// Number of frames still to be discarded to compensate the resampler delay.
+    skip: usize,
+}
+
+impl<T> Loader<T>
+where
+    T: Float + FloatConst + Sample,
+{
+    pub(crate) fn new(
+        channels: usize,
+        sr: u32,
+        nframes: Option<u64>,
+        options: &HLoadOptions,
+    ) -> HResult<Self> {
+        let channels_out = if options.mono { 1 } else { channels };
+        let sr_out = options.sr.unwrap_or(sr);
+
+        let resampler = if sr_out != sr {
+            Some(FftFixedIn::<T>::new(
+                sr as usize,
+                sr_out as usize,
+                CHUNK_SIZE,
+                2,
+                channels_out,
+            )?)
+        } else {
+            None
+        };
+        let (buffer, skip) = match &resampler {
+            Some(resampler) => (
+                vec![vec![T::zero(); resampler.output_frames_max()]; channels_out],
+                resampler.output_delay(),
+            ),
+            None => (Vec::new(), 0),
+        };
+
+        // Reserve the expected output size, up to a limit in case the number of frames is wrong.
+        let capacity = nframes
+            .map_or(0, |x| output_nframes(x, sr, sr_out) as usize)
+            .min(1 << 24);
+
+        Ok(Loader {
+            channels,
+            mono: options.mono,
+            sr_in: sr,
+            sr_out,
+            resampler,
+            input: vec![Vec::with_capacity(2 * CHUNK_SIZE); channels_out],
+            buffer,
+            output: vec![Vec::with_capacity(capacity); channels_out],
+            nframes_in: 0,
+            skip,
+        })
+    }
+
+    /// Adds interleaved samples.
+    pub(crate) fn push(&mut self, samples: &[T]) -> HResult<()> {
+        let target = if self.resampler.is_some() {
+            &mut self.input
+        } else {
+            &mut self.output
+        };
+
+        if self.mono {
+            let scale = T::one() / T::from(self.channels).unwrap();
+            target[0].extend(
+                samples
+                    .chunks_exact(self.channels)
+                    .map(|frame| frame.iter().fold(T::zero(), |acc, x| acc + *x) * scale),
+            );
+        } else {
+            for (channel, target) in target.iter_mut().enumerate() {
+                target.extend(samples.iter().skip(channel).step_by(self.channels));
+            }
+        }
+        self.nframes_in += (samples.len() / self.channels) as u64;
+
+        self.resample(false)
+    }
+
+    /// Resamples the pending frames. When flushing, the last partial chunk is resampled as well.
+    fn resample(&mut self, flush: bool) -> HResult<()> {
+        let Some(resampler) = &mut self.resampler else {
+            return Ok(());
+        };
+
+        loop {
+            let n = resampler.input_frames_next();
+            let pending = self.input[0].len();
+            let (nbr_in, nbr_out) = if pending >= n {
+                resampler.process_into_buffer(&self.input, &mut self.buffer, None)?
+            } else if flush && pending > 0 {
+                resampler.process_partial_into_buffer(Some(&self.input), &mut self.buffer, None)?
+            } else {
+                break;
+            };
+
+            for ((input, buffer), output) in self
+                .input
+                .iter_mut()
+                .zip(self.buffer.iter())
+                .zip(self.output.iter_mut())
+            {
+                // A partial chunk is padded with zeros.
+                input.drain(..nbr_in.min(input.len()));
+                output.extend_from_slice(&buffer[self.skip.min(nbr_out)..nbr_out]);
+            }
+            self.skip = self.skip.saturating_sub(nbr_out);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> HResult<(HArray<T, Ix2>, u32)> {
+        let nframes = output_nframes(self.nframes_in, self.sr_in, self.sr_out) as usize;
+
+        if self.resampler.is_some() {
+            self.resample(true)?;
+            // Push zeros through the resampler until the delayed frames come out.
+            while self.output[0].len() < nframes {
+                let resampler = self.resampler.as_mut().unwrap();
+                let (_, nbr_out) = resampler.process_partial_into_buffer(
+                    None::<&[Vec<T>]>,
+                    &mut self.buffer,
+                    None,
+                )?;
+                for (buffer, output) in self.buffer.iter().zip(self.output.iter_mut()) {
+                    output.extend_from_slice(&buffer[self.skip.min(nbr_out)..nbr_out]);
+                }
+                self.skip = self.skip.saturating_sub(nbr_out);
+            }
+        }
+
+        let channels = self.output.len();
+        let mut samples = Vec::with_capacity(channels * nframes);
+        for mut channel in self.output {
+            channel.truncate(nframes);
+            samples.append(&mut channel);
+        }
+        let harray = HArray::new_from_shape_vec((channels, nframes), samples)?;
+
+        Ok((harray, self.sr_out))
+    }
+}
+
+/// Number of frames after resampling `nframes` frames.
+fn output_nframes(nframes: u64, sr_in: u32, sr_out: u32) -> u64 {
+    (nframes as u128 * sr_out as u128).div_ceil(sr_in as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::decode_range;
+    use harmonium_core::audioop::AudioOp;
+
+    #[test]
+    fn load_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
+        let offset = HTime::Seconds(1.);
+        let duration = Some(HTime::Seconds(2.));
+        let (decoded, _) = decode_range::<f32>(fpath, offset, duration, None).unwrap();
+
+        // Native sampling rate.
+        let options = HLoadOptions {
+            mono: false,
+            offset,
+            duration,
+            ..Default::default()
+        };
+        let (loaded, sr) = load::<f32>(fpath, options).unwrap();
+        assert_eq!(sr, 44100);
+        assert_eq!(loaded, decoded);
+
+        let options = HLoadOptions {
+            offset,
+            duration,
+            ..Default::default()
+        };
+        let (loaded, sr) = load::<f32>(fpath, options).unwrap();
+        let mono = decoded.to_mono().unwrap();
+        assert_eq!(sr, 44100);
+        assert_eq!(loaded.shape(), &[1, 88200]);
+        assert!(loaded
+            .0
+            .iter()
+            .zip(mono.0.iter())
+            .all(|(x, y)| (x - y).abs() < 1e-6));
+
+        // Resampled.
+        let options = HLoadOptions {
+            sr: Some(22050),
+            mono: false,
+            offset,
+            duration,
+            ..Default::default()
+        };
+        let (loaded, sr) = load::<f64>(fpath, options).unwrap();
+        assert_eq!(sr, 22050);
+        assert_eq!(loaded.shape(), &[2, 44100]);
+        // A 2x downsampling keeps the low frequencies, and the delay is compensated.
+        let (decoded, _) = decode_range::<f64>(fpath, offset, duration, None).unwrap();
+        let error = loaded
+            .0
+            .iter()
+            .zip(decoded.0.iter().step_by(2))
+            .map(|(x, y)| (x - y).powi(2))
+            .sum::<f64>()
+            / decoded.0.iter().step_by(2).map(|x| x.powi(2)).sum::<f64>();
+        assert!(error < 0.01, "{}", error);
+
+        let options = HLoadOptions {
+            sr: Some(48000),
+            ..Default::default()
+        };
+        let (loaded, sr) = load::<f32>(fpath, options).unwrap();
+        assert_eq!(sr, 48000);
+        assert_eq!(
+            loaded.shape(),
+            &[1, output_nframes(698194, 44100, 48000) as usize]
+        );
+    }
+}
@@ -0,0 +1,1168 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use harmonium_core::errors::{HError, HResult};
+use symphonia::core::{formats::Cue, meta::Tag};
+
+use crate::{
+    decode::{HLoopType, HTime},
+    tags::{id3v2_frames, syncsafe_to_u32},
+};
+
+/// A marker as stored in the file, before its position is converted to frames.
+#[derive(Debug, PartialEq)]
+pub(crate) struct RawMarker {
+    pub(crate) label: String,
+    pub(crate) start: HTime,
+    pub(crate) end: Option<HTime>,
+    pub(crate) loop_type: Option<HLoopType>,
+}
+
+/// Reads the markers that are not handled by the format readers:
+/// * WAV - `cue ` points, labelled by the `LIST`/`adtl` chunk, and `smpl` loops.
+/// * MP3 - ID3v2 `CHAP` frames.
+/// * MP4 - QuickTime chapters (a text track referenced by `tref`/`chap`), or Nero chapters (`chpl` atom).
+/// * MKV/WebM - chapters of the default edition.
+///
+/// The reader is left at the start of the stream.
+pub(crate) fn read_markers<R: Read + Seek>(reader: &mut R) -> HResult<Vec<RawMarker>> {
+    let mut magic = [0; 12];
+    reader.seek(SeekFrom::Start(0))?;
+    let n = read_up_to(reader, &mut magic)?;
+    let magic = &magic[..n];
+
+    let markers = if is_wav(magic) {
+        read_wav_markers(reader)?
+    } else if magic.starts_with(b"ID3") {
+        read_id3v2_chapters(reader)?
+    } else if magic.get(4..8) == Some(b"ftyp".as_slice()) {
+        read_mp4_chapters(reader)?
+    } else if magic.starts_with(&[0x1a, 0x45, 0xdf, 0xa3]) {
+        read_mkv_chapters(reader)?
+    } else {
+        Vec::new()
+    };
+
+    reader.seek(SeekFrom::Start(0))?;
+
+    Ok(markers)
+}
+
+/// Whether the stream starts with a RIFF, RF64 or BW64 WAVE header.
+pub(crate) fn is_wav(magic: &[u8]) -> bool {
+    [b"RIFF", b"RF64", b"BW64"]
+        .iter()
+        .any(|x| magic.starts_with(*x))
+        && magic.get(8..12) == Some(b"WAVE".as_slice())
+}
+
+/// Reads the bodies of the WAVE chunks with the given ids, in the order they appear in the stream.
+/// The 64-bit sizes of RF64 and BW64 files are taken from the `ds64` chunk.
+pub(crate) fn read_wav_chunks<R: Read + Seek>(
+    reader: &mut R,
+    ids: &[&[u8; 4]],
+) -> HResult<Vec<([u8; 4], Vec<u8>)>> {
+    let mut chunks = Vec::new();
+    let mut data_len = None;
+
+    let mut pos = 12;
+    loop {
+        reader.seek(SeekFrom::Start(pos))?;
+        let mut header = [0; 8];
+        if read_up_to(reader, &mut header)? < 8 {
+            break;
+        }
+        let id: [u8; 4] = header[0..4].try_into().unwrap();
+        let mut len = u32::from_le_bytes(header[4..8].try_into().unwrap()) as u64;
+
+        if &id == b"ds64" {
+            let body = read_body(reader, len)?;
+            data_len = le_u64(&body, 8);
+        } else if &id == b"data" && len == u32::MAX as u64 {
+            match data_len {
+                Some(x) => len = x,
+                None => break,
+            }
+        }
+        if ids.contains(&&id) {
+            chunks.push((id, read_body(reader, len)?));
+        }
+
+        // The RIFF chunks have an even length.
+        pos += 8 + len + len % 2;
+    }
+
+    Ok(chunks)
+}
+
+/// Converts the tracks of a FLAC cuesheet to markers. The last track is the lead-out, which only marks the end of the
+/// previous track.
+pub(crate) fn cuesheet_markers(cues: &[Cue]) -> Vec<RawMarker> {
+    cues.windows(2)
+        .map(|x| RawMarker {
+            label: x[0].index.to_string(),
+            start: HTime::Frames(x[0].start_ts),
+            end: Some(HTime::Frames(x[1].start_ts)),
+            loop_type: None,
+        })
+        .collect()
+}
+
+/// Reads the chapters stored in Vorbis comments, as `CHAPTER001=00:00:00.000` and `CHAPTER001NAME=Intro`.
+pub(crate) fn vorbis_chapters(tags: &[Tag]) -> Vec<RawMarker> {
+    let mut chapters: Vec<(u32, f64, String)> = Vec::new();
+    for tag in tags {
+        let key = tag.key.to_ascii_uppercase();
+        let Some(number) = key.strip_prefix("CHAPTER") else {
+            continue;
+        };
+        let Ok(number) = number.parse::<u32>() else {
+            continue;
+        };
+        let Some(seconds) = parse_timestamp(&tag.value.to_string()) else {
+            continue;
+        };
+        let name = tags
+            .iter()
+            .find(|x| x.key.to_ascii_uppercase() == format!("{}NAME", key))
+            .map(|x| x.value.to_string())
+            .unwrap_or_default();
+        chapters.push((number, seconds, name));
+    }
+    chapters.sort_by_key(|x| x.0);
+
+    chapters_from_starts(chapters.into_iter().map(|x| (x.2, x.1)).collect())
+}
+
+/// Parses a `HH:MM:SS.sss` timestamp into seconds.
+fn parse_timestamp(s: &str) -> Option<f64> {
+    let mut parts = s.trim().rsplitn(3, ':');
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next().map_or(Ok(0.), |x| x.parse()).ok()?;
+    let hours: f64 = parts.next().map_or(Ok(0.), |x| x.parse()).ok()?;
+    Some(hours * 3600. + minutes * 60. + seconds)
+}
+
+/// Builds chapters from their labels and start times in seconds. Each chapter ends where the next one starts, and the
+/// last one lasts until the end of the stream.
+fn chapters_from_starts(chapters: Vec<(String, f64)>) -> Vec<RawMarker> {
+    let ends: Vec<Option<HTime>> = chapters
+        .iter()
+        .skip(1)
+        .map(|x| Some(HTime::Seconds(x.1)))
+        .chain(std::iter::once(None))
+        .collect();
+    chapters
+        .into_iter()
+        .zip(ends)
+        .map(|((label, start), end)| RawMarker {
+            label,
+            start: HTime::Seconds(start),
+            end,
+            loop_type: None,
+        })
+        .collect()
+}
+
+fn read_wav_markers<R: Read + Seek>(reader: &mut R) -> HResult<Vec<RawMarker>> {
+    // Cue point id and position in frames.
+    let mut cues: Vec<(u32, u64)> = Vec::new();
+    let mut labels: HashMap<u32, String> = HashMap::new();
+    // Length in frames of the region starting at a cue point.
+    let mut lengths: HashMap<u32, u64> = HashMap::new();
+    let mut loops: Vec<(u32, HLoopType, u64, u64)> = Vec::new();
+
+    for (id, body) in read_wav_chunks(reader, &[b"cue ", b"LIST", b"smpl"])? {
+        match &id {
+            b"cue " => {
+                let n = le_u32(&body, 0).unwrap_or(0) as usize;
+                for point in body.get(4..).unwrap_or_default().chunks_exact(24).take(n) {
+                    // The position is the sample offset in the data chunk.
+                    cues.push((le_u32(point, 0).unwrap(), le_u32(point, 20).unwrap() as u64));
+                }
+            }
+            b"LIST" if body.starts_with(b"adtl") => {
+                let mut pos = 4;
+                while let (Some(id), Some(sub_len)) =
+                    (body.get(pos..pos + 4), le_u32(&body, pos + 4))
+                {
+                    let sub_len = sub_len as usize;
+                    let Some(sub) = body.get(pos + 8..pos + 8 + sub_len) else {
+                        break;
+                    };
+                    match (id, le_u32(sub, 0)) {
+                        (b"labl", Some(cue_id)) => {
+                            labels.insert(cue_id, zstr(&sub[4..]));
+                        }
+                        (b"note", Some(cue_id)) => {
+                            labels.entry(cue_id).or_insert_with(|| zstr(&sub[4..]));
+                        }
+                        (b"ltxt", Some(cue_id)) => {
+                            if let Some(length) = le_u32(sub, 4).filter(|x| *x > 0) {
+                                lengths.insert(cue_id, length as u64);
+                            }
+                        }
+                        _ => (),
+                    }
+                    pos += 8 + sub_len + sub_len % 2;
+                }
+            }
+            b"smpl" => {
+                let n = le_u32(&body, 28).unwrap_or(0) as usize;
+                for sample_loop in body.get(36..).unwrap_or_default().chunks_exact(24).take(n) {
+                    let loop_type = match le_u32(sample_loop, 4).unwrap() {
+                        0 => HLoopType::Forward,
+                        1 => HLoopType::Alternating,
+                        2 => HLoopType::Backward,
+                        _ => continue,
+                    };
+                    loops.push((
+                        le_u32(sample_loop, 0).unwrap(),
+                        loop_type,
+                        le_u32(sample_loop, 8).unwrap() as u64,
+                        // The end of the loop is inclusive.
+                        le_u32(sample_loop, 12).unwrap() as u64 + 1,
+                    ));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut markers = Vec::with_capacity(cues.len() + loops.len());
+    for (id, start) in cues {
+        // Cue points are also used to label the loops starting at them.
+        if loops.iter().any(|x| x.0 == id && x.2 == start) {
+            continue;
+        }
+        markers.push(RawMarker {
+            label: labels.get(&id).cloned().unwrap_or_default(),
+            start: HTime::Frames(start),
+            end: lengths.get(&id).map(|x| HTime::Frames(start + x)),
+            loop_type: None,
+        });
+    }
+    for (id, loop_type, start, end) in loops {
+        markers.push(RawMarker {
+            label: labels.get(&id).cloned().unwrap_or_default(),
+            start: HTime::Frames(start),
+            end: Some(HTime::Frames(end)),
+            loop_type: Some(loop_type),
+        });
+    }
+
+    Ok(markers)
+}
+
+fn read_id3v2_chapters<R: Read + Seek>(reader: &mut R) -> HResult<Vec<RawMarker>> {
+    let mut header = [0; 10];
+    reader.seek(SeekFrom::Start(0))?;
+    if read_up_to(reader, &mut header)? < 10 {
+        return Ok(Vec::new());
+    }
+    let version = header[3];
+    let flags = header[5];
+    let size = syncsafe_to_u32(header[6..10].try_into().unwrap()) as u64;
+    let body = read_body(reader, size)?;
+
+    let mut markers = Vec::new();
+    for (id, frame) in id3v2_frames(&body, version, flags) {
+        if id != *b"CHAP" {
+            continue;
+        }
+        // Element id, then the start and end times in milliseconds and the start and end byte offsets.
+        let Some(element_id_len) = frame.iter().position(|x| *x == 0) else {
+            continue;
+        };
+        let times = &frame[element_id_len + 1..];
+        let (Some(start), Some(end)) = (be_u32(times, 0), be_u32(times, 4)) else {
+            continue;
+        };
+        let label = id3v2_frames(times.get(16..).unwrap_or_default(), version, 0)
+            .into_iter()
+            .find(|(id, _)| id == b"TIT2")
+            .map(|(_, body)| id3v2_text(&body))
+            .unwrap_or_default();
+        markers.push(RawMarker {
+            label,
+            start: HTime::Seconds(start as f64 / 1000.),
+            end: Some(HTime::Seconds(end as f64 / 1000.)),
+            loop_type: None,
+        });
+    }
+
+    Ok(markers)
+}
+
+/// Decodes the body of an ID3v2 text frame. Only the first value is returned.
+fn id3v2_text(body: &[u8]) -> String {
+    let text = match body.split_first() {
+        // ISO-8859-1.
+        Some((0, text)) => text.iter().map(|x| *x as char).collect(),
+        // UTF-16 with a byte order mark, or big-endian without one.
+        Some((1 | 2, text)) => {
+            let (big_endian, text) = match text {
+                [0xff, 0xfe, rest @ ..] => (false, rest),
+                [0xfe, 0xff, rest @ ..] => (true, rest),
+                _ => (true, text),
+            };
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|x| {
+                    if big_endian {
+                        u16::from_be_bytes([x[0], x[1]])
+                    } else {
+                        u16::from_le_bytes([x[0], x[1]])
+                    }
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        Some((3, text)) => String::from_utf8_lossy(text).into_owned(),
+        _ => String::new(),
+    };
+    text.split('\0').next().unwrap_or_default().to_string()
+}
+
+fn read_mp4_chapters<R: Read + Seek>(reader: &mut R) -> HResult<Vec<RawMarker>> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    let Some(moov) = find_mp4_atom(reader, 0, len, b"moov")? else {
+        return Ok(Vec::new());
+    };
+
+    // QuickTime chapters, as written by iTunes, are preferred over Nero chapters when a file has both.
+    let chapters = read_mp4_chapter_track(reader, moov)?;
+    if !chapters.is_empty() {
+        return Ok(chapters_from_starts(chapters));
+    }
+
+    let Some(body) = read_mp4_atom(reader, moov, &[b"udta", b"chpl"])? else {
+        return Ok(Vec::new());
+    };
+
+    // Version and flags, followed by 4 reserved bytes in version 1.
+    let mut pos = if body.first().is_some_and(|x| *x > 0) {
+        8
+    } else {
+        4
+    };
+    let n = body.get(pos).copied().unwrap_or(0);
+    pos += 1;
+    let mut chapters = Vec::with_capacity(n as usize);
+    for _ in 0..n {
+        let (Some(start), Some(title_len)) = (be_u64(&body, pos), body.get(pos + 8)) else {
+            break;
+        };
+        let title_len = *title_len as usize;
+        let Some(title) = body.get(pos + 9..pos + 9 + title_len) else {
+            break;
+        };
+        // The start time is in units of 100 nanoseconds.
+        chapters.push((
+            String::from_utf8_lossy(title).into_owned(),
+            start as f64 / 1e7,
+        ));
+        pos += 9 + title_len;
+    }
+
+    Ok(chapters_from_starts(chapters))
+}
+
+/// Reads the QuickTime chapters, which are the samples of a text track referenced by the `tref`/`chap` atom of another
+/// track. Returns the label and the start in seconds of each chapter.
+fn read_mp4_chapter_track<R: Read + Seek>(
+    reader: &mut R,
+    moov: (u64, u64),
+) -> HResult<Vec<(String, f64)>> {
+    let mut traks = Vec::new();
+    let mut pos = moov.0;
+    while let Some(trak) = find_mp4_atom(reader, pos, moov.1, b"trak")? {
+        traks.push(trak);
+        pos = trak.1;
+    }
+
+    let mut chapter_ids = Vec::new();
+    let mut track_ids = Vec::with_capacity(traks.len());
+    for trak in traks.iter() {
+        // The track id follows the version, the flags and the creation and modification times.
+        let tkhd = read_mp4_atom(reader, *trak, &[b"tkhd"])?.unwrap_or_default();
+        track_ids.push(be_u32(
+            &tkhd,
+            if tkhd.first() == Some(&1) { 20 } else { 12 },
+        ));
+        if let Some(chap) = read_mp4_atom(reader, *trak, &[b"tref", b"chap"])? {
+            chapter_ids.extend(
+                chap.chunks_exact(4)
+                    .map(|x| u32::from_be_bytes(x.try_into().unwrap())),
+            );
+        }
+    }
+    let Some(trak) = traks
+        .iter()
+        .zip(track_ids)
+        .find(|(_, id)| id.is_some_and(|id| chapter_ids.contains(&id)))
+        .map(|(trak, _)| *trak)
+    else {
+        return Ok(Vec::new());
+    };
+
+    // The timescale follows the version, the flags and the creation and modification times.
+    let mdhd = read_mp4_atom(reader, trak, &[b"mdia", b"mdhd"])?.unwrap_or_default();
+    let timescale = be_u32(&mdhd, if mdhd.first() == Some(&1) { 20 } else { 12 });
+    let (Some(timescale), Some(stbl)) = (
+        timescale.filter(|x| *x > 0),
+        find_mp4_path(reader, trak, &[b"mdia", b"minf", b"stbl"])?,
+    ) else {
+        return Ok(Vec::new());
+    };
+    let stts = read_mp4_atom(reader, stbl, &[b"stts"])?.unwrap_or_default();
+    let stsz = read_mp4_atom(reader, stbl, &[b"stsz"])?.unwrap_or_default();
+    let stsc = read_mp4_atom(reader, stbl, &[b"stsc"])?.unwrap_or_default();
+    let chunk_offsets: Vec<u64> = match read_mp4_atom(reader, stbl, &[b"stco"])? {
+        Some(stco) => stco
+            .get(8..)
+            .unwrap_or_default()
+            .chunks_exact(4)
+            .map(|x| u64::from(u32::from_be_bytes(x.try_into().unwrap())))
+            .collect(),
+        None => read_mp4_atom(reader, stbl, &[b"co64"])?
+            .unwrap_or_default()
+            .get(8..)
+            .unwrap_or_default()
+            .chunks_exact(8)
+            .map(|x| u64::from_be_bytes(x.try_into().unwrap()))
+            .collect(),
+    };
+
+    // The full atoms start with the version and the flags, followed by the number of entries or, for `stsz`, the size
+    // of every sample if they have the same size.
+    let nsamples = be_u32(&stsz, 8).unwrap_or(0) as usize;
+    let uniform_size = be_u32(&stsz, 4).unwrap_or(0);
+    let sample_size = |i: usize| match uniform_size {
+        0 => be_u32(&stsz, 12 + 4 * i),
+        x => Some(x),
+    };
+    // First chunk, numbered from 1, and number of samples per chunk of each run of chunks.
+    let runs: Vec<(u32, u32)> = stsc
+        .get(8..)
+        .unwrap_or_default()
+        .chunks_exact(12)
+        .map(|x| (be_u32(x, 0).unwrap(), be_u32(x, 4).unwrap()))
+        .collect();
+    let mut durations = stts
+        .get(8..)
+        .unwrap_or_default()
+        .chunks_exact(8)
+        .flat_map(|x| {
+            let count = be_u32(x, 0).unwrap() as usize;
+            std::iter::repeat_n(be_u32(x, 4).unwrap(), count)
+        });
+
+    let mut chapters = Vec::new();
+    let mut start = 0_u64;
+    'chunks: for (i, chunk_offset) in chunk_offsets.iter().enumerate() {
+        let nchunk_samples = runs
+            .iter()
+            .take_while(|x| x.0 as usize <= i + 1)
+            .last()
+            .map_or(0, |x| x.1);
+        let mut offset = *chunk_offset;
+        for _ in 0..nchunk_samples {
+            let (Some(size), Some(duration)) = (sample_size(chapters.len()), durations.next())
+            else {
+                break 'chunks;
+            };
+            if chapters.len() == nsamples {
+                break 'chunks;
+            }
+            let Some(label) = read_mp4_text_sample(reader, offset)? else {
+                break 'chunks;
+            };
+            chapters.push((label, start as f64 / f64::from(timescale)));
+            start += u64::from(duration);
+            offset += u64::from(size);
+        }
+    }
+
+    Ok(chapters)
+}
+
+/// Reads a sample of a text track, which is a UTF-8 or UTF-16 text prefixed by its length. Returns `None` if the
+/// sample is after the end of the stream.
+fn read_mp4_text_sample<R: Read + Seek>(reader: &mut R, offset: u64) -> HResult<Option<String>> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut text_len = [0; 2];
+    if read_up_to(reader, &mut text_len)? < 2 {
+        return Ok(None);
+    }
+    let text = read_body(reader, u64::from(u16::from_be_bytes(text_len)))?;
+    let text = match text.strip_prefix(&[0xfe, 0xff]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|x| u16::from_be_bytes([x[0], x[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => String::from_utf8_lossy(&text).into_owned(),
+    };
+    Ok(Some(text))
+}
+
+/// Looks for the atom at `path` inside the body of `parent`, returning the start and the end of its body.
+fn find_mp4_path<R: Read + Seek>(
+    reader: &mut R,
+    parent: (u64, u64),
+    path: &[&[u8; 4]],
+) -> HResult<Option<(u64, u64)>> {
+    let mut atom = parent;
+    for name in path {
+        match find_mp4_atom(reader, atom.0, atom.1, name)? {
+            Some(x) => atom = x,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(atom))
+}
+
+/// Reads the body of the atom at `path` inside the body of `parent`.
+fn read_mp4_atom<R: Read + Seek>(
+    reader: &mut R,
+    parent: (u64, u64),
+    path: &[&[u8; 4]],
+) -> HResult<Option<Vec<u8>>> {
+    let Some((start, end)) = find_mp4_path(reader, parent, path)? else {
+        return Ok(None);
+    };
+    reader.seek(SeekFrom::Start(start))?;
+    read_body(reader, end - start).map(Some)
+}
+
+/// Looks for an atom between `start` and `end`, returning the start and the end of its body.
+fn find_mp4_atom<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    end: u64,
+    name: &[u8; 4],
+) -> HResult<Option<(u64, u64)>> {
+    let mut pos = start;
+    while pos + 8 <= end {
+        reader.seek(SeekFrom::Start(pos))?;
+        let mut header = [0; 16];
+        let n = read_up_to(reader, &mut header)?;
+        if n < 8 {
+            break;
+        }
+        let (header_len, size) = match u32::from_be_bytes(header[0..4].try_into().unwrap()) {
+            // The atom lasts until the end of its parent.
+            0 => (8, end - pos),
+            // 64-bit size.
+            1 if n == 16 => (16, u64::from_be_bytes(header[8..16].try_into().unwrap())),
+            size => (8, size as u64),
+        };
+        if size < header_len {
+            return Err(HError::DecodeError("mp4: invalid atom size".into()));
+        }
+        if &header[4..8] == name {
+            return Ok(Some((pos + header_len, (pos + size).min(end))));
+        }
+        pos += size;
+    }
+    Ok(None)
+}
+
+fn read_mkv_chapters<R: Read + Seek>(reader: &mut R) -> HResult<Vec<RawMarker>> {
+    const SEGMENT: u64 = 0x1853_8067;
+    const CHAPTERS: u64 = 0x1043_a770;
+
+    let len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    // Skip the EBML header.
+    let Some((_, size)) = read_ebml_header(reader)? else {
+        return Ok(Vec::new());
+    };
+    let Some(size) = size else {
+        return Ok(Vec::new());
+    };
+    reader.seek(SeekFrom::Current(size as i64))?;
+
+    let Some((SEGMENT, segment_size)) = read_ebml_header(reader)? else {
+        return Ok(Vec::new());
+    };
+    let segment_start = reader.stream_position()?;
+    let segment_end = segment_size.map_or(len, |x| (segment_start + x).min(len));
+
+    while reader.stream_position()? < segment_end {
+        let Some((id, size)) = read_ebml_header(reader)? else {
+            break;
+        };
+        // Elements of unknown size, like the clusters of live streams, can't be skipped.
+        let Some(size) = size else {
+            break;
+        };
+        if id == CHAPTERS {
+            return Ok(mkv_chapters(&read_body(reader, size)?));
+        }
+        reader.seek(SeekFrom::Current(size as i64))?;
+    }
+
+    Ok(Vec::new())
+}
+
+/// Parses the body of a `Chapters` element, returning the chapters of the default edition or of the first one.
+fn mkv_chapters(body: &[u8]) -> Vec<RawMarker> {
+    const EDITION_ENTRY: u64 = 0x45b9;
+    const EDITION_FLAG_DEFAULT: u64 = 0x45db;
+
+    let editions: Vec<&[u8]> = ebml_elements(body)
+        .into_iter()
+        .filter(|x| x.0 == EDITION_ENTRY)
+        .map(|x| x.1)
+        .collect();
+    let edition = editions
+        .iter()
+        .find(|x| {
+            ebml_elements(x)
+                .iter()
+                .any(|(id, body)| *id == EDITION_FLAG_DEFAULT && ebml_uint(body) == 1)
+        })
+        .or(editions.first());
+
+    let mut chapters: Vec<(String, u64, Option<u64>)> = Vec::new();
+    if let Some(edition) = edition {
+        mkv_chapter_atoms(edition, &mut chapters);
+    }
+    chapters.sort_by_key(|x| x.1);
+
+    let next_starts: Vec<Option<u64>> = chapters
+        .iter()
+        .skip(1)
+        .map(|x| Some(x.1))
+        .chain(std::iter::once(None))
+        .collect();
+    chapters
+        .into_iter()
+        .zip(next_starts)
+        .map(|((label, start, end), next_start)| RawMarker {
+            label,
+            // The times are in nanoseconds.
+            start: HTime::Seconds(start as f64 / 1e9),
+            end: end.or(next_start).map(|x| HTime::Seconds(x as f64 / 1e9)),
+            loop_type: None,
+        })
+        .collect()
+}
+
+/// Collects the chapter atoms, including the nested ones, as label, start and end.
+fn mkv_chapter_atoms(body: &[u8], chapters: &mut Vec<(String, u64, Option<u64>)>) {
+    const CHAPTER_ATOM: u64 = 0xb6;
+    const CHAPTER_TIME_START: u64 = 0x91;
+    const CHAPTER_TIME_END: u64 = 0x92;
+    const CHAPTER_DISPLAY: u64 = 0x80;
+    const CHAP_STRING: u64 = 0x85;
+
+    for (id, atom) in ebml_elements(body) {
+        if id != CHAPTER_ATOM {
+            continue;
+        }
+        let mut label = String::new();
+        let mut start = None;
+        let mut end = None;
+        for (id, element) in ebml_elements(atom) {
+            match id {
+                CHAPTER_TIME_START => start = Some(ebml_uint(element)),
+                CHAPTER_TIME_END => end = Some(ebml_uint(element)),
+                CHAPTER_DISPLAY if label.is_empty() => {
+                    if let Some((_, s)) = ebml_elements(element)
+                        .into_iter()
+                        .find(|x| x.0 == CHAP_STRING)
+                    {
+                        label = String::from_utf8_lossy(s)
+                            .trim_end_matches('\0')
+                            .to_string();
+                    }
+                }
+                _ => (),
+            }
+        }
+        if let Some(start) = start {
+            chapters.push((label, start, end));
+        }
+        mkv_chapter_atoms(atom, chapters);
+    }
+}
+
+/// Reads the id and the size of an EBML element. The size is `None` when unknown.
+fn read_ebml_header<R: Read>(reader: &mut R) -> HResult<Option<(u64, Option<u64>)>> {
+    let Some((id, _)) = read_vint(reader)? else {
+        return Ok(None);
+    };
+    let Some((size, len)) = read_vint(reader)? else {
+        return Ok(None);
+    };
+    let data_bits = 7 * len as u32;
+    let mask = (1_u64 << data_bits) - 1;
+    let size = size & mask;
+    Ok(Some((id, (size != mask).then_some(size))))
+}
+
+/// Reads a variable-size integer, keeping the length marker. Returns the value and its length in bytes.
+fn read_vint<R: Read>(reader: &mut R) -> HResult<Option<(u64, usize)>> {
+    let mut first = [0];
+    if read_up_to(reader, &mut first)? == 0 {
+        return Ok(None);
+    }
+    let len = first[0].leading_zeros() as usize + 1;
+    if len > 8 {
+        return Err(HError::DecodeError("mkv: invalid element".into()));
+    }
+    let mut rest = [0; 7];
+    if read_up_to(reader, &mut rest[..len - 1])? < len - 1 {
+        return Ok(None);
+    }
+    let value = rest[..len - 1]
+        .iter()
+        .fold(first[0] as u64, |acc, x| (acc << 8) | *x as u64);
+    Ok(Some((value, len)))
+}
+
+/// Splits an in-memory EBML body into its elements.
+fn ebml_elements(mut body: &[u8]) -> Vec<(u64, &[u8])> {
+    let mut elements = Vec::new();
+    while let Ok(Some((id, Some(size)))) = read_ebml_header(&mut body) {
+        let Some(element) = body.get(..size as usize) else {
+            break;
+        };
+        elements.push((id, element));
+        body = &body[size as usize..];
+    }
+    elements
+}
+
+fn ebml_uint(body: &[u8]) -> u64 {
+    body.iter().fold(0, |acc, x| (acc << 8) | *x as u64)
+}
+
+/// Reads the body of a chunk or an atom. The length is not trusted, so the buffer grows as the data is read.
+fn read_body<R: Read>(reader: &mut R, len: u64) -> HResult<Vec<u8>> {
+    let mut body = Vec::new();
+    reader.take(len).read_to_end(&mut body)?;
+    if (body.len() as u64) < len {
+        return Err(HError::DecodeError(
+            "the file ended in the middle of a metadata block".into(),
+        ));
+    }
+    Ok(body)
+}
+
+/// Reads until `buf` is full or the end of the stream is reached, returning the number of bytes read.
+pub(crate) fn read_up_to<R: Read + ?Sized>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match reader.read(&mut buf[n..])? {
+            0 => break,
+            x => n += x,
+        }
+    }
+    Ok(n)
+}
+
+fn le_u32(bytes: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(pos..pos + 4)?.try_into().ok()?,
+    ))
+}
+
+fn be_u32(bytes: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(pos..pos + 4)?.try_into().ok()?,
+    ))
+}
+
+fn be_u64(bytes: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        bytes.get(pos..pos + 8)?.try_into().ok()?,
+    ))
+}
+
+fn le_u64(bytes: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(pos..pos + 8)?.try_into().ok()?,
+    ))
+}
+
+/// Reads a NUL-terminated or NUL-padded string.
+pub(crate) fn zstr(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|x| *x == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn ebml(id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut element = id.to_vec();
+        element.extend_from_slice(&(0x0100_0000_0000_0000 | body.len() as u64).to_be_bytes());
+        element.extend_from_slice(body);
+        element
+    }
+
+    fn wav_marker_chunks() -> Vec<u8> {
+        let mut cue = 3_u32.to_le_bytes().to_vec();
+        for (id, position) in [(1_u32, 100_u32), (2, 200), (3, 1000)] {
+            cue.extend_from_slice(&id.to_le_bytes());
+            cue.extend_from_slice(&position.to_le_bytes());
+            cue.extend_from_slice(b"data");
+            cue.extend_from_slice(&[0; 8]);
+            cue.extend_from_slice(&position.to_le_bytes());
+        }
+
+        let mut adtl = b"adtl".to_vec();
+        adtl.extend(chunk(
+            b"labl",
+            &[&1_u32.to_le_bytes()[..], b"intro\0"].concat(),
+        ));
+        adtl.extend(chunk(
+            b"labl",
+            &[&2_u32.to_le_bytes()[..], b"verse\0"].concat(),
+        ));
+        adtl.extend(chunk(
+            b"note",
+            &[&2_u32.to_le_bytes()[..], b"a note\0"].concat(),
+        ));
+        adtl.extend(chunk(
+            b"labl",
+            &[&3_u32.to_le_bytes()[..], b"loop\0"].concat(),
+        ));
+        let mut ltxt = 2_u32.to_le_bytes().to_vec();
+        ltxt.extend_from_slice(&50_u32.to_le_bytes());
+        ltxt.extend_from_slice(&[0; 12]);
+        adtl.extend(chunk(b"ltxt", &ltxt));
+
+        let mut smpl = vec![0; 28];
+        smpl.extend_from_slice(&1_u32.to_le_bytes());
+        smpl.extend_from_slice(&0_u32.to_le_bytes());
+        for x in [3_u32, 1, 1000, 1999, 0, 0] {
+            smpl.extend_from_slice(&x.to_le_bytes());
+        }
+
+        [
+            chunk(b"cue ", &cue),
+            chunk(b"LIST", &adtl),
+            chunk(b"smpl", &smpl),
+        ]
+        .concat()
+    }
+
+    fn id3v2_chapters_tag() -> Vec<u8> {
+        let mut frames = Vec::new();
+        for (element_id, start, end, title) in [
+            (&b"ch0"[..], 0_u32, 500_u32, "First"),
+            (b"ch1", 500, 1000, "Second"),
+        ] {
+            // UTF-16 with a byte order mark.
+            let mut tit2 = vec![1, 0xff, 0xfe];
+            tit2.extend(title.encode_utf16().flat_map(|x| x.to_le_bytes()));
+            let mut body = [element_id, &[0]].concat();
+            body.extend_from_slice(&start.to_be_bytes());
+            body.extend_from_slice(&end.to_be_bytes());
+            body.extend_from_slice(&[0xff; 8]);
+            body.extend_from_slice(b"TIT2");
+            body.extend_from_slice(&(tit2.len() as u32).to_be_bytes());
+            body.extend_from_slice(&[0, 0]);
+            body.extend(tit2);
+
+            frames.extend_from_slice(b"CHAP");
+            frames.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            frames.extend_from_slice(&[0, 0]);
+            frames.extend(body);
+        }
+
+        let size = frames.len() as u32;
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        tag.extend((0..4).rev().map(|i| ((size >> (7 * i)) & 0x7f) as u8));
+        tag.extend(frames);
+        tag
+    }
+
+    #[test]
+    fn wav_markers_test() {
+        let riff = [
+            b"WAVE".as_slice(),
+            &wav_marker_chunks(),
+            &chunk(b"data", &[0; 4]),
+        ]
+        .concat();
+        let wav = [
+            b"RIFF".as_slice(),
+            &(riff.len() as u32).to_le_bytes(),
+            &riff,
+        ]
+        .concat();
+
+        let markers = read_markers(&mut Cursor::new(wav)).unwrap();
+        assert_eq!(
+            markers,
+            vec![
+                RawMarker {
+                    label: "intro".into(),
+                    start: HTime::Frames(100),
+                    end: None,
+                    loop_type: None,
+                },
+                RawMarker {
+                    label: "verse".into(),
+                    start: HTime::Frames(200),
+                    end: Some(HTime::Frames(250)),
+                    loop_type: None,
+                },
+                RawMarker {
+                    label: "loop".into(),
+                    start: HTime::Frames(1000),
+                    end: Some(HTime::Frames(2000)),
+                    loop_type: Some(HLoopType::Alternating),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn id3v2_chapters_test() {
+        let mut tag = id3v2_chapters_tag();
+        tag.extend_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+
+        let markers = read_markers(&mut Cursor::new(tag)).unwrap();
+        assert_eq!(
+            markers,
+            vec![
+                RawMarker {
+                    label: "First".into(),
+                    start: HTime::Seconds(0.),
+                    end: Some(HTime::Seconds(0.5)),
+                    loop_type: None,
+                },
+                RawMarker {
+                    label: "Second".into(),
+                    start: HTime::Seconds(0.5),
+                    end: Some(HTime::Seconds(1.)),
+                    loop_type: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn mp4_chapters_test() {
+        let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+        for (start, title) in [(0_u64, "One"), (15_000_000, "Two")] {
+            chpl.extend_from_slice(&start.to_be_bytes());
+            chpl.push(title.len() as u8);
+            chpl.extend_from_slice(title.as_bytes());
+        }
+        let atom = |name: &[u8; 4], body: &[u8]| {
+            [&(body.len() as u32 + 8).to_be_bytes()[..], name, body].concat()
+        };
+        let mp4 = [
+            atom(b"ftyp", b"M4A \0\0\0\0"),
+            atom(
+                b"moov",
+                &[atom(b"trak", &[]), atom(b"udta", &atom(b"chpl", &chpl))].concat(),
+            ),
+            atom(b"mdat", &[0; 16]),
+        ]
+        .concat();
+
+        let markers = read_markers(&mut Cursor::new(mp4)).unwrap();
+        assert_eq!(
+            markers,
+            vec![
+                RawMarker {
+                    label: "One".into(),
+                    start: HTime::Seconds(0.),
+                    end: Some(HTime::Seconds(1.5)),
+                    loop_type: None,
+                },
+                RawMarker {
+                    label: "Two".into(),
+                    start: HTime::Seconds(1.5),
+                    end: None,
+                    loop_type: None,
+                },
+            ]
+        );
+
+        // QuickTime chapters are read from the text track referenced by another track, and preferred over Nero chapters.
+        // An atom made of 32-bit fields, starting with the version and the flags.
+        let u32_atom = |name: &[u8; 4], fields: &[u32]| {
+            let body: Vec<u8> = fields.iter().flat_map(|x| x.to_be_bytes()).collect();
+            atom(name, &body)
+        };
+        let utf16: Vec<u8> = [0xfeff_u16]
+            .into_iter()
+            .chain("Outro".encode_utf16())
+            .flat_map(|x| x.to_be_bytes())
+            .collect();
+        let samples = [
+            [&5_u16.to_be_bytes()[..], b"Intro"].concat(),
+            [&(utf16.len() as u16).to_be_bytes()[..], &utf16].concat(),
+        ];
+        let ftyp = atom(b"ftyp", b"M4A \0\0\0\0");
+        let mdat = atom(b"mdat", &samples.concat());
+        let stbl = [
+            u32_atom(b"stts", &[0, 2, 1, 1500, 1, 2000]),
+            // A sample size of 0 followed by the size of each sample.
+            u32_atom(
+                b"stsz",
+                &[0, 0, 2, samples[0].len() as u32, samples[1].len() as u32],
+            ),
+            u32_atom(b"stsc", &[0, 1, 1, 2, 1]),
+            u32_atom(b"stco", &[0, 1, ftyp.len() as u32 + 8]),
+        ]
+        .concat();
+        let tkhd = |id: u32| atom(b"tkhd", &[[0; 12].as_slice(), &id.to_be_bytes()].concat());
+        let audio_trak = atom(
+            b"trak",
+            &[tkhd(1), atom(b"tref", &atom(b"chap", &2_u32.to_be_bytes()))].concat(),
+        );
+        let mdhd = atom(
+            b"mdhd",
+            &[[0; 12].as_slice(), &1000_u32.to_be_bytes()].concat(),
+        );
+        let text_trak = atom(
+            b"trak",
+            &[
+                tkhd(2),
+                atom(
+                    b"mdia",
+                    &[mdhd, atom(b"minf", &atom(b"stbl", &stbl))].concat(),
+                ),
+            ]
+            .concat(),
+        );
+        let mp4 = [
+            ftyp,
+            mdat,
+            atom(
+                b"moov",
+                &[audio_trak, text_trak, atom(b"udta", &atom(b"chpl", &chpl))].concat(),
+            ),
+        ]
+        .concat();
+
+        let markers = read_markers(&mut Cursor::new(mp4)).unwrap();
+        assert_eq!(
+            markers,
+            vec![
+                RawMarker {
+                    label: "Intro".into(),
+                    start: HTime::Seconds(0.),
+                    end: Some(HTime::Seconds(1.5)),
+                    loop_type: None,
+                },
+                RawMarker {
+                    label: "Outro".into(),
+                    start: HTime::Seconds(1.5),
+                    end: None,
+                    loop_type: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn mkv_chapters_test() {
+        let atom = |start: u64, end: Option<u64>, title: &str| {
+            let mut body = ebml(&[0x91], &start.to_be_bytes());
+            if let Some(end) = end {
+                body.extend(ebml(&[0x92], &end.to_be_bytes()));
+            }
+            body.extend(ebml(&[0x80], &ebml(&[0x85], title.as_bytes())));
+            ebml(&[0xb6], &body)
+        };
+        let other_edition = ebml(&[0x45, 0xb9], &atom(0, None, "Other"));
+        let default_edition = ebml(
+            &[0x45, 0xb9],
+            &[
+                ebml(&[0x45, 0xdb], &[1]),
+                atom(2_000_000_000, None, "Second"),
+                atom(0, Some(1_000_000_000), "First"),
+            ]
+            .concat(),
+        );
+        let segment = [
+            ebml(&[0x15, 0x49, 0xa9, 0x66], &[0; 4]),
+            ebml(
+                &[0x10, 0x43, 0xa7, 0x70],
+                &[other_edition, default_edition].concat(),
+            ),
+        ]
+        .concat();
+        let mkv = [
+            ebml(&[0x1a, 0x45, 0xdf, 0xa3], &ebml(&[0x42, 0x82], b"webm")),
+            ebml(&[0x18, 0x53, 0x80, 0x67], &segment),
+        ]
+        .concat();
+
+        let markers = read_markers(&mut Cursor::new(mkv)).unwrap();
+        assert_eq!(
+            markers,
+            vec![
+                RawMarker {
+                    label: "First".into(),
+                    start: HTime::Seconds(0.),
+                    end: Some(HTime::Seconds(1.)),
+                    loop_type: None,
+                },
+                RawMarker {
+                    label: "Second".into(),
+                    start: HTime::Seconds(2.),
+                    end: None,
+                    loop_type: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn vorbis_chapters_test() {
+        let tags = [
+            Tag::new(None, "CHAPTER002", "00:01:00.500".into()),
+            Tag::new(None, "CHAPTER001", "00:00:00.000".into()),
+            Tag::new(None, "CHAPTER001NAME", "Start".into()),
+        ];
+
+        let markers = vorbis_chapters(&tags);
+        assert_eq!(
+            markers,
+            vec![
+                RawMarker {
+                    label: "Start".into(),
+                    start: HTime::Seconds(0.),
+                    end: Some(HTime::Seconds(60.5)),
+                    loop_type: None,
+                },
+                RawMarker {
+                    label: String::new(),
+                    start: HTime::Seconds(60.5),
+                    end: None,
+                    loop_type: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use harmonium_core::{
+    array::HArray,
+    audioop::AudioOp,
+    errors::{HError, HResult},
+};
+use ndarray::{s, Array2, Ix2};
+use num_traits::{Float, FloatConst};
+use rubato::Sample;
+
+use crate::{
+    decode::HTime,
+    load::{HLoadOptions, Loader},
+};
+
+/// Options for a source added to an `HMixer`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HMixOptions {
+    /// Linear gain applied to the source.
+    pub gain: f64,
+    /// Position of the source between the left (-1.0) and right (1.0) channels of a stereo mix. The channel opposite to
+    /// the pan is attenuated linearly, and a centered source (0.0) is not attenuated. Only 0.0 is allowed in mixes that
+    /// are not stereo.
+    pub pan: f64,
+    /// Start of the source in the mix, in seconds or frames at the mix sampling rate.
+    pub offset: HTime,
+}
+
+impl Default for HMixOptions {
+    /// Adds the source centered at the start of the mix, with its original level.
+    fn default() -> Self {
+        HMixOptions {
+            gain: 1.,
+            pan: 0.,
+            offset: HTime::Frames(0),
+        }
+    }
+}
+
+/// Layers several sources in a single audio, each one with its own gain, pan and start offset.
+/// Sources are resampled to the mix sampling rate when they are added. Mono sources are played on every channel of the
+/// mix, and other sources must have as many channels as the mix.
+/// The mix can be rendered to an `HArray` with `render` and `render_range`, or played by `HAudioSink::append_from_mixer`.
+#[derive(Debug, Clone)]
+pub struct HMixer<T> {
+    nchannels: usize,
+    sr: u32,
+    sources: Vec<MixSource<T>>,
+}
+
+#[derive(Debug, Clone)]
+struct MixSource<T> {
+    // Samples at the mix sampling rate, with shape (nchannels, nframes).
+    harray: HArray<T, Ix2>,
+    // Gain applied to the source in each channel of the mix.
+    gains: Vec<T>,
+    // Frame of the mix at which the source starts.
+    offset: u64,
+}
+
+impl<T> HMixer<T>
+where
+    T: Float + FloatConst + Sample,
+{
+    /// Creates an empty mix with `nchannels` channels at the `sr` sampling rate.
+    pub fn new(nchannels: usize, sr: u32) -> HResult<Self> {
+        if nchannels == 0 || nchannels > usize::from(u16::MAX) {
+            return Err(HError::OutOfSpecError(
+                "nchannels must be between 1 and 65535".into(),
+            ));
+        }
+        if sr == 0 {
+            return Err(HError::OutOfSpecError(
+                "the sampling rate must be greater than 0".into(),
+            ));
+        }
+
+        Ok(HMixer {
+            nchannels,
+            sr,
+            sources: Vec::new(),
+        })
+    }
+
+    /// The number of channels of the mix.
+    pub fn nchannels(&self) -> usize {
+        self.nchannels
+    }
+
+    /// The sampling rate of the mix.
+    pub fn sr(&self) -> u32 {
+        self.sr
+    }
+
+    /// The number of sources in the mix.
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Returns true if the mix has no sources.
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// The number of frames of the mix, up to the end of the last source.
+    pub fn nframes(&self) -> u64 {
+        self.sources
+            .iter()
+            .map(|source| source.offset + source.harray.nframes() as u64)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Adds a source to the mix.
+    ///
+    /// # Arguments
+    ///
+    /// * `harray` - The source, with shape (nchannels, nframes). It must be mono or have as many channels as the mix.
+    /// * `sr` - The sampling rate of the source. The source is resampled if it differs from the mix sampling rate.
+    /// * `options` - The `HMixOptions`.
+    pub fn add(&mut self, harray: &HArray<T, Ix2>, sr: u32, options: HMixOptions) -> HResult<()> {
+        let nchannels = harray.nchannels();
+        if nchannels != 1 && nchannels != self.nchannels {
+            return Err(HError::ShapeMismatch {
+                expected: vec![self.nchannels, harray.nframes()],
+                got: harray.shape().to_vec(),
+            });
+        }
+        if sr == 0 {
+            return Err(HError::OutOfSpecError(
+                "the sampling rate must be greater than 0".into(),
+            ));
+        }
+        if !options.gain.is_finite() {
+            return Err(HError::OutOfSpecError("the gain must be finite".into()));
+        }
+        if !(-1. ..=1.).contains(&options.pan) {
+            return Err(HError::OutOfSpecError(
+                "the pan must be between -1.0 and 1.0".into(),
+            ));
+        }
+        if options.pan != 0. && self.nchannels != 2 {
+            return Err(HError::OutOfSpecError(
+                "the pan is only supported by stereo mixes".into(),
+            ));
+        }
+        let offset = options.offset.to_frames(self.sr)?;
+
+        let harray = if sr != self.sr {
+            let load_options = HLoadOptions {
+                sr: Some(self.sr),
+                mono: false,
+                ..Default::default()
+            };
+            let mut loader =
+                Loader::new(nchannels, sr, Some(harray.nframes() as u64), &load_options)?;
+            let interleaved: Vec<T> = harray.0.t().iter().copied().collect();
+            loader.push(&interleaved)?;
+            loader.finish()?.0
+        } else {
+            harray.clone()
+        };
+
+        let mut gains = vec![options.gain; self.nchannels];
+        if self.nchannels == 2 {
+            gains[0] *= (1. - options.pan).min(1.);
+            gains[1] *= (1. + options.pan).min(1.);
+        }
+        // This should not panic since it is a conversion from f64 to a float.
+        let gains = gains.into_iter().map(|x| T::from(x).unwrap()).collect();
+
+        self.sources.push(MixSource {
+            harray,
+            gains,
+            offset,
+        });
+
+        Ok(())
+    }
+
+    /// Renders the whole mix, with shape (nchannels, nframes).
+    pub fn render(&self) -> HArray<T, Ix2> {
+        self.render_range(0, self.nframes() as usize)
+    }
+
+    /// Renders `nframes` frames of the mix starting at frame `start`, with shape (nchannels, nframes). The frames after the
+    /// end of the mix are silent.
+    pub fn render_range(&self, start: u64, nframes: usize) -> HArray<T, Ix2> {
+        let mut output = Array2::<T>::zeros((self.nchannels, nframes));
+        let end = start + nframes as u64;
+
+        for source in self.sources.iter() {
+            let from = start.max(source.offset);
+            let to = end.min(source.offset + source.harray.nframes() as u64);
+            if from >= to {
+                continue;
+            }
+
+            let input = source.harray.0.slice(s![
+                ..,
+                (from - source.offset) as usize..(to - source.offset) as usize
+            ]);
+            let mut output =
+                output.slice_mut(s![.., (from - start) as usize..(to - start) as usize]);
+            for (channel, (mut row, gain)) in output
+                .rows_mut()
+                .into_iter()
+                .zip(source.gains.iter())
+                .enumerate()
+            {
+                let input = input.row(if input.nrows() == 1 { 0 } else { channel });
+                row.zip_mut_with(&input, |y, x| *y += *x * *gain);
+            }
+        }
+
+        HArray(output.into_shared())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use ndarray::array;
+
+    #[test]
+    fn mix_test() {
+        let mono = HArray::new_from_shape_vec((1, 3), vec![1., 2., 3.]).unwrap();
+        let stereo = HArray::new_from_shape_vec((2, 2), vec![1., 1., 1., 1.]).unwrap();
+
+        let mut mixer = HMixer::<f64>::new(2, 10).unwrap();
+        assert!(mixer.is_empty());
+        assert_eq!(mixer.nframes(), 0);
+        let options = HMixOptions {
+            gain: 2.,
+            offset: HTime::Frames(2),
+            ..Default::default()
+        };
+        mixer.add(&mono, 10, options).unwrap();
+        let options = HMixOptions {
+            pan: 0.5,
+            offset: HTime::Seconds(0.3),
+            ..Default::default()
+        };
+        mixer.add(&stereo, 10, options).unwrap();
+        assert_eq!(mixer.len(), 2);
+        assert_eq!(mixer.nframes(), 5);
+
+        // The stereo source is panned to the right, attenuating the left channel.
+        assert_eq!(
+            mixer.render().0,
+            array![[0., 0., 2., 4.5, 6.5], [0., 0., 2., 5., 7.]]
+        );
+        assert_eq!(
+            mixer.render_range(4, 3).0,
+            array![[6.5, 0., 0.], [7., 0., 0.]]
+        );
+
+        // Invalid sources.
+        assert!(mixer
+            .add(
+                &HArray(Array2::zeros((3, 2)).into_shared()),
+                10,
+                HMixOptions::default()
+            )
+            .is_err());
+        let options = HMixOptions {
+            pan: 1.5,
+            ..Default::default()
+        };
+        assert!(mixer.add(&mono, 10, options).is_err());
+        let mut mixer_mono = HMixer::<f64>::new(1, 10).unwrap();
+        let options = HMixOptions {
+            pan: 0.5,
+            ..Default::default()
+        };
+        assert!(mixer_mono.add(&mono, 10, options).is_err());
+        assert!(HMixer::<f64>::new(0, 10).is_err());
+
+        // Sources are resampled to the mix sampling rate.
+        let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
+        let options = HLoadOptions {
+            mono: false,
+            ..Default::default()
+        };
+        let (harray, sr) = load::<f32>(fpath, options).unwrap();
+        let options = HLoadOptions {
+            sr: Some(22050),
+            mono: false,
+            ..Default::default()
+        };
+        let (resampled, _) = load::<f32>(fpath, options).unwrap();
+        let mut mixer = HMixer::<f32>::new(2, 22050).unwrap();
+        mixer.add(&harray, sr, HMixOptions::default()).unwrap();
+        assert_eq!(mixer.render(), resampled);
+    }
+}
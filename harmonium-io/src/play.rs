@@ -1,22 +1,55 @@
-use std::time::Duration;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Mutex, MutexGuard,
+    },
+    time::Duration,
+};
 
 use harmonium_core::{
+    array::HArray,
     audioop::{Audio, AudioOp},
     errors::{HError, HResult},
 };
-use ndarray::Array1;
+use ndarray::{Array1, Ix2};
 use num_traits::{Float, FloatConst, FromPrimitive};
 use rodio::{
     buffer::SamplesBuffer,
-    cpal::{traits::HostTrait, SupportedBufferSize},
-    DeviceTrait, OutputStream, Sink,
+    cpal::{
+        self,
+        traits::{HostTrait, StreamTrait},
+        BufferSize, FromSample, SampleFormat, SizedSample, StreamConfig, SupportedBufferSize,
+    },
+    queue::SourcesQueueOutput,
+    source::{SeekError, UniformSourceIterator},
+    DeviceTrait, OutputStream, Sink, Source,
 };
+use rubato::Sample;
 
-use crate::decode::decode;
+use crate::{
+    decode::{stream_from_input, DecoderStream, HInput, HStreamTail},
+    encode::{write_wav, HOutOfRange, HSampleFormat},
+    mix::HMixer,
+};
 
 pub struct HAudioSink {
     sink: Sink,
-    _stream: OutputStream,
+    output: SinkOutput,
+    events: Arc<PlaybackEvents>,
+}
+
+enum SinkOutput {
+    /// The sounds are played by the default audio device, as long as the stream is alive.
+    Device { _stream: OutputStream },
+    /// The sounds are played by a chosen audio device and configuration, as long as the stream is alive. The last error
+    /// reported by the stream is kept until it is taken.
+    DeviceWithConfig {
+        _stream: cpal::Stream,
+        error: Arc<Mutex<Option<cpal::StreamError>>>,
+    },
+    /// The sounds are rendered on demand, without an audio device.
+    Offline(Mutex<OfflineOutput>),
 }
 
 impl HAudioSink {
@@ -25,7 +58,183 @@ impl HAudioSink {
         let (_stream, stream_handle) = OutputStream::try_default()?;
         let sink = Sink::try_new(&stream_handle)?;
 
-        Ok(HAudioSink { sink, _stream })
+        Ok(HAudioSink {
+            sink,
+            output: SinkOutput::Device { _stream },
+            events: Arc::default(),
+        })
+    }
+
+    /// Creates a new `HAudioSink` instance that plays on the output device named `name`, as listed by `audio_output_devices`.
+    /// The sink is set on "play" mode from the start.
+    /// The stream configuration is chosen from the configurations supported by the device, as listed by
+    /// `audio_device_supported_configs`. Unset fields of `config` are taken from the device's default configuration.
+    pub fn try_new_with_device(name: &str, config: &HStreamConfig) -> HResult<Self> {
+        let device = output_device(name)?;
+        let default_config = device.default_output_config()?;
+        let supported = supported_configs(&device)?;
+
+        let nchannels = config.nchannels.unwrap_or(default_config.channels());
+        let sr = config.sr.unwrap_or(default_config.sample_rate().0);
+        let idx = select_config(
+            &supported,
+            nchannels,
+            sr,
+            config.buffer_size,
+            HDeviceSampleFormat::try_from(default_config.sample_format()).ok(),
+        )
+        .ok_or_else(|| HError::Unsupported {
+            what: format!(
+                "the device does not support {} channels at {} Hz{}",
+                nchannels,
+                sr,
+                config
+                    .buffer_size
+                    .map(|x| format!(" with a buffer size of {x}"))
+                    .unwrap_or_default()
+            ),
+        })?;
+
+        let stream_config = StreamConfig {
+            channels: nchannels,
+            sample_rate: cpal::SampleRate(sr),
+            buffer_size: config
+                .buffer_size
+                .map_or(BufferSize::Default, BufferSize::Fixed),
+        };
+        let (sink, queue) = Sink::new_idle();
+        // The sounds are converted to the stream's format, as done by rodio's mixer.
+        let source = UniformSourceIterator::<_, f32>::new(queue, nchannels, sr);
+        let error = Arc::new(Mutex::new(None));
+
+        let stream = match supported[idx].sample_format {
+            HDeviceSampleFormat::I8 => build_stream::<i8>(&device, &stream_config, source, &error)?,
+            HDeviceSampleFormat::I16 => {
+                build_stream::<i16>(&device, &stream_config, source, &error)?
+            }
+            HDeviceSampleFormat::I32 => {
+                build_stream::<i32>(&device, &stream_config, source, &error)?
+            }
+            HDeviceSampleFormat::I64 => {
+                build_stream::<i64>(&device, &stream_config, source, &error)?
+            }
+            HDeviceSampleFormat::U8 => build_stream::<u8>(&device, &stream_config, source, &error)?,
+            HDeviceSampleFormat::U16 => {
+                build_stream::<u16>(&device, &stream_config, source, &error)?
+            }
+            HDeviceSampleFormat::U32 => {
+                build_stream::<u32>(&device, &stream_config, source, &error)?
+            }
+            HDeviceSampleFormat::U64 => {
+                build_stream::<u64>(&device, &stream_config, source, &error)?
+            }
+            HDeviceSampleFormat::F32 => {
+                build_stream::<f32>(&device, &stream_config, source, &error)?
+            }
+            HDeviceSampleFormat::F64 => {
+                build_stream::<f64>(&device, &stream_config, source, &error)?
+            }
+        };
+        stream.play()?;
+
+        Ok(HAudioSink {
+            sink,
+            output: SinkOutput::DeviceWithConfig {
+                _stream: stream,
+                error,
+            },
+            events: Arc::default(),
+        })
+    }
+
+    /// Creates a new offline `HAudioSink` instance, which is not attached to an audio device. The sink is set on "play"
+    /// mode from the start.
+    /// The queue advances as its output is rendered by `render`, `render_until_end` or `render_to_wav`, which mix the sounds
+    /// to `nchannels` channels at the `sr` sampling rate. Queueing, speed, volume, seeking and skipping behave as they
+    /// would on a device, so playback can be tested without one.
+    pub fn try_new_offline(nchannels: usize, sr: u32) -> HResult<Self> {
+        if nchannels == 0 || nchannels > usize::from(u16::MAX) {
+            return Err(HError::OutOfSpecError(
+                "nchannels must be between 1 and 65535".into(),
+            ));
+        }
+        if sr == 0 {
+            return Err(HError::OutOfSpecError(
+                "the sampling rate must be greater than 0".into(),
+            ));
+        }
+
+        let (sink, queue) = Sink::new_idle();
+
+        Ok(HAudioSink {
+            sink,
+            output: SinkOutput::Offline(Mutex::new(OfflineOutput::new(queue, nchannels, sr))),
+            events: Arc::default(),
+        })
+    }
+
+    /// Returns true if this sink is not attached to an audio device.
+    pub fn is_offline(&self) -> bool {
+        matches!(self.output, SinkOutput::Offline(_))
+    }
+
+    /// Renders the next `nframes` frames of an offline sink, with shape `(nchannels, nframes)`.
+    /// Silence is rendered while the queue is empty or the sink is paused.
+    pub fn render(&self, nframes: usize) -> HResult<HArray<f32, Ix2>> {
+        let mut output = self.offline()?;
+        let data = output.render(&self.sink, nframes);
+        output.to_harray(data)
+    }
+
+    /// Renders an offline sink until its queue is empty, with shape `(nchannels, nframes)`.
+    /// Returns an error if the sink is paused, since its sounds would never end.
+    pub fn render_until_end(&self) -> HResult<HArray<f32, Ix2>> {
+        let mut output = self.offline()?;
+        if self.sink.is_paused() && !self.sink.empty() {
+            return Err(HError::PlayError(
+                "cannot render a paused sink until the end".into(),
+            ));
+        }
+        let data = output.render_until_end(&self.sink);
+        output.to_harray(data)
+    }
+
+    /// Renders an offline sink until its queue is empty and writes the output to a WAV file.
+    pub fn render_to_wav(
+        &self,
+        fpath: &str,
+        sample_format: HSampleFormat,
+        out_of_range: HOutOfRange,
+    ) -> HResult<()> {
+        let harray = self.render_until_end()?;
+        let sr = self.offline()?.sr;
+        write_wav(fpath, &harray, sr, sample_format, out_of_range)
+    }
+
+    fn offline(&self) -> HResult<MutexGuard<'_, OfflineOutput>> {
+        match &self.output {
+            SinkOutput::Offline(output) => Ok(output.lock().unwrap()),
+            _ => Err(HError::Unsupported {
+                what: "rendering is only supported by offline sinks".into(),
+            }),
+        }
+    }
+
+    fn append<S>(&self, source: S)
+    where
+        S: Source<Item = f32> + Send + 'static,
+    {
+        if let SinkOutput::Offline(output) = &self.output {
+            // The sink waits for stopped sounds to end before appending, so they must be rendered first.
+            let mut output = output.lock().unwrap();
+            if output.stopped {
+                output.drain(&self.sink);
+                output.stopped = false;
+            }
+        }
+        let index = self.events.next_index.fetch_add(1, Ordering::Relaxed);
+        self.sink
+            .append(EventSource::new(source, index, self.events.clone()));
     }
 
     /// Appends a sound to the queue of sounds to play.
@@ -40,7 +249,7 @@ impl HAudioSink {
                     unsafe { x.to_f32().unwrap_unchecked() }
                 });
                 let source = SamplesBuffer::new(1, sr, ndarray.as_slice().unwrap());
-                self.sink.append(source);
+                self.append(source);
             }
             Audio::D2(harray) => {
                 let nchannels = harray.nchannels();
@@ -63,7 +272,7 @@ impl HAudioSink {
                     ndarray.as_slice().unwrap(),
                 );
 
-                self.sink.append(source);
+                self.append(source);
             }
             Audio::Dyn(harray) => {
                 assert!(harray.ndim() == 2);
@@ -87,22 +296,52 @@ impl HAudioSink {
                     ndarray.as_slice().unwrap(),
                 );
 
-                self.sink.append(source);
+                self.append(source);
             }
         }
     }
 
     /// Appends a sound to the queue of sounds to play.
+    /// The file is decoded while it is played, so playback starts right away and the memory used does not depend on the
+    /// length of the file.
     pub fn append_from_file(&self, fpath: &str) -> HResult<()> {
-        let (harray, sr) = decode::<f32>(fpath)?;
-        let audio = Audio::D2(&harray);
-        self.append_from_harray(&audio, sr);
+        self.append_from_input(HInput::from_file(fpath)?, None)
+    }
+
+    /// Appends a sound to the queue of sounds to play, decoding it while it is played.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input file, buffer or reader.
+    /// * `track_id` - The track to play. If `None`, the default track is used.
+    pub fn append_from_input(&self, input: HInput, track_id: Option<u32>) -> HResult<()> {
+        let source = HDecoderSource::try_new(input, track_id)?;
+        self.append(source);
         Ok(())
     }
 
+    /// Appends a mix to the queue of sounds to play. The mix is rendered while it is played.
+    pub fn append_from_mixer<T>(&self, mixer: &HMixer<T>)
+    where
+        T: Float + FloatConst + Sample + Send + Sync + 'static,
+    {
+        self.append(HMixerSource::new(mixer.clone()));
+    }
+
     /// Removes all currently loaded `Source`s from the `Sink` and pauses it.
     pub fn clear(&self) {
-        self.sink.clear()
+        match &self.output {
+            SinkOutput::Offline(output) => {
+                // The sink waits for the skipped sounds to end, so they are skipped and rendered here instead.
+                let mut output = output.lock().unwrap();
+                for _ in 0..self.sink.len() {
+                    self.sink.skip_one();
+                }
+                output.drain(&self.sink);
+                self.sink.pause();
+            }
+            _ => self.sink.clear(),
+        }
     }
 
     /// Destroys the sink without stopping the sounds that are still playing.
@@ -114,7 +353,11 @@ impl HAudioSink {
     /// This takes into account any speedup or delay applied.
     /// Example: if you apply a speedup of 2 to an mp3 decoder source and get_pos() returns 5s then the position in the mp3 recording is 10s from its start.
     pub fn get_pos(&self) -> Duration {
-        self.sink.get_pos()
+        match &self.output {
+            // Offline seeks are not seen by the sink, so the position is tracked by the sounds.
+            SinkOutput::Offline(_) => self.events.position.lock().unwrap().get(self.sink.speed()),
+            _ => self.sink.get_pos(),
+        }
     }
 
     /// Returns true if this sink has no more sounds to play.
@@ -146,6 +389,30 @@ impl HAudioSink {
         self.sink.play();
     }
 
+    /// Gets the interval between `HPlaybackEvent::Position` events. Returns `None` if they are disabled.
+    pub fn position_interval(&self) -> Option<Duration> {
+        match self.events.interval.load(Ordering::Relaxed) {
+            0 => None,
+            x => Some(Duration::from_nanos(x)),
+        }
+    }
+
+    /// Sets the interval between `HPlaybackEvent::Position` events, measured in the position of the sound being played.
+    /// `None` disables them, which is the default. The sound being played applies the change within about 5 milliseconds.
+    pub fn set_position_interval(&self, interval: Option<Duration>) -> HResult<()> {
+        let nanos = match interval {
+            Some(interval) if interval.is_zero() => {
+                return Err(HError::OutOfSpecError(
+                    "the position interval must be greater than 0".into(),
+                ))
+            }
+            Some(interval) => u64::try_from(interval.as_nanos()).unwrap_or(u64::MAX),
+            None => 0,
+        };
+        self.events.interval.store(nanos, Ordering::Relaxed);
+        Ok(())
+    }
+
     /// Changes the speed of the sound.
     /// The value 1.0 is the “normal” speed (unfiltered input). Any value other than 1.0 will change the play speed of the sound.
     pub fn set_speed(&self, value: f32) {
@@ -166,8 +433,17 @@ impl HAudioSink {
     }
 
     /// Sleeps the current thread until the sound ends.
+    /// An offline sink renders the remaining sounds instead, which are returned first by the next call to `render`. Nothing
+    /// is rendered if the sink is paused.
     pub fn sleep_until_end(&self) {
-        self.sink.sleep_until_end();
+        match &self.output {
+            SinkOutput::Offline(output) => {
+                if !self.sink.is_paused() {
+                    output.lock().unwrap().drain(&self.sink);
+                }
+            }
+            _ => self.sink.sleep_until_end(),
+        }
     }
 
     /// Gets the speed of the sound.
@@ -180,6 +456,32 @@ impl HAudioSink {
     /// The sink will keep its previous state (play or pause).
     pub fn stop(&self) {
         self.sink.stop();
+        if let SinkOutput::Offline(output) = &self.output {
+            output.lock().unwrap().stopped = true;
+        }
+    }
+
+    /// Returns a receiver of the playback events of this sink. Each receiver gets the events reported after its creation.
+    /// The events are sent by the thread that plays the sounds, or by the thread rendering an offline sink, without ever
+    /// blocking it. A receiver holds up to 1024 unread events, and newer events are dropped until it is read.
+    pub fn subscribe(&self) -> Receiver<HPlaybackEvent> {
+        let mut subscribers = self.events.subscribers.lock().unwrap();
+        // The events reported before the subscription go to the previous subscribers only.
+        subscribers.dispatch();
+        let (sender, receiver) = mpsc::sync_channel(EVENTS_CAPACITY);
+        subscribers.senders.push(sender);
+        receiver
+    }
+
+    /// Returns the last error reported by the audio stream of a sink created with `try_new_with_device`, such as the device
+    /// being disconnected, and clears it. Returns `None` if no error was reported since the last call, or for other sinks.
+    pub fn take_stream_error(&self) -> Option<HError> {
+        match &self.output {
+            SinkOutput::DeviceWithConfig { error, .. } => {
+                error.lock().unwrap().take().map(HError::from)
+            }
+            _ => None,
+        }
     }
 
     /// Attempts to seek to a given position in the current source.
@@ -191,8 +493,38 @@ impl HAudioSink {
     /// - one of the underlying sources does not support seeking.
     /// - an implementation ran into one during the seek.
     /// - when seeking beyond the end of a source when the duration of the source is not known.
+    ///
+    /// An offline sink applies the seek at the start of the next block of about 5 milliseconds of the sound being played, and
+    /// the frames rendered until then are returned first by the next call to `render`. If the offline sink is paused, the seek
+    /// is applied once it is resumed.
     pub fn try_seek(&self, pos: Duration) -> HResult<()> {
-        self.sink.try_seek(pos).map_err(HError::from)
+        let output = match &self.output {
+            SinkOutput::Offline(output) if !self.sink.empty() => output,
+            _ => return self.sink.try_seek(pos).map_err(HError::from),
+        };
+        let mut output = output.lock().unwrap();
+
+        *self.events.seek.lock().unwrap() = OfflineSeek::Requested(pos);
+        self.events.seek_requested.store(true, Ordering::Release);
+        loop {
+            {
+                let mut seek = self.events.seek.lock().unwrap();
+                match std::mem::take(&mut *seek) {
+                    OfflineSeek::Done(result) => return result.map_err(HError::from),
+                    requested => *seek = requested,
+                }
+                if self.sink.empty() {
+                    // The sounds ended before the seek could be applied.
+                    *seek = OfflineSeek::None;
+                    self.events.seek_requested.store(false, Ordering::Release);
+                    return Ok(());
+                }
+                if self.sink.is_paused() {
+                    return Ok(());
+                }
+            }
+            output.render_pending_frame(&self.sink);
+        }
     }
 
     /// Gets the volume of the sound.
@@ -202,6 +534,684 @@ impl HAudioSink {
     }
 }
 
+/// A rodio `Source` that decodes an audio input as it is played, block by block.
+/// Seeking is sample-exact, as in `DecoderStream::seek`.
+pub struct HDecoderSource {
+    stream: DecoderStream<f32>,
+    // Interleaved samples of the block being played.
+    block: Vec<f32>,
+    // Index of the next sample in `block`.
+    idx: usize,
+    // Number of zeros still to be returned to complete the frame interrupted by a seek.
+    pad: usize,
+}
+
+impl HDecoderSource {
+    // Number of frames decoded per block.
+    const BLOCK_FRAMES: usize = 4096;
+
+    /// Creates a new `HDecoderSource` for an input.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input file, buffer or reader.
+    /// * `track_id` - The track to play. If `None`, the default track is used.
+    pub fn try_new(input: HInput, track_id: Option<u32>) -> HResult<Self> {
+        let stream =
+            stream_from_input(input, Self::BLOCK_FRAMES, track_id)?.with_tail(HStreamTail::Partial);
+        if stream.nchannels() == 0 || stream.nchannels() > usize::from(u16::MAX) {
+            return Err(HError::Unsupported {
+                what: format!("cannot play {} channels", stream.nchannels()),
+            });
+        }
+
+        Ok(HDecoderSource {
+            stream,
+            block: Vec::new(),
+            idx: 0,
+            pad: 0,
+        })
+    }
+}
+
+impl Iterator for HDecoderSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.pad > 0 {
+            self.pad -= 1;
+            return Some(0.);
+        }
+
+        if self.idx == self.block.len() {
+            let harray = self.stream.next()?;
+            self.block.clear();
+            self.block.extend(harray.0.t().iter());
+            self.idx = 0;
+        }
+
+        let sample = self.block.get(self.idx).copied();
+        self.idx += 1;
+        sample
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.stream.nframes().saturating_sub(self.stream.position()) as usize
+            * self.stream.nchannels()
+            + self.block.len()
+            - self.idx
+            + self.pad;
+        (remaining, Some(remaining))
+    }
+}
+
+impl Source for HDecoderSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        // The number of channels was checked on creation.
+        self.stream.nchannels() as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.stream.sr()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f64(
+            self.stream.nframes() as f64 / f64::from(self.stream.sr()),
+        ))
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        // Seeking saturates at the end of the stream.
+        let frame = (pos.as_secs_f64() * f64::from(self.stream.sr())).round() as u64;
+        let frame = frame.min(self.stream.nframes());
+        self.stream
+            .seek(frame)
+            .map_err(|err| SeekError::Other(Box::new(err)))?;
+
+        // The samples after the seek must start at the same channel as they would have without it.
+        let nchannels = self.stream.nchannels();
+        let channel = if self.pad > 0 {
+            nchannels - self.pad
+        } else {
+            self.idx % nchannels
+        };
+        self.pad = (nchannels - channel) % nchannels;
+        self.block.clear();
+        self.idx = 0;
+        Ok(())
+    }
+}
+
+/// A rodio `Source` that renders an `HMixer` as it is played, block by block.
+pub struct HMixerSource<T> {
+    mixer: HMixer<T>,
+    // Index of the first frame of the next block.
+    frame: u64,
+    // Interleaved samples of the block being played.
+    block: Vec<f32>,
+    // Index of the next sample in `block`.
+    idx: usize,
+    // Number of zeros still to be returned to complete the frame interrupted by a seek.
+    pad: usize,
+}
+
+impl<T> HMixerSource<T>
+where
+    T: Float + FloatConst + Sample,
+{
+    // Number of frames rendered per block.
+    const BLOCK_FRAMES: u64 = 4096;
+
+    /// Creates a new `HMixerSource` that plays the mix from its start.
+    pub fn new(mixer: HMixer<T>) -> Self {
+        HMixerSource {
+            mixer,
+            frame: 0,
+            block: Vec::new(),
+            idx: 0,
+            pad: 0,
+        }
+    }
+}
+
+impl<T> Iterator for HMixerSource<T>
+where
+    T: Float + FloatConst + Sample,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.pad > 0 {
+            self.pad -= 1;
+            return Some(0.);
+        }
+
+        if self.idx == self.block.len() {
+            let nframes = Self::BLOCK_FRAMES.min(self.mixer.nframes().saturating_sub(self.frame));
+            if nframes == 0 {
+                return None;
+            }
+            let harray = self.mixer.render_range(self.frame, nframes as usize);
+            self.block.clear();
+            self.block.extend(harray.0.t().iter().map(|x| {
+                // This should not panic since it is a conversion from f32 or f64.
+                unsafe { x.to_f32().unwrap_unchecked() }
+            }));
+            self.frame += nframes;
+            self.idx = 0;
+        }
+
+        let sample = self.block.get(self.idx).copied();
+        self.idx += 1;
+        sample
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.mixer.nframes().saturating_sub(self.frame) as usize
+            * self.mixer.nchannels()
+            + self.block.len()
+            - self.idx
+            + self.pad;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T> Source for HMixerSource<T>
+where
+    T: Float + FloatConst + Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        // The number of channels is checked by the mixer.
+        self.mixer.nchannels() as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.mixer.sr()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f64(
+            self.mixer.nframes() as f64 / f64::from(self.mixer.sr()),
+        ))
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        // Seeking saturates at the end of the mix.
+        let frame = (pos.as_secs_f64() * f64::from(self.mixer.sr())).round() as u64;
+        self.frame = frame.min(self.mixer.nframes());
+
+        // The samples after the seek must start at the same channel as they would have without it.
+        let nchannels = self.mixer.nchannels();
+        let channel = if self.pad > 0 {
+            nchannels - self.pad
+        } else {
+            self.idx % nchannels
+        };
+        self.pad = (nchannels - channel) % nchannels;
+        self.block.clear();
+        self.idx = 0;
+        Ok(())
+    }
+}
+
+/// An event reported by an `HAudioSink` to its subscribers.
+/// `index` identifies the sound by the order in which it was appended to the sink, starting at 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HPlaybackEvent {
+    /// The sound started playing.
+    Started { index: usize },
+    /// The sound finished playing, or was removed from the queue after it started.
+    Ended { index: usize },
+    /// The position of the sound being played. Reported every position interval and after seeking.
+    Position { index: usize, pos: Duration },
+}
+
+/// The maximum number of events waiting to be dispatched, and waiting in each receiver.
+const EVENTS_CAPACITY: usize = 1024;
+
+/// The playback events state shared by a sink and its sounds.
+struct PlaybackEvents {
+    // Events reported by the sounds, waiting to be dispatched to the subscribers.
+    pending: SyncSender<HPlaybackEvent>,
+    subscribers: Mutex<Subscribers>,
+    // Interval between position events in nanoseconds, or 0 if they are disabled.
+    interval: AtomicU64,
+    // Index of the next sound to be appended.
+    next_index: AtomicUsize,
+    // Seek requested by an offline sink, applied by the sound being played at the start of its next block.
+    seek: Mutex<OfflineSeek>,
+    seek_requested: AtomicBool,
+    // Position of the sound being played, updated once per block. Used by offline sinks, whose seeks are not seen by the
+    // position tracked by the sink.
+    position: Mutex<SoundPosition>,
+}
+
+struct Subscribers {
+    pending: Receiver<HPlaybackEvent>,
+    senders: Vec<SyncSender<HPlaybackEvent>>,
+}
+
+impl Default for PlaybackEvents {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::sync_channel(EVENTS_CAPACITY);
+        PlaybackEvents {
+            pending: sender,
+            subscribers: Mutex::new(Subscribers {
+                pending: receiver,
+                senders: Vec::new(),
+            }),
+            interval: AtomicU64::new(0),
+            next_index: AtomicUsize::new(0),
+            seek: Mutex::default(),
+            seek_requested: AtomicBool::new(false),
+            position: Mutex::default(),
+        }
+    }
+}
+
+impl PlaybackEvents {
+    // Called by the thread playing the sounds, so it never blocks. If `subscribe` holds the lock, the event is dispatched
+    // with the next one.
+    fn send(&self, event: HPlaybackEvent) {
+        let _ = self.pending.try_send(event);
+        if let Ok(mut subscribers) = self.subscribers.try_lock() {
+            subscribers.dispatch();
+        }
+    }
+}
+
+#[derive(Default)]
+enum OfflineSeek {
+    #[default]
+    None,
+    Requested(Duration),
+    Done(Result<(), SeekError>),
+}
+
+/// The position of a sound as tracked by the sink: the position of the last seek plus the time played since then.
+#[derive(Default)]
+struct SoundPosition {
+    seek_pos: Duration,
+    // Number of samples played since the last seek.
+    nsamples: u64,
+    samples_per_sec: u64,
+}
+
+impl SoundPosition {
+    fn get(&self, speed: f32) -> Duration {
+        let secs = self.nsamples as f64 / (self.samples_per_sec as f64 * f64::from(speed));
+        self.seek_pos + Duration::try_from_secs_f64(secs).unwrap_or_default()
+    }
+}
+
+impl Subscribers {
+    fn dispatch(&mut self) {
+        for event in self.pending.try_iter() {
+            // Subscribers whose receiver was dropped are removed, and full receivers miss the event.
+            self.senders.retain(|sender| {
+                !matches!(sender.try_send(event), Err(TrySendError::Disconnected(_)))
+            });
+        }
+    }
+}
+
+/// Wraps a sound appended to a sink to report its playback events.
+struct EventSource<S> {
+    inner: S,
+    index: usize,
+    events: Arc<PlaybackEvents>,
+    started: bool,
+    ended: bool,
+    // Number of samples played, including the ones skipped by seeking.
+    nsamples: u64,
+    // Position interval in nanoseconds used to compute `next_tick`.
+    interval: u64,
+    // Number of samples at which the next position event is reported.
+    next_tick: u64,
+    // Number of samples left in the current block. The shared state is read once per block.
+    block_left: u64,
+    // Position of the last seek, and `nsamples` at that time.
+    seek_pos: Duration,
+    seek_nsamples: u64,
+}
+
+impl<S> EventSource<S>
+where
+    S: Source<Item = f32>,
+{
+    fn new(inner: S, index: usize, events: Arc<PlaybackEvents>) -> Self {
+        EventSource {
+            inner,
+            index,
+            events,
+            started: false,
+            ended: false,
+            nsamples: 0,
+            interval: 0,
+            next_tick: u64::MAX,
+            block_left: 0,
+            seek_pos: Duration::ZERO,
+            seek_nsamples: 0,
+        }
+    }
+
+    fn samples_per_sec(&self) -> u128 {
+        (u128::from(self.inner.sample_rate()) * u128::from(self.inner.channels())).max(1)
+    }
+
+    fn pos(&self) -> Duration {
+        let nframes = self.nsamples / u64::from(self.inner.channels().max(1));
+        Duration::from_secs_f64(nframes as f64 / f64::from(self.inner.sample_rate().max(1)))
+    }
+
+    // The number of samples at the first multiple of the position interval after `nsamples`.
+    fn tick_after(&self, nsamples: u64) -> u64 {
+        if self.interval == 0 {
+            return u64::MAX;
+        }
+        let interval = u128::from(self.interval);
+        let nanos = u128::from(nsamples) * 1_000_000_000 / self.samples_per_sec();
+        let tick = (nanos / interval + 1) * interval;
+        u64::try_from((tick * self.samples_per_sec()).div_ceil(1_000_000_000)).unwrap_or(u64::MAX)
+    }
+
+    // Starts a block of about 5 milliseconds, made of whole frames.
+    fn start_block(&mut self) {
+        let interval = self.events.interval.load(Ordering::Relaxed);
+        if interval != self.interval {
+            self.interval = interval;
+            self.next_tick = self.tick_after(self.nsamples);
+        }
+        if self.events.seek_requested.load(Ordering::Acquire) {
+            self.apply_offline_seek();
+        }
+        let channels = u64::from(self.inner.channels().max(1));
+        self.block_left = (u64::from(self.inner.sample_rate()) / 200).max(1) * channels;
+        self.update_position();
+    }
+
+    fn apply_offline_seek(&mut self) {
+        let events = self.events.clone();
+        let Ok(mut seek) = events.seek.try_lock() else {
+            return;
+        };
+        if let OfflineSeek::Requested(pos) = *seek {
+            *seek = OfflineSeek::Done(self.try_seek(pos));
+            events.seek_requested.store(false, Ordering::Release);
+        }
+    }
+
+    fn update_position(&self) {
+        if let Ok(mut position) = self.events.position.try_lock() {
+            *position = SoundPosition {
+                seek_pos: self.seek_pos,
+                nsamples: self.nsamples - self.seek_nsamples,
+                samples_per_sec: self.samples_per_sec() as u64,
+            };
+        }
+    }
+
+    fn report_position(&mut self) {
+        self.events.send(HPlaybackEvent::Position {
+            index: self.index,
+            pos: self.pos(),
+        });
+        self.next_tick = self.tick_after(self.nsamples);
+    }
+}
+
+impl<S> Iterator for EventSource<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if !self.started {
+            self.started = true;
+            self.events
+                .send(HPlaybackEvent::Started { index: self.index });
+        }
+
+        if self.block_left == 0 {
+            self.start_block();
+        }
+        self.block_left -= 1;
+
+        match self.inner.next() {
+            Some(sample) => {
+                self.nsamples += 1;
+                if self.nsamples >= self.next_tick {
+                    self.report_position();
+                }
+                Some(sample)
+            }
+            None => {
+                if !self.ended {
+                    self.ended = true;
+                    self.events
+                        .send(HPlaybackEvent::Ended { index: self.index });
+                }
+                None
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S> Source for EventSource<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)?;
+
+        let pos = self.inner.total_duration().map_or(pos, |x| pos.min(x));
+        let nframes = (pos.as_secs_f64() * f64::from(self.inner.sample_rate())).round() as u64;
+        self.nsamples = nframes * u64::from(self.inner.channels());
+        self.seek_pos = pos;
+        self.seek_nsamples = self.nsamples;
+        self.interval = self.events.interval.load(Ordering::Relaxed);
+        self.next_tick = self.tick_after(self.nsamples);
+        // The seek starts a new block, keeping the blocks aligned with the frames.
+        self.block_left = 0;
+        if self.started && self.interval != 0 {
+            self.report_position();
+        }
+        Ok(())
+    }
+}
+
+impl<S> Drop for EventSource<S> {
+    fn drop(&mut self) {
+        // Sounds removed from the queue by skipping, stopping or clearing are dropped without ending.
+        if self.started && !self.ended {
+            self.events
+                .send(HPlaybackEvent::Ended { index: self.index });
+        }
+        // No sound is being played until the next one starts.
+        if self.started {
+            if let Ok(mut position) = self.events.position.try_lock() {
+                *position = SoundPosition::default();
+            }
+        }
+    }
+}
+
+/// The output of an offline sink. Frames are pulled from the sink's queue and linearly interpolated to the output number of
+/// channels and sampling rate, which also applies the speed set on the sink.
+struct OfflineOutput {
+    queue: SourcesQueueOutput<f32>,
+    nchannels: usize,
+    sr: u32,
+    // Interleaved frames rendered while driving the sink, returned first by the next render.
+    pending: Vec<f32>,
+    // The frames being interpolated.
+    prev: OfflineFrame,
+    next: OfflineFrame,
+    // The position between `prev` and `next`.
+    pos: f64,
+    // Whether the sink was stopped since the last sound was appended.
+    stopped: bool,
+}
+
+struct OfflineFrame {
+    samples: Vec<f32>,
+    // The sampling rate of the sound the frame belongs to, with the speed applied.
+    sr: u32,
+    // Whether the frame belongs to a sound in the queue.
+    live: bool,
+}
+
+impl OfflineFrame {
+    fn silence(nchannels: usize, sr: u32) -> Self {
+        OfflineFrame {
+            samples: vec![0.; nchannels],
+            sr,
+            live: false,
+        }
+    }
+}
+
+impl OfflineOutput {
+    fn new(queue: SourcesQueueOutput<f32>, nchannels: usize, sr: u32) -> Self {
+        OfflineOutput {
+            queue,
+            nchannels,
+            sr,
+            pending: Vec::new(),
+            prev: OfflineFrame::silence(nchannels, sr),
+            next: OfflineFrame::silence(nchannels, sr),
+            // Both frames are pulled before the first one is rendered.
+            pos: 2.,
+            stopped: false,
+        }
+    }
+
+    fn render(&mut self, sink: &Sink, nframes: usize) -> Vec<f32> {
+        let npending = (self.pending.len() / self.nchannels).min(nframes);
+        let mut data: Vec<f32> = self.pending.drain(..npending * self.nchannels).collect();
+        data.reserve((nframes - npending) * self.nchannels);
+        for _ in npending..nframes {
+            self.advance(sink);
+            self.interpolate(&mut data);
+        }
+        data
+    }
+
+    fn render_until_end(&mut self, sink: &Sink) -> Vec<f32> {
+        let mut data = std::mem::take(&mut self.pending);
+        loop {
+            self.advance(sink);
+            if !self.prev.live && sink.empty() {
+                break;
+            }
+            self.interpolate(&mut data);
+        }
+        data
+    }
+
+    // Renders the remaining sounds to the pending frames.
+    fn drain(&mut self, sink: &Sink) {
+        self.pending = self.render_until_end(sink);
+    }
+
+    fn render_pending_frame(&mut self, sink: &Sink) {
+        let mut pending = std::mem::take(&mut self.pending);
+        self.advance(sink);
+        self.interpolate(&mut pending);
+        self.pending = pending;
+    }
+
+    fn advance(&mut self, sink: &Sink) {
+        while self.pos >= 1. {
+            std::mem::swap(&mut self.prev, &mut self.next);
+            self.pull(sink);
+            self.pos -= 1.;
+        }
+    }
+
+    fn interpolate(&mut self, data: &mut Vec<f32>) {
+        let pos = self.pos as f32;
+        for (a, b) in self.prev.samples.iter().zip(self.next.samples.iter()) {
+            data.push(a + (b - a) * pos);
+        }
+        self.pos += f64::from(self.prev.sr) / f64::from(self.sr);
+    }
+
+    // Pulls a frame from the queue into `next`, converting it to the output number of channels.
+    fn pull(&mut self, sink: &Sink) {
+        let frame = &mut self.next;
+        frame.samples.fill(0.);
+        frame.sr = self.sr;
+        frame.live = false;
+
+        if sink.empty() {
+            return;
+        }
+        let Some(first) = self.queue.next() else {
+            return;
+        };
+        // Pulling a frame can end the last sound, in which case the sample belongs to the silence played by the queue
+        // while it is empty.
+        if sink.empty() {
+            return;
+        }
+
+        let nchannels = usize::from(self.queue.channels());
+        frame.sr = self.queue.sample_rate();
+        frame.live = true;
+
+        if nchannels == 1 {
+            frame.samples.fill(first);
+            return;
+        }
+        frame.samples[0] = first;
+        for ch in 1..nchannels {
+            let sample = self.queue.next().unwrap_or(0.);
+            if ch < self.nchannels {
+                frame.samples[ch] = sample;
+            }
+        }
+    }
+
+    fn to_harray(&self, data: Vec<f32>) -> HResult<HArray<f32, Ix2>> {
+        let nframes = data.len() / self.nchannels;
+        let harray = HArray::<f32, Ix2>::new_from_shape_vec((nframes, self.nchannels), data)?;
+        Ok(HArray(
+            harray.0.t().as_standard_layout().into_owned().into_shared(),
+        ))
+    }
+}
+
 /// Returns a list of available audio output devices.
 pub fn audio_output_devices() -> HResult<Vec<String>> {
     let host = rodio::cpal::default_host();
@@ -223,39 +1233,191 @@ pub fn audio_default_device() -> HResult<String> {
 }
 
 /// Returns the supported configurations for the default audio output device.
-/// The following informations are provided:
-/// * Number of channels.
-/// * Minimum and maximum value for the sampling rate.
-/// * Minimum and maximum value for the buffer size.
-/// * Type of data expected by the device.
-pub fn audio_supported_configs() -> HResult<Vec<String>> {
-    let host = rodio::cpal::default_host();
+/// Configurations with a sample format unknown to `HDeviceSampleFormat` are skipped.
+pub fn audio_supported_configs() -> HResult<Vec<HSupportedConfig>> {
+    let host = cpal::default_host();
     let device = host
         .default_output_device()
         .ok_or_else(|| HError::PlayError("cannot get the default output device".into()))?;
-    let supported_configs = device.supported_output_configs()?;
-    let mut strings: Vec<String> = Vec::with_capacity(supported_configs.size_hint().0);
-    for x in supported_configs {
-        let buffer_size = match x.buffer_size() {
-            SupportedBufferSize::Range { min: x, max: y } => format!("({x}, {y})"),
-            SupportedBufferSize::Unknown => "Unknown".into(),
+    supported_configs(&device)
+}
+
+/// Returns the supported configurations for the output device named `name`, as listed by `audio_output_devices`.
+/// Configurations with a sample format unknown to `HDeviceSampleFormat` are skipped.
+pub fn audio_device_supported_configs(name: &str) -> HResult<Vec<HSupportedConfig>> {
+    supported_configs(&output_device(name)?)
+}
+
+fn supported_configs(device: &cpal::Device) -> HResult<Vec<HSupportedConfig>> {
+    Ok(device
+        .supported_output_configs()?
+        .filter_map(|x| HSupportedConfig::try_from(&x).ok())
+        .collect())
+}
+
+/// The type of the samples expected by an audio output device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HDeviceSampleFormat {
+    /// 8-bit signed integer.
+    I8,
+    /// 16-bit signed integer.
+    I16,
+    /// 32-bit signed integer.
+    I32,
+    /// 64-bit signed integer.
+    I64,
+    /// 8-bit unsigned integer.
+    U8,
+    /// 16-bit unsigned integer.
+    U16,
+    /// 32-bit unsigned integer.
+    U32,
+    /// 64-bit unsigned integer.
+    U64,
+    /// 32-bit float.
+    F32,
+    /// 64-bit float.
+    F64,
+}
+
+impl TryFrom<SampleFormat> for HDeviceSampleFormat {
+    type Error = HError;
+
+    fn try_from(sample_format: SampleFormat) -> HResult<Self> {
+        match sample_format {
+            SampleFormat::I8 => Ok(HDeviceSampleFormat::I8),
+            SampleFormat::I16 => Ok(HDeviceSampleFormat::I16),
+            SampleFormat::I32 => Ok(HDeviceSampleFormat::I32),
+            SampleFormat::I64 => Ok(HDeviceSampleFormat::I64),
+            SampleFormat::U8 => Ok(HDeviceSampleFormat::U8),
+            SampleFormat::U16 => Ok(HDeviceSampleFormat::U16),
+            SampleFormat::U32 => Ok(HDeviceSampleFormat::U32),
+            SampleFormat::U64 => Ok(HDeviceSampleFormat::U64),
+            SampleFormat::F32 => Ok(HDeviceSampleFormat::F32),
+            SampleFormat::F64 => Ok(HDeviceSampleFormat::F64),
+            x => Err(HError::Unsupported {
+                what: format!("the {x} sample format is not supported"),
+            }),
+        }
+    }
+}
+
+/// A range of configurations supported by an audio output device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HSupportedConfig {
+    /// Number of channels.
+    pub nchannels: u16,
+    /// Minimum sampling rate in Hz.
+    pub min_sr: u32,
+    /// Maximum sampling rate in Hz.
+    pub max_sr: u32,
+    /// Minimum and maximum buffer size in frames, if known.
+    pub buffer_size: Option<(u32, u32)>,
+    /// Type of data expected by the device.
+    pub sample_format: HDeviceSampleFormat,
+}
+
+impl TryFrom<&cpal::SupportedStreamConfigRange> for HSupportedConfig {
+    type Error = HError;
+
+    fn try_from(config: &cpal::SupportedStreamConfigRange) -> HResult<Self> {
+        let buffer_size = match config.buffer_size() {
+            SupportedBufferSize::Range { min, max } => Some((*min, *max)),
+            SupportedBufferSize::Unknown => None,
         };
-        let s = format!(
-            "[channels: {}, sample_rate(min, max): ({}, {}), buffer_size(min, max): {}, sample_format: {}]",
-            x.channels(),
-            x.min_sample_rate().0,
-            x.max_sample_rate().0,
+        Ok(HSupportedConfig {
+            nchannels: config.channels(),
+            min_sr: config.min_sample_rate().0,
+            max_sr: config.max_sample_rate().0,
             buffer_size,
-            x.sample_format()
-        );
-        strings.push(s);
+            sample_format: HDeviceSampleFormat::try_from(config.sample_format())?,
+        })
     }
-    Ok(strings)
+}
+
+/// The stream configuration used to open an audio output device. Unset fields are taken from the device's default
+/// configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HStreamConfig {
+    /// Number of channels.
+    pub nchannels: Option<u16>,
+    /// Sampling rate in Hz.
+    pub sr: Option<u32>,
+    /// Buffer size in frames. The device's default buffer size is used if unset.
+    pub buffer_size: Option<u32>,
+}
+
+fn output_device(name: &str) -> HResult<cpal::Device> {
+    let host = cpal::default_host();
+    for device in host.output_devices()? {
+        if device.name()? == name {
+            return Ok(device);
+        }
+    }
+    Err(HError::PlayError(
+        format!("cannot find the output device {name}").into(),
+    ))
+}
+
+// Returns the index of the first configuration supporting the number of channels, sampling rate and buffer size,
+// preferring the default sample format.
+fn select_config(
+    supported: &[HSupportedConfig],
+    nchannels: u16,
+    sr: u32,
+    buffer_size: Option<u32>,
+    default_sample_format: Option<HDeviceSampleFormat>,
+) -> Option<usize> {
+    let matches = |x: &HSupportedConfig| {
+        x.nchannels == nchannels
+            && (x.min_sr..=x.max_sr).contains(&sr)
+            && match (buffer_size, x.buffer_size) {
+                (Some(size), Some((min, max))) => (min..=max).contains(&size),
+                _ => true,
+            }
+    };
+    supported
+        .iter()
+        .position(|x| matches(x) && Some(x.sample_format) == default_sample_format)
+        .or_else(|| supported.iter().position(matches))
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut source: UniformSourceIterator<SourcesQueueOutput<f32>, f32>,
+    error: &Arc<Mutex<Option<cpal::StreamError>>>,
+) -> HResult<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let error = error.clone();
+    let stream = device.build_output_stream::<T, _, _>(
+        config,
+        move |data, _| {
+            for x in data.iter_mut() {
+                *x = T::from_sample(source.next().unwrap_or(0.));
+            }
+        },
+        move |err| {
+            // Only the last error is kept.
+            if let Ok(mut error) = error.lock() {
+                *error = Some(err);
+            }
+        },
+        None,
+    )?;
+    Ok(stream)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        decode::{decode, HTime},
+        mix::HMixOptions,
+    };
+    use ndarray::{s, Array2};
 
     #[test]
     fn play_test() {
@@ -313,10 +1475,329 @@ mod tests {
         assert_eq!(sink.len(), 1);
     }
 
+    #[test]
+    fn decoder_source_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.flac";
+        let (harray, sr) = decode::<f32>(fpath, None).unwrap();
+        let interleaved: Vec<f32> = harray.0.t().iter().copied().collect();
+
+        let source = HDecoderSource::try_new(HInput::from_file(fpath).unwrap(), None).unwrap();
+        assert_eq!(source.channels(), 2);
+        assert_eq!(source.sample_rate(), sr);
+        assert_eq!(source.size_hint().0, interleaved.len());
+        assert_eq!(source.collect::<Vec<f32>>(), interleaved);
+
+        // Seeking in the middle of a frame keeps the channels aligned.
+        let mut source = HDecoderSource::try_new(HInput::from_file(fpath).unwrap(), None).unwrap();
+        assert_eq!(source.next(), Some(interleaved[0]));
+        source.try_seek(Duration::from_secs(1)).unwrap();
+        assert_eq!(source.size_hint().0, interleaved.len() - 2 * 44100 + 1);
+        assert_eq!(source.next(), Some(0.));
+        let samples: Vec<f32> = source.by_ref().take(10).collect();
+        assert_eq!(samples, interleaved[2 * 44100..2 * 44100 + 10]);
+        source.try_seek(Duration::from_secs(1000)).unwrap();
+        assert_eq!(source.next(), None);
+    }
+
+    #[test]
+    fn offline_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.wav";
+        let fpath_mono = "../testfiles/gs-16b-1c-44100hz.wav";
+        let (harray, _) = decode::<f32>(fpath, None).unwrap();
+        let (harray_mono, _) = decode::<f32>(fpath_mono, None).unwrap();
+        let nframes = harray.nframes();
+
+        // Rendering follows the queue.
+        let sink = HAudioSink::try_new_offline(2, 44100).unwrap();
+        assert!(sink.is_offline());
+        assert!(sink.take_stream_error().is_none());
+        assert!(HAudioSink::try_new_offline(0, 44100).is_err());
+        sink.append_from_file(fpath).unwrap();
+        sink.append_from_file(fpath_mono).unwrap();
+        assert_eq!(sink.len(), 2);
+        let rendered = sink.render(1000).unwrap();
+        assert_eq!(rendered.0, harray.0.slice(s![.., ..1000]));
+        let rendered = sink.render_until_end().unwrap();
+        assert_eq!(rendered.nframes(), nframes - 1000 + harray_mono.nframes());
+        assert_eq!(
+            rendered.0.slice(s![.., ..10]),
+            harray.0.slice(s![.., 1000..1010])
+        );
+        // The mono sound is played on both channels.
+        let mono = rendered.0.slice(s![.., nframes - 1000..]);
+        assert_eq!(mono.row(0), harray_mono.0.row(0));
+        assert_eq!(mono.row(1), harray_mono.0.row(0));
+        assert!(sink.is_empty());
+        assert_eq!(sink.render(10).unwrap().0, Array2::<f32>::zeros((2, 10)));
+
+        // Skipping.
+        let sink = HAudioSink::try_new_offline(2, 44100).unwrap();
+        sink.append_from_file(fpath_mono).unwrap();
+        sink.append_from_file(fpath).unwrap();
+        sink.skip_one();
+        let rendered = sink.render_until_end().unwrap();
+        assert_eq!(rendered.0, harray.0);
+
+        // Speed and volume.
+        let sink = HAudioSink::try_new_offline(2, 44100).unwrap();
+        sink.set_speed(2.);
+        sink.set_volume(0.5);
+        sink.append_from_file(fpath).unwrap();
+        let rendered = sink.render_until_end().unwrap();
+        assert!(rendered.nframes().abs_diff(nframes / 2) <= 1);
+        assert_eq!(rendered.0[[0, 100]], harray.0[[0, 200]] * 0.5);
+
+        // Seeking.
+        let sink = HAudioSink::try_new_offline(2, 44100).unwrap();
+        sink.append_from_file(fpath).unwrap();
+        sink.try_seek(Duration::from_secs(10)).unwrap();
+        let rendered = sink.render(44100).unwrap();
+        assert_eq!(rendered.0, harray.0.slice(s![.., 441000..485100]));
+        // The position is updated every 5 milliseconds.
+        let pos = sink.get_pos().as_secs_f64();
+        assert!((pos - 11.).abs() < 0.005);
+        let rendered = sink.render_until_end().unwrap();
+        assert_eq!(rendered.nframes(), nframes - 485100);
+        assert_eq!(sink.get_pos(), Duration::ZERO);
+        assert!(sink.try_seek(Duration::from_secs(1)).is_ok());
+
+        // Seeking while playing applies the seek at the start of the next 5 milliseconds block.
+        let sink = HAudioSink::try_new_offline(2, 44100).unwrap();
+        sink.append_from_file(fpath).unwrap();
+        sink.render(1000).unwrap();
+        sink.try_seek(Duration::from_secs(10)).unwrap();
+        let rendered = sink.render_until_end().unwrap();
+        assert_eq!(rendered.nframes(), 100 + nframes - 441000);
+        assert_eq!(
+            rendered.0.slice(s![.., ..100]),
+            harray.0.slice(s![.., 1000..1100])
+        );
+        assert_eq!(
+            rendered.0.slice(s![.., 100..110]),
+            harray.0.slice(s![.., 441000..441010])
+        );
+
+        // Pausing, stopping and clearing.
+        let sink = HAudioSink::try_new_offline(1, 22050).unwrap();
+        sink.append_from_file(fpath).unwrap();
+        sink.pause();
+        assert!(sink.render_until_end().is_err());
+        assert_eq!(sink.render(10).unwrap().0, Array2::<f32>::zeros((1, 10)));
+        sink.play();
+        sink.stop();
+        sink.append_from_file(fpath_mono).unwrap();
+        assert_eq!(sink.len(), 1);
+        sink.clear();
+        assert!(sink.is_empty());
+        assert!(sink.is_paused());
+
+        // Rendering to a file.
+        let sink = HAudioSink::try_new_offline(2, 22050).unwrap();
+        sink.append_from_file(fpath).unwrap();
+        let out = std::env::temp_dir()
+            .join(format!("harmonium_{}_offline.wav", std::process::id()))
+            .to_str()
+            .unwrap()
+            .to_string();
+        sink.render_to_wav(&out, HSampleFormat::Float32, HOutOfRange::Error)
+            .unwrap();
+        let (rendered, sr) = decode::<f32>(&out, None).unwrap();
+        std::fs::remove_file(&out).unwrap();
+        assert_eq!(sr, 22050);
+        assert!(rendered.nframes().abs_diff(nframes / 2) <= 1);
+    }
+
+    #[test]
+    fn mixer_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.wav";
+        let fpath_mono = "../testfiles/gs-16b-1c-44100hz.wav";
+        let (harray, sr) = decode::<f32>(fpath, None).unwrap();
+        let (harray_mono, _) = decode::<f32>(fpath_mono, None).unwrap();
+
+        let mut mixer = HMixer::<f32>::new(2, sr).unwrap();
+        mixer.add(&harray, sr, HMixOptions::default()).unwrap();
+        let options = HMixOptions {
+            gain: 0.5,
+            pan: -0.5,
+            offset: HTime::Seconds(1.5),
+        };
+        mixer.add(&harray_mono, sr, options).unwrap();
+        let mix = mixer.render();
+
+        // The source is the mix, interleaved.
+        let interleaved: Vec<f32> = mix.0.t().iter().copied().collect();
+        let source = HMixerSource::new(mixer.clone());
+        assert_eq!(source.channels(), 2);
+        assert_eq!(source.size_hint().0, interleaved.len());
+        assert_eq!(source.collect::<Vec<f32>>(), interleaved);
+
+        let mut source = HMixerSource::new(mixer.clone());
+        assert_eq!(source.next(), Some(interleaved[0]));
+        source.try_seek(Duration::from_secs(2)).unwrap();
+        assert_eq!(source.next(), Some(0.));
+        let samples: Vec<f32> = source.by_ref().take(10).collect();
+        assert_eq!(samples, interleaved[2 * 88200..2 * 88200 + 10]);
+
+        // Played by an offline sink.
+        let sink = HAudioSink::try_new_offline(2, sr).unwrap();
+        sink.append_from_mixer(&mixer);
+        assert_eq!(sink.render_until_end().unwrap(), mix);
+    }
+
+    #[test]
+    fn events_test() {
+        let fpath = "../testfiles/gs-16b-2c-44100hz.wav";
+        let fpath_mono = "../testfiles/gs-16b-1c-44100hz.wav";
+        let (harray, _) = decode::<f32>(fpath, None).unwrap();
+        let (harray_mono, _) = decode::<f32>(fpath_mono, None).unwrap();
+
+        // Sounds report their start, end and position at every interval.
+        let sink = HAudioSink::try_new_offline(2, 44100).unwrap();
+        let receiver = sink.subscribe();
+        assert_eq!(sink.position_interval(), None);
+        assert!(sink.set_position_interval(Some(Duration::ZERO)).is_err());
+        sink.set_position_interval(Some(Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(sink.position_interval(), Some(Duration::from_secs(1)));
+        sink.append_from_file(fpath).unwrap();
+        sink.append_from_file(fpath_mono).unwrap();
+        sink.render_until_end().unwrap();
+
+        let mut expected = Vec::new();
+        for (index, nframes) in [harray.nframes(), harray_mono.nframes()]
+            .into_iter()
+            .enumerate()
+        {
+            expected.push(HPlaybackEvent::Started { index });
+            for secs in 1..=(nframes / 44100) as u64 {
+                expected.push(HPlaybackEvent::Position {
+                    index,
+                    pos: Duration::from_secs(secs),
+                });
+            }
+            expected.push(HPlaybackEvent::Ended { index });
+        }
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), expected);
+
+        // Skipped sounds end, and seeking reports the new position.
+        let sink = HAudioSink::try_new_offline(2, 44100).unwrap();
+        let receiver = sink.subscribe();
+        sink.append_from_file(fpath).unwrap();
+        sink.append_from_file(fpath_mono).unwrap();
+        sink.render(10).unwrap();
+        // The skip is applied by the sink every 5 milliseconds of output.
+        sink.skip_one();
+        sink.render(1000).unwrap();
+        sink.set_position_interval(Some(Duration::from_secs(100)))
+            .unwrap();
+        sink.try_seek(Duration::from_secs(2)).unwrap();
+        sink.set_position_interval(None).unwrap();
+        sink.render_until_end().unwrap();
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![
+                HPlaybackEvent::Started { index: 0 },
+                HPlaybackEvent::Ended { index: 0 },
+                HPlaybackEvent::Started { index: 1 },
+                HPlaybackEvent::Position {
+                    index: 1,
+                    pos: Duration::from_secs(2)
+                },
+                HPlaybackEvent::Ended { index: 1 },
+            ]
+        );
+
+        // Sounds are indexed in the order they were appended, and dropped receivers are ignored.
+        drop(receiver);
+        let receiver = sink.subscribe();
+        sink.append_from_file(fpath_mono).unwrap();
+        sink.render_until_end().unwrap();
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![
+                HPlaybackEvent::Started { index: 2 },
+                HPlaybackEvent::Ended { index: 2 },
+            ]
+        );
+        // Receivers that are not read keep the oldest events.
+        let receiver = sink.subscribe();
+        sink.set_position_interval(Some(Duration::from_millis(1)))
+            .unwrap();
+        sink.append_from_file(fpath).unwrap();
+        sink.render_until_end().unwrap();
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(events.len(), EVENTS_CAPACITY);
+        assert_eq!(events[0], HPlaybackEvent::Started { index: 3 });
+    }
+
     #[test]
     fn audio_devices_test() {
         audio_default_device().unwrap();
         audio_supported_configs().unwrap();
         audio_output_devices().unwrap();
+        let name = audio_default_device().unwrap();
+        audio_device_supported_configs(&name).unwrap();
+        let sink = HAudioSink::try_new_with_device(&name, &HStreamConfig::default()).unwrap();
+        assert!(!sink.is_offline());
+    }
+
+    #[test]
+    fn select_config_test() {
+        let config = |nchannels, min_sr, max_sr, buffer_size, sample_format| HSupportedConfig {
+            nchannels,
+            min_sr,
+            max_sr,
+            buffer_size,
+            sample_format,
+        };
+        let supported = [
+            config(2, 8000, 48000, Some((64, 1024)), HDeviceSampleFormat::I16),
+            config(2, 8000, 48000, Some((64, 1024)), HDeviceSampleFormat::F32),
+            config(1, 44100, 44100, None, HDeviceSampleFormat::F32),
+        ];
+        assert_eq!(
+            select_config(&supported, 2, 44100, None, Some(HDeviceSampleFormat::F32)),
+            Some(1)
+        );
+        assert_eq!(
+            select_config(
+                &supported,
+                2,
+                44100,
+                Some(512),
+                Some(HDeviceSampleFormat::U8)
+            ),
+            Some(0)
+        );
+        assert_eq!(
+            select_config(
+                &supported,
+                2,
+                44100,
+                Some(4096),
+                Some(HDeviceSampleFormat::F32)
+            ),
+            None
+        );
+        assert_eq!(
+            select_config(&supported, 2, 96000, None, Some(HDeviceSampleFormat::F32)),
+            None
+        );
+        assert_eq!(
+            select_config(
+                &supported,
+                1,
+                44100,
+                Some(4096),
+                Some(HDeviceSampleFormat::I16)
+            ),
+            Some(2)
+        );
+        assert_eq!(
+            select_config(&supported, 1, 48000, None, Some(HDeviceSampleFormat::F32)),
+            None
+        );
+        // Without a default sample format, the first matching configuration is used.
+        assert_eq!(select_config(&supported, 2, 44100, None, None), Some(0));
     }
 }
@@ -0,0 +1,434 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use harmonium_core::{
+    array::HArray,
+    audioop::AudioOp,
+    errors::{HError, HResult},
+};
+use ndarray::Ix2;
+use num_traits::{Float, FloatConst, FromPrimitive};
+use symphonia::core::{
+    audio::Channels,
+    codecs::{
+        CodecParameters, CodecType, CODEC_TYPE_PCM_F32BE, CODEC_TYPE_PCM_F32LE,
+        CODEC_TYPE_PCM_F64BE, CODEC_TYPE_PCM_F64LE, CODEC_TYPE_PCM_S16BE, CODEC_TYPE_PCM_S16LE,
+        CODEC_TYPE_PCM_S24BE, CODEC_TYPE_PCM_S24LE, CODEC_TYPE_PCM_S32BE, CODEC_TYPE_PCM_S32LE,
+        CODEC_TYPE_PCM_U8,
+    },
+    conv::ConvertibleSample,
+    errors::{seek_error, unsupported_error, Error, Result, SeekErrorKind},
+    formats::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track},
+    io::{MediaSource, MediaSourceStream},
+    meta::{Metadata, MetadataLog},
+    units::TimeBase,
+};
+
+use crate::{
+    decode::{decode_from_input, stream_from_input, DecoderStream, HInput},
+    encode::{check_range, write_sample, HOutOfRange, HSampleFormat},
+    markers::read_up_to,
+};
+
+/// Number of frames in each packet read from a headerless PCM input.
+const FRAMES_PER_PACKET: u64 = 1024;
+
+/// The byte order of the samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HEndianness {
+    #[default]
+    Little,
+    Big,
+}
+
+/// The layout of headerless PCM audio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HRawFormat {
+    pub sample_format: HSampleFormat,
+    /// Byte order of the samples. Ignored for `HSampleFormat::UInt8`.
+    pub endianness: HEndianness,
+    pub nchannels: usize,
+    /// Whether the samples of each frame are stored together. Otherwise all the samples of the first channel are
+    /// stored first, then all the samples of the second channel and so on, which requires a seekable input.
+    pub interleaved: bool,
+    /// The sampling rate in Hz.
+    pub sr: u32,
+}
+
+impl HRawFormat {
+    /// Number of bytes used by each frame.
+    fn frame_len(&self) -> u64 {
+        self.nchannels as u64 * self.sample_len()
+    }
+
+    /// Number of bytes used by each sample.
+    fn sample_len(&self) -> u64 {
+        (self.sample_format.bits_per_sample() / 8) as u64
+    }
+
+    fn codec(&self) -> CodecType {
+        let little = self.endianness == HEndianness::Little;
+        match self.sample_format {
+            HSampleFormat::UInt8 => CODEC_TYPE_PCM_U8,
+            HSampleFormat::Int16 if little => CODEC_TYPE_PCM_S16LE,
+            HSampleFormat::Int16 => CODEC_TYPE_PCM_S16BE,
+            HSampleFormat::Int24 if little => CODEC_TYPE_PCM_S24LE,
+            HSampleFormat::Int24 => CODEC_TYPE_PCM_S24BE,
+            HSampleFormat::Int32 if little => CODEC_TYPE_PCM_S32LE,
+            HSampleFormat::Int32 => CODEC_TYPE_PCM_S32BE,
+            HSampleFormat::Float32 if little => CODEC_TYPE_PCM_F32LE,
+            HSampleFormat::Float32 => CODEC_TYPE_PCM_F32BE,
+            HSampleFormat::Float64 if little => CODEC_TYPE_PCM_F64LE,
+            HSampleFormat::Float64 => CODEC_TYPE_PCM_F64BE,
+        }
+    }
+}
+
+/// Decode a headerless PCM file as an HArray.
+/// The samples are normalized to fit in the range of \[-1.0, 1.0\]. A partial frame at the end of the file is ignored.
+/// Other functions of the `decode` module can read headerless PCM from an `HInput` created with
+/// `HInput::with_raw_format`.
+///
+/// # Arguments
+///
+/// * `fpath` - The input file.
+/// * `raw_format` - The sample format, byte order, number of channels, layout and sampling rate of the samples.
+///
+/// # Examples
+///
+/// ```
+/// //let raw_format = HRawFormat {
+/// //    sample_format: HSampleFormat::Int16,
+/// //    endianness: HEndianness::Little,
+/// //    nchannels: 2,
+/// //    interleaved: true,
+/// //    sr: 44100,
+/// //};
+/// //decode_raw::<f32>("audio.pcm", raw_format).unwrap();
+/// ```
+pub fn decode_raw<T>(fpath: &str, raw_format: HRawFormat) -> HResult<HArray<T, Ix2>>
+where
+    T: Float + FloatConst + ConvertibleSample,
+{
+    let input = HInput::from_file(fpath)?.with_raw_format(raw_format);
+    let (harray, _) = decode_from_input(input, None)?;
+    Ok(harray)
+}
+
+/// stream a headerless PCM file as an iterator. See `decode_raw` and `stream` for the details.
+pub fn stream_raw<T>(
+    fpath: &str,
+    frames: usize,
+    raw_format: HRawFormat,
+) -> HResult<DecoderStream<T>>
+where
+    T: Float + FloatConst + ConvertibleSample,
+{
+    let input = HInput::from_file(fpath)?.with_raw_format(raw_format);
+    stream_from_input(input, frames, None)
+}
+
+/// Encode an HArray as a headerless PCM file.
+/// The samples are expected to be normalized in the range of \[-1.0, 1.0\], as returned by `decode`.
+/// Samples outside this range are handled according to `out_of_range` when encoding to an integer sample format. Float
+/// sample formats store the samples as they are. The sampling rate of `raw_format` is not stored.
+///
+/// # Arguments
+///
+/// * `fpath` - The output file.
+/// * `harray` - The samples, with shape `(nchannels, nframes)`.
+/// * `raw_format` - The sample format, byte order, number of channels and layout of the samples.
+/// * `out_of_range` - How to handle samples outside the range of \[-1.0, 1.0\].
+pub fn write_raw<T>(
+    fpath: &str,
+    harray: &HArray<T, Ix2>,
+    raw_format: HRawFormat,
+    out_of_range: HOutOfRange,
+) -> HResult<()>
+where
+    T: Float + FloatConst + FromPrimitive,
+{
+    if harray.nchannels() != raw_format.nchannels {
+        return Err(HError::ShapeMismatch {
+            expected: vec![raw_format.nchannels, harray.nframes()],
+            got: harray.shape().to_vec(),
+        });
+    }
+    if out_of_range == HOutOfRange::Error && !raw_format.sample_format.is_float() {
+        check_range(harray)?;
+    }
+
+    let file = File::create(Path::new(fpath))?;
+    let mut writer = BufWriter::new(file);
+    let mut sample = Vec::with_capacity(8);
+    let mut write = |x: &T| -> HResult<()> {
+        sample.clear();
+        write_sample(&mut sample, *x, raw_format.sample_format)?;
+        if raw_format.endianness == HEndianness::Big {
+            sample.reverse();
+        }
+        writer.write_all(&sample)?;
+        Ok(())
+    };
+
+    if raw_format.interleaved {
+        harray
+            .0
+            .view()
+            .reversed_axes()
+            .iter()
+            .try_for_each(&mut write)?;
+    } else {
+        harray.0.iter().try_for_each(&mut write)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// A format reader for headerless PCM, which yields packets of interleaved samples for the PCM decoder.
+pub(crate) struct RawReader {
+    source: MediaSourceStream,
+    raw_format: HRawFormat,
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+    // Number of frames, if the length of the input is known.
+    nframes: Option<u64>,
+    // Index of the next frame to be read.
+    position: u64,
+}
+
+impl RawReader {
+    pub(crate) fn try_new(source: MediaSourceStream, raw_format: HRawFormat) -> HResult<Self> {
+        let channels = u32::try_from(raw_format.nchannels)
+            .ok()
+            .filter(|x| *x > 0 && *x < 32)
+            .and_then(|x| Channels::from_bits((1 << x) - 1))
+            .ok_or_else(|| HError::OutOfSpecError("invalid number of channels".into()))?;
+        if raw_format.sr == 0 {
+            return Err(HError::OutOfSpecError(
+                "the sampling rate must be greater than zero".into(),
+            ));
+        }
+
+        let nframes = source.byte_len().map(|x| x / raw_format.frame_len());
+        if !raw_format.interleaved && (nframes.is_none() || !source.is_seekable()) {
+            return Err(HError::OutOfSpecError(
+                "non-interleaved samples can only be read from a seekable input".into(),
+            ));
+        }
+
+        let bits_per_sample = raw_format.sample_format.bits_per_sample() as u32;
+        let mut codec_params = CodecParameters::new();
+        codec_params
+            .for_codec(raw_format.codec())
+            .with_sample_rate(raw_format.sr)
+            .with_time_base(TimeBase::new(1, raw_format.sr))
+            .with_channels(channels)
+            .with_bits_per_sample(bits_per_sample)
+            .with_bits_per_coded_sample(bits_per_sample)
+            .with_max_frames_per_packet(FRAMES_PER_PACKET);
+        if let Some(nframes) = nframes {
+            codec_params.with_n_frames(nframes);
+        }
+
+        Ok(RawReader {
+            source,
+            raw_format,
+            tracks: vec![Track::new(0, codec_params)],
+            cues: Vec::new(),
+            metadata: MetadataLog::default(),
+            nframes,
+            position: 0,
+        })
+    }
+}
+
+impl FormatReader for RawReader {
+    fn try_new(_source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        unsupported_error("raw: the layout of the samples is required")
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        if !self.source.is_seekable() {
+            return seek_error(SeekErrorKind::Unseekable);
+        }
+
+        let ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => TimeBase::new(1, self.raw_format.sr).calc_timestamp(time),
+        };
+        let ts = match self.nframes {
+            Some(nframes) if ts > nframes => return seek_error(SeekErrorKind::OutOfRange),
+            _ => ts,
+        };
+
+        if self.raw_format.interleaved {
+            self.source
+                .seek(SeekFrom::Start(ts * self.raw_format.frame_len()))?;
+        }
+        self.position = ts;
+
+        Ok(SeekedTo {
+            track_id: 0,
+            required_ts: ts,
+            actual_ts: ts,
+        })
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let frames = match self.nframes {
+            Some(nframes) => FRAMES_PER_PACKET.min(nframes.saturating_sub(self.position)),
+            None => FRAMES_PER_PACKET,
+        };
+        let frame_len = self.raw_format.frame_len() as usize;
+        let mut data = vec![0; frames as usize * frame_len];
+
+        if self.raw_format.interleaved {
+            let n = read_up_to(&mut self.source, &mut data)?;
+            // A partial frame at the end of the input is discarded.
+            data.truncate(n / frame_len * frame_len);
+        } else {
+            // Gather the samples of each channel into interleaved frames.
+            let sample_len = self.raw_format.sample_len() as usize;
+            let nframes = self.nframes.unwrap_or(0);
+            let mut channel = vec![0; frames as usize * sample_len];
+            for i in 0..self.raw_format.nchannels {
+                let start = (i as u64 * nframes + self.position) * sample_len as u64;
+                self.source.seek(SeekFrom::Start(start))?;
+                self.source.read_exact(&mut channel)?;
+                for (frame, sample) in channel.chunks_exact(sample_len).enumerate() {
+                    let pos = frame * frame_len + i * sample_len;
+                    data[pos..pos + sample_len].copy_from_slice(sample);
+                }
+            }
+        }
+
+        if data.is_empty() {
+            return Err(Error::IoError(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "end of stream",
+            )));
+        }
+
+        let frames = (data.len() / frame_len) as u64;
+        let packet =
+            Packet::new_from_boxed_slice(0, self.position, frames, data.into_boxed_slice());
+        self.position += frames;
+
+        Ok(packet)
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::decode;
+    use ndarray::s;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("harmonium_{}_{}", std::process::id(), name))
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn raw_test() {
+        let (harray, sr) = decode::<f64>("../testfiles/gs-16b-2c-44100hz.flac", None).unwrap();
+
+        for sample_format in [
+            HSampleFormat::Int16,
+            HSampleFormat::Int24,
+            HSampleFormat::Int32,
+            HSampleFormat::Float32,
+            HSampleFormat::Float64,
+        ] {
+            for endianness in [HEndianness::Little, HEndianness::Big] {
+                for interleaved in [true, false] {
+                    let raw_format = HRawFormat {
+                        sample_format,
+                        endianness,
+                        nchannels: 2,
+                        interleaved,
+                        sr,
+                    };
+                    let fpath = temp_path(&format!(
+                        "{:?}_{:?}_{}.raw",
+                        sample_format, endianness, interleaved
+                    ));
+                    write_raw(&fpath, &harray, raw_format, HOutOfRange::Error).unwrap();
+                    assert_eq!(
+                        std::fs::metadata(&fpath).unwrap().len(),
+                        harray.len() as u64 * raw_format.sample_len()
+                    );
+
+                    let harray_rt = decode_raw::<f64>(&fpath, raw_format).unwrap();
+                    assert_eq!(harray, harray_rt);
+
+                    // Streaming and seeking.
+                    let mut stream = stream_raw::<f64>(&fpath, 1000, raw_format).unwrap();
+                    assert_eq!(stream.next().unwrap().0, harray.0.slice(s![.., 0..1000]));
+                    stream.seek(123457).unwrap();
+                    assert_eq!(
+                        stream.next().unwrap().0,
+                        harray.0.slice(s![.., 123457..124457])
+                    );
+
+                    std::fs::remove_file(&fpath).unwrap();
+                }
+            }
+        }
+
+        // The bytes of the first frames.
+        let fpath = temp_path("UInt8.raw");
+        let harray = HArray::new_from_shape_vec((2, 2), vec![-1., 0., 0.5, 1.]).unwrap();
+        let raw_format = HRawFormat {
+            sample_format: HSampleFormat::UInt8,
+            endianness: HEndianness::Big,
+            nchannels: 2,
+            interleaved: true,
+            sr: 8000,
+        };
+        write_raw(&fpath, &harray, raw_format, HOutOfRange::Clip).unwrap();
+        assert_eq!(std::fs::read(&fpath).unwrap(), vec![0, 192, 128, 255]);
+        let lhs = decode_raw::<f32>(&fpath, raw_format).unwrap();
+        let rhs = HArray::new_from_shape_vec((2, 2), vec![-1., 0., 0.5, 127. / 128.]).unwrap();
+        assert_eq!(lhs, rhs);
+        std::fs::remove_file(&fpath).unwrap();
+
+        let raw_format = HRawFormat {
+            sample_format: HSampleFormat::Int16,
+            endianness: HEndianness::Big,
+            nchannels: 1,
+            interleaved: true,
+            sr: 8000,
+        };
+        let input = HInput::from_bytes(vec![0x40, 0, 0xc0, 0, 0x7f]).with_raw_format(raw_format);
+        let (lhs, sr) = decode_from_input::<f32>(input, None).unwrap();
+        let rhs = HArray::new_from_shape_vec((1, 2), vec![0.5, -0.5]).unwrap();
+        assert_eq!(sr, 8000);
+        assert_eq!(lhs, rhs);
+    }
+}
@@ -0,0 +1,1030 @@
+use std::borrow::Cow;
+
+use harmonium_core::errors::{HError, HResult};
+
+use crate::{decode::HTag, encode::HCoverArt};
+
+/// Vendor string used when the file has no Vorbis comment to take it from.
+const VENDOR: &str = "harmonium";
+
+/// Picture type used for the cover art, as defined by ID3v2 and FLAC.
+const FRONT_COVER: u8 = 3;
+
+/// A standard tag key, as returned by `metadata_from_file`, and the corresponding field in each tag format.
+struct StdKey {
+    std: &'static str,
+    vorbis: &'static str,
+    riff: Option<&'static [u8; 4]>,
+    id3: &'static str,
+}
+
+const STD_KEYS: &[StdKey] = &[
+    StdKey {
+        std: "TrackTitle",
+        vorbis: "TITLE",
+        riff: Some(b"INAM"),
+        id3: "TIT2",
+    },
+    StdKey {
+        std: "Artist",
+        vorbis: "ARTIST",
+        riff: Some(b"IART"),
+        id3: "TPE1",
+    },
+    StdKey {
+        std: "Album",
+        vorbis: "ALBUM",
+        riff: Some(b"IPRD"),
+        id3: "TALB",
+    },
+    StdKey {
+        std: "AlbumArtist",
+        vorbis: "ALBUMARTIST",
+        riff: None,
+        id3: "TPE2",
+    },
+    StdKey {
+        std: "Comment",
+        vorbis: "COMMENT",
+        riff: Some(b"ICMT"),
+        id3: "COMM",
+    },
+    StdKey {
+        std: "Composer",
+        vorbis: "COMPOSER",
+        riff: Some(b"IMUS"),
+        id3: "TCOM",
+    },
+    StdKey {
+        std: "Copyright",
+        vorbis: "COPYRIGHT",
+        riff: Some(b"ICOP"),
+        id3: "TCOP",
+    },
+    StdKey {
+        std: "Date",
+        vorbis: "DATE",
+        riff: Some(b"ICRD"),
+        id3: "TDRC",
+    },
+    StdKey {
+        std: "Encoder",
+        vorbis: "ENCODER",
+        riff: Some(b"ISFT"),
+        id3: "TSSE",
+    },
+    StdKey {
+        std: "EncodedBy",
+        vorbis: "ENCODED-BY",
+        riff: Some(b"ITCH"),
+        id3: "TENC",
+    },
+    StdKey {
+        std: "Genre",
+        vorbis: "GENRE",
+        riff: Some(b"IGNR"),
+        id3: "TCON",
+    },
+    StdKey {
+        std: "Language",
+        vorbis: "LANGUAGE",
+        riff: Some(b"ILNG"),
+        id3: "TLAN",
+    },
+    StdKey {
+        std: "TrackNumber",
+        vorbis: "TRACKNUMBER",
+        riff: Some(b"ITRK"),
+        id3: "TRCK",
+    },
+];
+
+fn std_key(tag: &HTag) -> Option<&'static StdKey> {
+    STD_KEYS.iter().find(|x| x.std == tag.tag_std_key)
+}
+
+/// Rewrites the tags of a WAV file. The tags are stored in a `LIST` chunk of type `INFO`, placed before the `data` chunk
+/// so they are found by readers that stop at the samples. The existing `INFO` chunks are removed, wherever they are in
+/// the file. The existing `id3 ` chunks are rewritten in place with `write_id3v2_tags`, and one is added before the `data`
+/// chunk to hold the cover art if there is none.
+pub(crate) fn write_wav_tags(
+    bytes: &[u8],
+    tags: &[HTag],
+    cover_art: Option<&HCoverArt>,
+) -> HResult<Vec<u8>> {
+    let mut info = Vec::new();
+    info.extend_from_slice(b"INFO");
+    for tag in tags {
+        let id = match std_key(tag).and_then(|x| x.riff) {
+            Some(id) => *id,
+            None => <[u8; 4]>::try_from(tag.tag_key.as_bytes())
+                .ok()
+                .filter(|x| x.iter().all(|c| c.is_ascii_alphanumeric()))
+                .ok_or_else(|| {
+                    HError::OutOfSpecError(format!(
+                        "the tag {} cannot be stored in a RIFF INFO chunk, use a 4-character id such as ICMT",
+                        tag.tag_key
+                    ))
+                })?,
+        };
+        // INFO values are NUL-terminated strings.
+        let mut value = tag.tag_value.clone().into_bytes();
+        value.push(0);
+        push_riff_chunk(&mut info, &id, &value)?;
+    }
+
+    let is_id3 = |id: &[u8]| id == b"id3 " || id == b"ID3 ";
+    let mut has_id3 = false;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        has_id3 |= is_id3(&bytes[pos..pos + 4]);
+        pos = pos.saturating_add(8 + len + len % 2);
+    }
+
+    let mut out = Vec::with_capacity(bytes.len() + info.len());
+    out.extend_from_slice(&bytes[..12]);
+    let mut pos = 12;
+    let mut has_data = false;
+
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let len = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let is_data = id == b"data";
+        // The data chunk may have an unknown length when the file was streamed, in which case it lasts until the end of
+        // the file.
+        let end = if is_data {
+            (pos + 8).saturating_add(len + len % 2).min(bytes.len())
+        } else {
+            (pos + 8)
+                .checked_add(len + len % 2)
+                .filter(|x| *x <= bytes.len())
+                .ok_or_else(|| HError::DecodeError("wav: chunk length exceeds the file".into()))?
+        };
+
+        if is_data && !has_data {
+            if !tags.is_empty() {
+                push_riff_chunk(&mut out, b"LIST", &info)?;
+            }
+            if cover_art.is_some() && !has_id3 {
+                push_riff_chunk(&mut out, b"id3 ", &write_id3v2_tags(&[], tags, cover_art)?)?;
+            }
+            has_data = true;
+        }
+
+        let is_info = id == b"LIST" && bytes.get(pos + 8..pos + 12) == Some(b"INFO".as_slice());
+        if is_id3(id) {
+            let tag = write_id3v2_tags(&bytes[pos + 8..pos + 8 + len], tags, cover_art)?;
+            push_riff_chunk(&mut out, id.try_into().unwrap(), &tag)?;
+        } else if !is_info {
+            out.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+    }
+
+    if !has_data {
+        return Err(HError::DecodeError("wav: missing data chunk".into()));
+    }
+
+    let riff_len = u32::try_from(out.len() - 8)
+        .map_err(|_| HError::OutOfSpecError("the data is too large for a WAV file".into()))?;
+    out[4..8].copy_from_slice(&riff_len.to_le_bytes());
+
+    Ok(out)
+}
+
+fn push_riff_chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) -> HResult<()> {
+    let len = u32::try_from(data.len())
+        .map_err(|_| HError::OutOfSpecError("the data is too large for a WAV file".into()))?;
+    out.extend_from_slice(id);
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(data);
+    // The RIFF chunks must have an even length.
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+    Ok(())
+}
+
+/// Rewrites the tags of a FLAC file. The `VORBIS_COMMENT` block is replaced and, if a cover art is given, the `PICTURE`
+/// blocks too. The other metadata blocks are kept.
+pub(crate) fn write_flac_tags(
+    bytes: &[u8],
+    tags: &[HTag],
+    cover_art: Option<&HCoverArt>,
+) -> HResult<Vec<u8>> {
+    const VORBIS_COMMENT: u8 = 4;
+    const PICTURE: u8 = 6;
+
+    let mut blocks: Vec<(u8, &[u8])> = Vec::new();
+    let mut vendor = None;
+    let mut pos = 4;
+
+    loop {
+        let header = bytes
+            .get(pos..pos + 4)
+            .ok_or_else(|| HError::DecodeError("flac: truncated metadata block".into()))?;
+        let is_last = header[0] & 0x80 != 0;
+        let block_type = header[0] & 0x7f;
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let body = bytes
+            .get(pos + 4..pos + 4 + len)
+            .ok_or_else(|| HError::DecodeError("flac: truncated metadata block".into()))?;
+
+        match block_type {
+            VORBIS_COMMENT => vendor = parse_vorbis_comment(body).map(|x| x.0),
+            PICTURE if cover_art.is_some() => (),
+            _ => blocks.push((block_type, body)),
+        }
+
+        pos += 4 + len;
+        if is_last {
+            break;
+        }
+    }
+
+    if blocks.first().is_none_or(|x| x.0 != 0) {
+        return Err(HError::DecodeError("flac: missing STREAMINFO block".into()));
+    }
+
+    let comment = vorbis_comment(vendor.as_deref().unwrap_or(VENDOR), tags, &[])?;
+    let picture = cover_art.map(picture_block).transpose()?;
+    // The new blocks go right after STREAMINFO, which must be the first block.
+    let mut new_blocks = vec![(VORBIS_COMMENT, comment.as_slice())];
+    if let Some(picture) = &picture {
+        new_blocks.push((PICTURE, picture.as_slice()));
+    }
+    blocks.splice(1..1, new_blocks);
+
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(b"fLaC");
+    for (i, (block_type, body)) in blocks.iter().enumerate() {
+        let len = u32::try_from(body.len())
+            .ok()
+            .filter(|x| *x < 1 << 24)
+            .ok_or_else(|| {
+                HError::OutOfSpecError("the metadata block is too large for FLAC".into())
+            })?;
+        let is_last = if i == blocks.len() - 1 { 0x80 } else { 0 };
+        out.push(is_last | block_type);
+        out.extend_from_slice(&len.to_be_bytes()[1..]);
+        out.extend_from_slice(body);
+    }
+    out.extend_from_slice(&bytes[pos..]);
+
+    Ok(out)
+}
+
+/// Rewrites the comment header of the first Vorbis or Opus stream of an OGG file. The cover art is stored as a
+/// `METADATA_BLOCK_PICTURE` comment. The header pages are rebuilt and the following pages of the stream are renumbered.
+pub(crate) fn write_ogg_tags(
+    bytes: &[u8],
+    tags: &[HTag],
+    cover_art: Option<&HCoverArt>,
+) -> HResult<Vec<u8>> {
+    let pages = parse_ogg_pages(bytes)?;
+    let serial = pages
+        .iter()
+        .find(|x| x.header_type & 0x02 != 0)
+        .map(|x| x.serial)
+        .ok_or_else(|| HError::DecodeError("ogg: missing first page".into()))?;
+
+    // Collect the header packets of the stream.
+    let mut packets: Vec<Vec<u8>> = Vec::new();
+    let mut packet = Vec::new();
+    let mut nheaders = None;
+    let mut header_pages = 0;
+    for page in pages.iter().filter(|x| x.serial == serial) {
+        header_pages += 1;
+        let mut offset = 0;
+        for (i, lacing) in page.lacing.iter().enumerate() {
+            packet.extend_from_slice(&page.data[offset..offset + *lacing as usize]);
+            offset += *lacing as usize;
+            if *lacing < 255 {
+                packets.push(std::mem::take(&mut packet));
+                if nheaders.is_none() {
+                    nheaders = Some(match packets[0].as_slice() {
+                        [0x01, b'v', b'o', b'r', b'b', b'i', b's', ..] => 3,
+                        [b'O', b'p', b'u', b's', b'H', b'e', b'a', b'd', ..] => 2,
+                        _ => {
+                            return Err(HError::Unsupported {
+                                what: "only Vorbis and Opus streams are supported in OGG files"
+                                    .into(),
+                            })
+                        }
+                    });
+                }
+                if Some(packets.len()) == nheaders && i != page.lacing.len() - 1 {
+                    return Err(HError::DecodeError(
+                        "ogg: the header packets must end on a page boundary".into(),
+                    ));
+                }
+            }
+        }
+        if nheaders.is_some_and(|x| packets.len() >= x) {
+            break;
+        }
+    }
+    if nheaders.is_none_or(|x| packets.len() < x) {
+        return Err(HError::DecodeError("ogg: missing header packets".into()));
+    }
+
+    let is_opus = nheaders == Some(2);
+    let magic: &[u8] = if is_opus { b"OpusTags" } else { b"\x03vorbis" };
+    let old_comment = packets[1]
+        .strip_prefix(magic)
+        .and_then(parse_vorbis_comment)
+        .ok_or_else(|| HError::DecodeError("ogg: invalid comment header".into()))?;
+    // Keep the existing cover art unless a new one is given.
+    let mut extra: Vec<String> = if cover_art.is_none() {
+        old_comment
+            .1
+            .into_iter()
+            .filter(|x| {
+                x.split_once('=')
+                    .is_some_and(|(k, _)| k.eq_ignore_ascii_case("METADATA_BLOCK_PICTURE"))
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if let Some(cover_art) = cover_art {
+        extra.push(format!(
+            "METADATA_BLOCK_PICTURE={}",
+            base64(&picture_block(cover_art)?)
+        ));
+    }
+
+    let mut comment = magic.to_vec();
+    comment.extend(vorbis_comment(&old_comment.0, tags, &extra)?);
+    if !is_opus {
+        // Framing bit.
+        comment.push(1);
+    }
+    packets[1] = comment;
+
+    // The identification header is alone in the first page and the other headers start a new page.
+    let mut new_pages = paginate(&packets[..1], serial, 0x02, 0);
+    new_pages.extend(paginate(&packets[1..], serial, 0, new_pages.len() as u32));
+
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut seq = new_pages.len() as u32;
+    let mut skipped = 0;
+    for page in pages.iter() {
+        if page.serial != serial {
+            out.extend_from_slice(page.raw);
+        } else if skipped < header_pages {
+            if skipped == 0 {
+                for new_page in new_pages.iter() {
+                    out.extend(new_page.to_bytes());
+                }
+            }
+            skipped += 1;
+        } else {
+            let mut raw = page.raw.to_vec();
+            raw[18..22].copy_from_slice(&seq.to_le_bytes());
+            raw[22..26].fill(0);
+            let crc = ogg_crc(&raw);
+            raw[22..26].copy_from_slice(&crc.to_le_bytes());
+            out.extend(raw);
+            seq += 1;
+        }
+    }
+
+    Ok(out)
+}
+
+struct OggPage<'a> {
+    header_type: u8,
+    granule: u64,
+    serial: u32,
+    seq: u32,
+    lacing: Vec<u8>,
+    data: Vec<u8>,
+    raw: &'a [u8],
+}
+
+impl OggPage<'_> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(27 + self.lacing.len() + self.data.len());
+        out.extend_from_slice(b"OggS");
+        out.push(0);
+        out.push(self.header_type);
+        out.extend_from_slice(&self.granule.to_le_bytes());
+        out.extend_from_slice(&self.serial.to_le_bytes());
+        out.extend_from_slice(&self.seq.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.push(self.lacing.len() as u8);
+        out.extend_from_slice(&self.lacing);
+        out.extend_from_slice(&self.data);
+        let crc = ogg_crc(&out);
+        out[22..26].copy_from_slice(&crc.to_le_bytes());
+        out
+    }
+}
+
+fn parse_ogg_pages(bytes: &[u8]) -> HResult<Vec<OggPage<'_>>> {
+    let mut pages = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let header = bytes
+            .get(pos..pos + 27)
+            .filter(|x| x.starts_with(b"OggS"))
+            .ok_or_else(|| HError::DecodeError("ogg: invalid page".into()))?;
+        let nsegments = header[26] as usize;
+        let lacing = bytes
+            .get(pos + 27..pos + 27 + nsegments)
+            .ok_or_else(|| HError::DecodeError("ogg: truncated page".into()))?;
+        let data_len: usize = lacing.iter().map(|x| *x as usize).sum();
+        let end = pos + 27 + nsegments + data_len;
+        let raw = bytes
+            .get(pos..end)
+            .ok_or_else(|| HError::DecodeError("ogg: truncated page".into()))?;
+        pages.push(OggPage {
+            header_type: header[5],
+            granule: u64::from_le_bytes(header[6..14].try_into().unwrap()),
+            serial: u32::from_le_bytes(header[14..18].try_into().unwrap()),
+            seq: u32::from_le_bytes(header[18..22].try_into().unwrap()),
+            lacing: lacing.to_vec(),
+            data: raw[27 + nsegments..].to_vec(),
+            raw,
+        });
+        pos = end;
+    }
+    Ok(pages)
+}
+
+/// Splits header packets in pages, starting a new page for the first packet and ending the last page with the last
+/// packet.
+fn paginate(packets: &[Vec<u8>], serial: u32, header_type: u8, seq: u32) -> Vec<OggPage<'static>> {
+    let mut pages = Vec::new();
+    let mut page = OggPage {
+        header_type,
+        granule: u64::MAX,
+        serial,
+        seq,
+        lacing: Vec::new(),
+        data: Vec::new(),
+        raw: &[],
+    };
+
+    for packet in packets {
+        let mut remaining = packet.as_slice();
+        loop {
+            if page.lacing.len() == 255 {
+                let next = OggPage {
+                    // Flag the page if it starts in the middle of a packet.
+                    header_type: if remaining.len() < packet.len() {
+                        0x01
+                    } else {
+                        0
+                    },
+                    granule: u64::MAX,
+                    serial,
+                    seq: page.seq + 1,
+                    lacing: Vec::new(),
+                    data: Vec::new(),
+                    raw: &[],
+                };
+                pages.push(std::mem::replace(&mut page, next));
+            }
+            let len = remaining.len().min(255);
+            page.lacing.push(len as u8);
+            page.data.extend_from_slice(&remaining[..len]);
+            remaining = &remaining[len..];
+            if len < 255 {
+                // Header pages have a granule position of zero once a packet finishes on them.
+                page.granule = 0;
+                break;
+            }
+        }
+    }
+    pages.push(page);
+
+    pages
+}
+
+/// CRC-32 used by OGG pages, with polynomial 0x04c11db7, no reflection and zero initial value.
+fn ogg_crc(bytes: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = (i as u32) << 24;
+            let mut j = 0;
+            while j < 8 {
+                crc = if crc & 0x8000_0000 != 0 {
+                    (crc << 1) ^ 0x04c1_1db7
+                } else {
+                    crc << 1
+                };
+                j += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+
+    bytes.iter().fold(0, |crc, x| {
+        (crc << 8) ^ TABLE[((crc >> 24) as u8 ^ x) as usize]
+    })
+}
+
+/// Rewrites the ID3v2 tag at the start of an MP3 file. The text, `TXXX` and `COMM` frames are replaced by the new tags,
+/// and the `APIC` frames by the cover art if one is given. The other frames, such as `CHAP`, `PRIV` or `USLT`, are kept.
+/// ID3v2.2 tags and compressed or encrypted frames are not supported, since their frames could not be kept.
+pub(crate) fn write_id3v2_tags(
+    bytes: &[u8],
+    tags: &[HTag],
+    cover_art: Option<&HCoverArt>,
+) -> HResult<Vec<u8>> {
+    let (old_tag_len, old_frames) = match bytes {
+        [b'I', b'D', b'3', version, _, flags, s0, s1, s2, s3, ..] => {
+            if !(3..=4).contains(version) {
+                return Err(HError::Unsupported {
+                    what: format!("rewriting an ID3v2.{} tag", version),
+                });
+            }
+            let size = syncsafe_to_u32([*s0, *s1, *s2, *s3]) as usize;
+            // Plus the footer, if present.
+            let len = 10 + size + if flags & 0x10 != 0 { 10 } else { 0 };
+            let frames = bytes.get(10..10 + size).unwrap_or_default();
+            let mut old_frames = Vec::new();
+            for (id, body) in parse_id3v2_frames(frames, *version, *flags) {
+                let is_tag = id.starts_with(b"T") || id == *b"COMM";
+                let is_picture = id == *b"APIC" && cover_art.is_some();
+                if is_tag || is_picture {
+                    continue;
+                }
+                let body = body.ok_or_else(|| HError::Unsupported {
+                    what: format!(
+                        "rewriting an ID3v2 tag with a compressed or encrypted {} frame",
+                        String::from_utf8_lossy(&id)
+                    ),
+                })?;
+                old_frames.push((id, id3v24_frame_body(&id, &body, *version)));
+            }
+            (len.min(bytes.len()), old_frames)
+        }
+        _ => (0, Vec::new()),
+    };
+
+    let mut out = id3v2_tag(tags, cover_art, &old_frames)?;
+    out.extend_from_slice(&bytes[old_tag_len..]);
+
+    Ok(out)
+}
+
+/// Converts the body of a frame read from an ID3v2 tag of the given version to ID3v2.4. Only `CHAP` and `CTOC` differ,
+/// since the size of their embedded frames is not syncsafe in ID3v2.3.
+fn id3v24_frame_body(id: &[u8], body: &[u8], version: u8) -> Vec<u8> {
+    // The element id, then the start and end times and offsets for CHAP, or the flags and the child element ids for
+    // CTOC.
+    let header_len = body
+        .iter()
+        .position(|x| *x == 0)
+        .map(|x| x + 1)
+        .and_then(|x| match id {
+            b"CHAP" => Some(x + 16),
+            b"CTOC" => {
+                let count = *body.get(x + 1)? as usize;
+                (0..count).try_fold(x + 2, |pos, _| {
+                    Some(pos + body.get(pos..)?.iter().position(|x| *x == 0)? + 1)
+                })
+            }
+            _ => None,
+        });
+
+    match header_len {
+        Some(header_len) if version == 3 && header_len <= body.len() => {
+            let mut out = body[..header_len].to_vec();
+            for (id, frame) in id3v2_frames(&body[header_len..], version, 0) {
+                // Frames too large for an ID3v2.4 tag are dropped.
+                let Ok(size) = u32_to_syncsafe(frame.len()) else {
+                    continue;
+                };
+                out.extend_from_slice(&id);
+                out.extend_from_slice(&size);
+                out.extend_from_slice(&[0, 0]);
+                out.extend_from_slice(&frame);
+            }
+            out
+        }
+        _ => body.to_vec(),
+    }
+}
+
+/// Builds an ID3v2.4 tag. `kept` holds the ids and bodies of existing frames to be kept.
+fn id3v2_tag(
+    tags: &[HTag],
+    cover_art: Option<&HCoverArt>,
+    kept: &[([u8; 4], Vec<u8>)],
+) -> HResult<Vec<u8>> {
+    const UTF8: u8 = 3;
+
+    let mut frames: Vec<(String, Vec<u8>)> = Vec::new();
+    for tag in tags {
+        let id = match std_key(tag) {
+            Some(x) => x.id3.to_string(),
+            None if is_id3v2_text_frame(&tag.tag_key) => tag.tag_key.clone(),
+            None => "TXXX".to_string(),
+        };
+        match id.as_str() {
+            "COMM" => {
+                let mut body = vec![UTF8];
+                body.extend_from_slice(b"eng\0");
+                body.extend_from_slice(tag.tag_value.as_bytes());
+                frames.push((id, body));
+            }
+            "TXXX" => {
+                // Keys read from a TXXX frame are prefixed with the frame id.
+                let description = tag.tag_key.strip_prefix("TXXX:").unwrap_or(&tag.tag_key);
+                let mut body = vec![UTF8];
+                body.extend_from_slice(description.as_bytes());
+                body.push(0);
+                body.extend_from_slice(tag.tag_value.as_bytes());
+                frames.push((id, body));
+            }
+            _ => match frames.iter_mut().find(|x| x.0 == id) {
+                // A text frame can appear only once, with multiple values separated by NUL.
+                Some((_, body)) => {
+                    body.push(0);
+                    body.extend_from_slice(tag.tag_value.as_bytes());
+                }
+                None => {
+                    let mut body = vec![UTF8];
+                    body.extend_from_slice(tag.tag_value.as_bytes());
+                    frames.push((id, body));
+                }
+            },
+        }
+    }
+    for (id, body) in kept {
+        frames.push((String::from_utf8_lossy(id).into_owned(), body.clone()));
+    }
+    if let Some(cover_art) = cover_art {
+        let mut body = vec![UTF8];
+        body.extend_from_slice(cover_art.media_type.as_bytes());
+        body.push(0);
+        body.push(FRONT_COVER);
+        body.extend_from_slice(cover_art.description.as_bytes());
+        body.push(0);
+        body.extend_from_slice(&cover_art.data);
+        frames.push(("APIC".to_string(), body));
+    }
+
+    let mut body = Vec::new();
+    for (id, frame) in frames.iter() {
+        body.extend_from_slice(id.as_bytes());
+        body.extend_from_slice(&u32_to_syncsafe(frame.len())?);
+        // Frame flags.
+        body.extend_from_slice(&[0, 0]);
+        body.extend_from_slice(frame);
+    }
+
+    let mut out = Vec::with_capacity(10 + body.len());
+    out.extend_from_slice(b"ID3");
+    // Version 2.4.0, no flags.
+    out.extend_from_slice(&[4, 0, 0]);
+    out.extend_from_slice(&u32_to_syncsafe(body.len())?);
+    out.extend(body);
+
+    Ok(out)
+}
+
+/// Returns the id and the body of the frames of an ID3v2.3 or ID3v2.4 tag, undoing the unsynchronisation. Compressed
+/// or encrypted frames are skipped.
+pub(crate) fn id3v2_frames(frames: &[u8], version: u8, flags: u8) -> Vec<([u8; 4], Cow<'_, [u8]>)> {
+    parse_id3v2_frames(frames, version, flags)
+        .into_iter()
+        .filter_map(|(id, body)| Some((id, body?)))
+        .collect()
+}
+
+/// The body of an ID3v2 frame, or `None` if the frame is compressed or encrypted.
+type Id3v2Body<'a> = Option<Cow<'a, [u8]>>;
+
+/// Same as `id3v2_frames`, but the compressed or encrypted frames are returned too.
+fn parse_id3v2_frames(frames: &[u8], version: u8, flags: u8) -> Vec<([u8; 4], Id3v2Body<'_>)> {
+    let mut out = Vec::new();
+    if !(3..=4).contains(&version) {
+        return out;
+    }
+
+    // In ID3v2.3 the unsynchronisation applies to the whole tag, and in ID3v2.4 to each frame, as told by its flags.
+    if version == 3 && flags & 0x80 != 0 {
+        let frames = id3v2_resync(frames);
+        return parse_id3v2_frames(&frames, version, flags & !0x80)
+            .into_iter()
+            .map(|(id, body)| (id, body.map(|x| Cow::Owned(x.into_owned()))))
+            .collect();
+    }
+
+    let mut pos = 0;
+    // Skip the extended header.
+    if flags & 0x40 != 0 {
+        if let Some(size) = frames.get(0..4) {
+            let size = <[u8; 4]>::try_from(size).unwrap();
+            pos = if version == 4 {
+                syncsafe_to_u32(size) as usize
+            } else {
+                u32::from_be_bytes(size) as usize + 4
+            };
+        }
+    }
+
+    while let Some(header) = frames.get(pos..pos + 10) {
+        if header[0] == 0 {
+            // Padding.
+            break;
+        }
+        let id = <[u8; 4]>::try_from(&header[0..4]).unwrap();
+        let size = <[u8; 4]>::try_from(&header[4..8]).unwrap();
+        let size = if version == 4 {
+            syncsafe_to_u32(size) as usize
+        } else {
+            u32::from_be_bytes(size) as usize
+        };
+        let Some(mut body) = frames.get(pos + 10..pos + 10 + size) else {
+            break;
+        };
+        pos += 10 + size;
+
+        // The grouping identity, then the data length indicator (ID3v2.4 only) come before the frame data.
+        let (grouping, data_length, unsynchronised, encoded) = if version == 4 {
+            (
+                header[9] & 0x40 != 0,
+                header[9] & 0x01 != 0,
+                header[9] & 0x02 != 0,
+                header[9] & 0x0c != 0,
+            )
+        } else {
+            (header[9] & 0x20 != 0, false, false, header[9] & 0xc0 != 0)
+        };
+        if encoded {
+            out.push((id, None));
+            continue;
+        }
+        let skip = if grouping { 1 } else { 0 } + if data_length { 4 } else { 0 };
+        body = body.get(skip..).unwrap_or_default();
+        let body = if unsynchronised {
+            Cow::Owned(id3v2_resync(body))
+        } else {
+            Cow::Borrowed(body)
+        };
+        out.push((id, Some(body)));
+    }
+
+    out
+}
+
+/// Undoes the ID3v2 unsynchronisation, which inserts a zero byte after each 0xff byte.
+fn id3v2_resync(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut previous = 0;
+    for x in bytes {
+        if !(previous == 0xff && *x == 0) {
+            out.push(*x);
+        }
+        previous = *x;
+    }
+    out
+}
+
+fn is_id3v2_text_frame(key: &str) -> bool {
+    key.len() == 4
+        && key.starts_with('T')
+        && key != "TXXX"
+        && key
+            .bytes()
+            .all(|x| x.is_ascii_uppercase() || x.is_ascii_digit())
+}
+
+pub(crate) fn syncsafe_to_u32(bytes: [u8; 4]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |acc, x| (acc << 7) | (*x & 0x7f) as u32)
+}
+
+fn u32_to_syncsafe(x: usize) -> HResult<[u8; 4]> {
+    if x >= 1 << 28 {
+        return Err(HError::OutOfSpecError(
+            "the data is too large for an ID3v2 tag".into(),
+        ));
+    }
+    Ok([
+        (x >> 21) as u8 & 0x7f,
+        (x >> 14) as u8 & 0x7f,
+        (x >> 7) as u8 & 0x7f,
+        x as u8 & 0x7f,
+    ])
+}
+
+/// Builds a Vorbis comment, without the packet type and the framing bit used in OGG. `extra` holds comments already
+/// formatted as `KEY=value`.
+fn vorbis_comment(vendor: &str, tags: &[HTag], extra: &[String]) -> HResult<Vec<u8>> {
+    let mut comments: Vec<String> = Vec::with_capacity(tags.len() + extra.len());
+    for tag in tags {
+        let key = match std_key(tag) {
+            Some(x) => x.vorbis,
+            None => tag.tag_key.as_str(),
+        };
+        if key.is_empty() || !key.bytes().all(|x| (0x20..=0x7d).contains(&x) && x != b'=') {
+            return Err(HError::OutOfSpecError(format!(
+                "the tag {} is not a valid Vorbis comment field name",
+                key
+            )));
+        }
+        comments.push(format!("{}={}", key, tag.tag_value));
+    }
+    comments.extend(extra.iter().cloned());
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    out.extend_from_slice(vendor.as_bytes());
+    out.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        out.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        out.extend_from_slice(comment.as_bytes());
+    }
+
+    Ok(out)
+}
+
+/// Parses a Vorbis comment into the vendor string and the list of comments.
+fn parse_vorbis_comment(bytes: &[u8]) -> Option<(String, Vec<String>)> {
+    fn read_string(bytes: &[u8], pos: &mut usize) -> Option<String> {
+        let len = u32::from_le_bytes(bytes.get(*pos..*pos + 4)?.try_into().ok()?) as usize;
+        let s = bytes.get(*pos + 4..*pos + 4 + len)?;
+        *pos += 4 + len;
+        Some(String::from_utf8_lossy(s).into_owned())
+    }
+
+    let mut pos = 0;
+    let vendor = read_string(bytes, &mut pos)?;
+    let n = u32::from_le_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?);
+    pos += 4;
+    let comments = (0..n)
+        .map(|_| read_string(bytes, &mut pos))
+        .collect::<Option<Vec<String>>>()?;
+
+    Some((vendor, comments))
+}
+
+/// Builds the body of a FLAC `PICTURE` block, also used by the `METADATA_BLOCK_PICTURE` Vorbis comment.
+fn picture_block(cover_art: &HCoverArt) -> HResult<Vec<u8>> {
+    let data_len = u32::try_from(cover_art.data.len())
+        .map_err(|_| HError::OutOfSpecError("the cover art is too large".into()))?;
+    let mut out = Vec::with_capacity(32 + cover_art.data.len());
+    out.extend_from_slice(&(FRONT_COVER as u32).to_be_bytes());
+    out.extend_from_slice(&(cover_art.media_type.len() as u32).to_be_bytes());
+    out.extend_from_slice(cover_art.media_type.as_bytes());
+    out.extend_from_slice(&(cover_art.description.len() as u32).to_be_bytes());
+    out.extend_from_slice(cover_art.description.as_bytes());
+    // Width, height, color depth and number of colors are unknown.
+    out.extend_from_slice(&[0; 16]);
+    out.extend_from_slice(&data_len.to_be_bytes());
+    out.extend_from_slice(&cover_art.data);
+    Ok(out)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let x = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(x >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ogg_crc_test() {
+        let bytes = std::fs::read("../testfiles/gs-16b-2c-44100hz.ogg").unwrap();
+        let pages = parse_ogg_pages(&bytes).unwrap();
+        for page in pages.iter().take(10) {
+            let mut raw = page.raw.to_vec();
+            raw[22..26].fill(0);
+            assert_eq!(
+                ogg_crc(&raw).to_le_bytes(),
+                page.raw[22..26],
+                "page {}",
+                page.seq
+            );
+        }
+    }
+
+    #[test]
+    fn base64_test() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn write_id3v2_tags_test() {
+        // An ID3v2.3 frame, whose size is not syncsafe.
+        let frame = |id: &[u8], body: &[u8]| {
+            let mut out = id.to_vec();
+            out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            out.extend_from_slice(&[0, 0]);
+            out.extend_from_slice(body);
+            out
+        };
+        let label = [&[0][..], &[b'a'; 200]].concat();
+        let chap = [
+            &b"ch0\0"[..],
+            &[0, 0, 0, 0, 0, 0, 3, 232],
+            &[0xff; 8],
+            &frame(b"TIT2", &label),
+        ]
+        .concat();
+        let frames = [
+            frame(b"TIT2", b"\0Old"),
+            frame(b"TXXX", b"\0key\0value"),
+            frame(b"PRIV", b"owner\0data"),
+            frame(b"CHAP", &chap),
+            frame(b"APIC", b"\0image/png\0\x03\0png"),
+        ]
+        .concat();
+        let mut bytes = b"ID3\x03\0\0".to_vec();
+        bytes.extend_from_slice(&u32_to_syncsafe(frames.len()).unwrap());
+        bytes.extend(frames);
+        bytes.extend_from_slice(&[0xff, 0xfb, 0x90, 0x00]);
+
+        let tag = HTag {
+            tag_key: "title".into(),
+            tag_std_key: "TrackTitle".into(),
+            tag_value: "New".into(),
+        };
+        let out = write_id3v2_tags(&bytes, &[tag], None).unwrap();
+        assert_eq!(&out[..4], b"ID3\x04");
+        assert!(out.ends_with(&[0xff, 0xfb, 0x90, 0x00]));
+
+        let size = syncsafe_to_u32(out[6..10].try_into().unwrap()) as usize;
+        let frames = id3v2_frames(&out[10..10 + size], 4, 0);
+        let ids: Vec<[u8; 4]> = frames.iter().map(|x| x.0).collect();
+        assert_eq!(ids, [*b"TIT2", *b"PRIV", *b"CHAP", *b"APIC"]);
+        assert_eq!(*frames[0].1, *b"\x03New");
+        assert_eq!(*frames[1].1, *b"owner\0data");
+
+        // The frames embedded in CHAP are converted to ID3v2.4.
+        let chap = &frames[2].1;
+        assert_eq!(
+            &chap[..20],
+            &[b"ch0\0", &[0, 0, 0, 0, 0, 0, 3, 232][..], &[0xff; 8]].concat()
+        );
+        let embedded = id3v2_frames(&chap[20..], 4, 0);
+        assert_eq!(embedded, [(*b"TIT2", Cow::Borrowed(label.as_slice()))]);
+
+        // A new cover art replaces the existing one.
+        let cover_art = HCoverArt {
+            media_type: "image/jpeg".into(),
+            description: String::new(),
+            data: vec![1, 2, 3],
+        };
+        let out = write_id3v2_tags(&out, &[], Some(&cover_art)).unwrap();
+        let size = syncsafe_to_u32(out[6..10].try_into().unwrap()) as usize;
+        let frames = id3v2_frames(&out[10..10 + size], 4, 0);
+        let ids: Vec<[u8; 4]> = frames.iter().map(|x| x.0).collect();
+        assert_eq!(ids, [*b"PRIV", *b"CHAP", *b"APIC"]);
+        assert!(frames[2].1.starts_with(b"\x03image/jpeg"));
+
+        // The frames of an unsynchronised tag are kept, without the unsynchronisation.
+        let mut frames = [frame(b"TIT2", b"\0Old"), frame(b"PRIV", b"\xff\xe0")].concat();
+        frames.insert(frames.len() - 1, 0);
+        let mut bytes = b"ID3\x03\0\x80".to_vec();
+        bytes.extend_from_slice(&u32_to_syncsafe(frames.len()).unwrap());
+        bytes.extend(frames);
+        let out = write_id3v2_tags(&bytes, &[], None).unwrap();
+        let size = syncsafe_to_u32(out[6..10].try_into().unwrap()) as usize;
+        let frames = id3v2_frames(&out[10..10 + size], 4, 0);
+        assert_eq!(frames, [(*b"PRIV", Cow::Borrowed(&b"\xff\xe0"[..]))]);
+
+        // The frames of ID3v2.2 tags and compressed frames can't be kept.
+        let mut bytes = b"ID3\x02\0\0\0\0\0\x0a".to_vec();
+        bytes.extend_from_slice(b"TT2\0\0\x04\0Old");
+        let res = write_id3v2_tags(&bytes, &[], None);
+        assert!(matches!(res, Err(HError::Unsupported { .. })));
+        let mut bytes = b"ID3\x04\0\0\0\0\0\x0e".to_vec();
+        bytes.extend_from_slice(b"PRIV\0\0\0\x04\0\x08data");
+        let res = write_id3v2_tags(&bytes, &[], None);
+        assert!(matches!(res, Err(HError::Unsupported { .. })));
+    }
+}
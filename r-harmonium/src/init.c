@@ -135,6 +135,16 @@ SEXP savvy_HAudioSink_new__impl(void) {
     return handle_result(res);
 }
 
+SEXP savvy_HAudioSink_new_with_device__impl(SEXP c_arg__name, SEXP c_arg__nchannels, SEXP c_arg__sr, SEXP c_arg__buffer_size) {
+    SEXP res = savvy_HAudioSink_new_with_device__ffi(c_arg__name, c_arg__nchannels, c_arg__sr, c_arg__buffer_size);
+    return handle_result(res);
+}
+
+SEXP savvy_HAudioSink_new_offline__impl(SEXP c_arg__nchannels, SEXP c_arg__sr) {
+    SEXP res = savvy_HAudioSink_new_offline__ffi(c_arg__nchannels, c_arg__sr);
+    return handle_result(res);
+}
+
 SEXP savvy_HAudioSink_append_from_harray__impl(SEXP self__, SEXP c_arg__harray, SEXP c_arg__sr) {
     SEXP res = savvy_HAudioSink_append_from_harray__ffi(self__, c_arg__harray, c_arg__sr);
     return handle_result(res);
@@ -145,6 +155,11 @@ SEXP savvy_HAudioSink_append_from_file__impl(SEXP self__, SEXP c_arg__fpath) {
     return handle_result(res);
 }
 
+SEXP savvy_HAudioSink_append_from_mixer__impl(SEXP self__, SEXP c_arg__hmixer) {
+    SEXP res = savvy_HAudioSink_append_from_mixer__ffi(self__, c_arg__hmixer);
+    return handle_result(res);
+}
+
 SEXP savvy_HAudioSink_audio_default_device__impl(void) {
     SEXP res = savvy_HAudioSink_audio_default_device__ffi();
     return handle_result(res);
@@ -155,8 +170,8 @@ SEXP savvy_HAudioSink_audio_output_devices__impl(void) {
     return handle_result(res);
 }
 
-SEXP savvy_HAudioSink_audio_supported_configs__impl(void) {
-    SEXP res = savvy_HAudioSink_audio_supported_configs__ffi();
+SEXP savvy_HAudioSink_audio_supported_configs__impl(SEXP c_arg__name) {
+    SEXP res = savvy_HAudioSink_audio_supported_configs__ffi(c_arg__name);
     return handle_result(res);
 }
 
@@ -175,6 +190,11 @@ SEXP savvy_HAudioSink_is_empty__impl(SEXP self__) {
     return handle_result(res);
 }
 
+SEXP savvy_HAudioSink_is_offline__impl(SEXP self__) {
+    SEXP res = savvy_HAudioSink_is_offline__ffi(self__);
+    return handle_result(res);
+}
+
 SEXP savvy_HAudioSink_is_paused__impl(SEXP self__) {
     SEXP res = savvy_HAudioSink_is_paused__ffi(self__);
     return handle_result(res);
@@ -195,6 +215,31 @@ SEXP savvy_HAudioSink_play__impl(SEXP self__) {
     return handle_result(res);
 }
 
+SEXP savvy_HAudioSink_poll_events__impl(SEXP self__) {
+    SEXP res = savvy_HAudioSink_poll_events__ffi(self__);
+    return handle_result(res);
+}
+
+SEXP savvy_HAudioSink_position_interval__impl(SEXP self__) {
+    SEXP res = savvy_HAudioSink_position_interval__ffi(self__);
+    return handle_result(res);
+}
+
+SEXP savvy_HAudioSink_render__impl(SEXP self__, SEXP c_arg__nframes) {
+    SEXP res = savvy_HAudioSink_render__ffi(self__, c_arg__nframes);
+    return handle_result(res);
+}
+
+SEXP savvy_HAudioSink_render_until_end__impl(SEXP self__) {
+    SEXP res = savvy_HAudioSink_render_until_end__ffi(self__);
+    return handle_result(res);
+}
+
+SEXP savvy_HAudioSink_set_position_interval__impl(SEXP self__, SEXP c_arg__interval) {
+    SEXP res = savvy_HAudioSink_set_position_interval__ffi(self__, c_arg__interval);
+    return handle_result(res);
+}
+
 SEXP savvy_HAudioSink_set_speed__impl(SEXP self__, SEXP c_arg__value) {
     SEXP res = savvy_HAudioSink_set_speed__ffi(self__, c_arg__value);
     return handle_result(res);
@@ -225,6 +270,11 @@ SEXP savvy_HAudioSink_stop__impl(SEXP self__) {
     return handle_result(res);
 }
 
+SEXP savvy_HAudioSink_take_stream_error__impl(SEXP self__) {
+    SEXP res = savvy_HAudioSink_take_stream_error__ffi(self__);
+    return handle_result(res);
+}
+
 SEXP savvy_HAudioSink_try_seek__impl(SEXP self__, SEXP c_arg__pos) {
     SEXP res = savvy_HAudioSink_try_seek__ffi(self__, c_arg__pos);
     return handle_result(res);
@@ -265,6 +315,21 @@ SEXP savvy_HDecodedAudio_sr__impl(SEXP self__) {
     return handle_result(res);
 }
 
+SEXP savvy_HDecodedAudio_delay__impl(SEXP self__) {
+    SEXP res = savvy_HDecodedAudio_delay__ffi(self__);
+    return handle_result(res);
+}
+
+SEXP savvy_HDecodedAudio_padding__impl(SEXP self__) {
+    SEXP res = savvy_HDecodedAudio_padding__ffi(self__);
+    return handle_result(res);
+}
+
+SEXP savvy_HDecodedAudio_concealed__impl(SEXP self__) {
+    SEXP res = savvy_HDecodedAudio_concealed__ffi(self__);
+    return handle_result(res);
+}
+
 SEXP savvy_HDecodedAudio_invalidate__impl(SEXP self__) {
     SEXP res = savvy_HDecodedAudio_invalidate__ffi(self__);
     return handle_result(res);
@@ -275,6 +340,16 @@ SEXP savvy_HDecoderStream_stream__impl(SEXP self__) {
     return handle_result(res);
 }
 
+SEXP savvy_HDecoderStream_seek__impl(SEXP self__, SEXP c_arg__frame) {
+    SEXP res = savvy_HDecoderStream_seek__ffi(self__, c_arg__frame);
+    return handle_result(res);
+}
+
+SEXP savvy_HDecoderStream_position__impl(SEXP self__) {
+    SEXP res = savvy_HDecoderStream_position__ffi(self__);
+    return handle_result(res);
+}
+
 SEXP savvy_HFft_new_forward__impl(SEXP c_arg__length, SEXP c_arg__dtype) {
     SEXP res = savvy_HFft_new_forward__ffi(c_arg__length, c_arg__dtype);
     return handle_result(res);
@@ -325,13 +400,23 @@ SEXP savvy_HFft_invalidate__impl(SEXP self__) {
     return handle_result(res);
 }
 
-SEXP savvy_HFile_decode__impl(SEXP c_arg__fpath, SEXP c_arg__dtype) {
-    SEXP res = savvy_HFile_decode__ffi(c_arg__fpath, c_arg__dtype);
+SEXP savvy_HFile_decode__impl(SEXP c_arg__fpath, SEXP c_arg__dtype, SEXP c_arg__track_id, SEXP c_arg__gapless) {
+    SEXP res = savvy_HFile_decode__ffi(c_arg__fpath, c_arg__dtype, c_arg__track_id, c_arg__gapless);
+    return handle_result(res);
+}
+
+SEXP savvy_HFile_decode_tolerant__impl(SEXP c_arg__fpath, SEXP c_arg__dtype, SEXP c_arg__track_id, SEXP c_arg__concealment, SEXP c_arg__max_errors) {
+    SEXP res = savvy_HFile_decode_tolerant__ffi(c_arg__fpath, c_arg__dtype, c_arg__track_id, c_arg__concealment, c_arg__max_errors);
     return handle_result(res);
 }
 
-SEXP savvy_HFile_decode_stream__impl(SEXP c_arg__fpath, SEXP c_arg__frames, SEXP c_arg__dtype) {
-    SEXP res = savvy_HFile_decode_stream__ffi(c_arg__fpath, c_arg__frames, c_arg__dtype);
+SEXP savvy_HFile_decode_stream__impl(SEXP c_arg__fpath, SEXP c_arg__frames, SEXP c_arg__dtype, SEXP c_arg__track_id, SEXP c_arg__tail, SEXP c_arg__hop_length) {
+    SEXP res = savvy_HFile_decode_stream__ffi(c_arg__fpath, c_arg__frames, c_arg__dtype, c_arg__track_id, c_arg__tail, c_arg__hop_length);
+    return handle_result(res);
+}
+
+SEXP savvy_HFile_load__impl(SEXP c_arg__fpath, SEXP c_arg__dtype, SEXP c_arg__sr, SEXP c_arg__mono, SEXP c_arg__offset, SEXP c_arg__duration, SEXP c_arg__track_id) {
+    SEXP res = savvy_HFile_load__ffi(c_arg__fpath, c_arg__dtype, c_arg__sr, c_arg__mono, c_arg__offset, c_arg__duration, c_arg__track_id);
     return handle_result(res);
 }
 
@@ -340,13 +425,23 @@ SEXP savvy_HFile_metadata__impl(SEXP c_arg__fpath, SEXP c_arg__metadata_type) {
     return handle_result(res);
 }
 
-SEXP savvy_HFile_params__impl(SEXP c_arg__fpath) {
-    SEXP res = savvy_HFile_params__ffi(c_arg__fpath);
+SEXP savvy_HFile_params__impl(SEXP c_arg__fpath, SEXP c_arg__track_id) {
+    SEXP res = savvy_HFile_params__ffi(c_arg__fpath, c_arg__track_id);
+    return handle_result(res);
+}
+
+SEXP savvy_HFile_verify__impl(SEXP c_arg__fpath, SEXP c_arg__track_id) {
+    SEXP res = savvy_HFile_verify__ffi(c_arg__fpath, c_arg__track_id);
+    return handle_result(res);
+}
+
+SEXP savvy_HFile_verify_report__impl(SEXP c_arg__fpath, SEXP c_arg__track_id, SEXP c_arg__lenient) {
+    SEXP res = savvy_HFile_verify_report__ffi(c_arg__fpath, c_arg__track_id, c_arg__lenient);
     return handle_result(res);
 }
 
-SEXP savvy_HFile_verify__impl(SEXP c_arg__fpath) {
-    SEXP res = savvy_HFile_verify__ffi(c_arg__fpath);
+SEXP savvy_HFile_tracks__impl(SEXP c_arg__fpath) {
+    SEXP res = savvy_HFile_tracks__ffi(c_arg__fpath);
     return handle_result(res);
 }
 
@@ -380,6 +475,36 @@ SEXP savvy_HMetadataType_ne__impl(SEXP self__, SEXP c_arg__other) {
     return handle_result(res);
 }
 
+SEXP savvy_HMixer_new__impl(SEXP c_arg__nchannels, SEXP c_arg__sr, SEXP c_arg__dtype) {
+    SEXP res = savvy_HMixer_new__ffi(c_arg__nchannels, c_arg__sr, c_arg__dtype);
+    return handle_result(res);
+}
+
+SEXP savvy_HMixer_add__impl(SEXP self__, SEXP c_arg__harray, SEXP c_arg__sr, SEXP c_arg__gain, SEXP c_arg__pan, SEXP c_arg__offset) {
+    SEXP res = savvy_HMixer_add__ffi(self__, c_arg__harray, c_arg__sr, c_arg__gain, c_arg__pan, c_arg__offset);
+    return handle_result(res);
+}
+
+SEXP savvy_HMixer_dtype__impl(SEXP self__) {
+    SEXP res = savvy_HMixer_dtype__ffi(self__);
+    return handle_result(res);
+}
+
+SEXP savvy_HMixer_len__impl(SEXP self__) {
+    SEXP res = savvy_HMixer_len__ffi(self__);
+    return handle_result(res);
+}
+
+SEXP savvy_HMixer_nframes__impl(SEXP self__) {
+    SEXP res = savvy_HMixer_nframes__ffi(self__);
+    return handle_result(res);
+}
+
+SEXP savvy_HMixer_render__impl(SEXP self__) {
+    SEXP res = savvy_HMixer_render__ffi(self__);
+    return handle_result(res);
+}
+
 SEXP savvy_HPolynomialDegree_print__impl(SEXP self__) {
     SEXP res = savvy_HPolynomialDegree_print__ffi(self__);
     return handle_result(res);
@@ -588,24 +713,34 @@ static const R_CallMethodDef CallEntries[] = {
     {"savvy_HArrayAudio_db_to_amplitude__impl", (DL_FUNC) &savvy_HArrayAudio_db_to_amplitude__impl, 3},
     {"savvy_HArrayAudio_to_mono__impl", (DL_FUNC) &savvy_HArrayAudio_to_mono__impl, 1},
     {"savvy_HAudioSink_new__impl", (DL_FUNC) &savvy_HAudioSink_new__impl, 0},
+    {"savvy_HAudioSink_new_with_device__impl", (DL_FUNC) &savvy_HAudioSink_new_with_device__impl, 4},
+    {"savvy_HAudioSink_new_offline__impl", (DL_FUNC) &savvy_HAudioSink_new_offline__impl, 2},
     {"savvy_HAudioSink_append_from_harray__impl", (DL_FUNC) &savvy_HAudioSink_append_from_harray__impl, 3},
     {"savvy_HAudioSink_append_from_file__impl", (DL_FUNC) &savvy_HAudioSink_append_from_file__impl, 2},
+    {"savvy_HAudioSink_append_from_mixer__impl", (DL_FUNC) &savvy_HAudioSink_append_from_mixer__impl, 2},
     {"savvy_HAudioSink_audio_default_device__impl", (DL_FUNC) &savvy_HAudioSink_audio_default_device__impl, 0},
     {"savvy_HAudioSink_audio_output_devices__impl", (DL_FUNC) &savvy_HAudioSink_audio_output_devices__impl, 0},
-    {"savvy_HAudioSink_audio_supported_configs__impl", (DL_FUNC) &savvy_HAudioSink_audio_supported_configs__impl, 0},
+    {"savvy_HAudioSink_audio_supported_configs__impl", (DL_FUNC) &savvy_HAudioSink_audio_supported_configs__impl, 1},
     {"savvy_HAudioSink_clear__impl", (DL_FUNC) &savvy_HAudioSink_clear__impl, 1},
     {"savvy_HAudioSink_get_pos__impl", (DL_FUNC) &savvy_HAudioSink_get_pos__impl, 1},
     {"savvy_HAudioSink_is_empty__impl", (DL_FUNC) &savvy_HAudioSink_is_empty__impl, 1},
+    {"savvy_HAudioSink_is_offline__impl", (DL_FUNC) &savvy_HAudioSink_is_offline__impl, 1},
     {"savvy_HAudioSink_is_paused__impl", (DL_FUNC) &savvy_HAudioSink_is_paused__impl, 1},
     {"savvy_HAudioSink_len__impl", (DL_FUNC) &savvy_HAudioSink_len__impl, 1},
     {"savvy_HAudioSink_pause__impl", (DL_FUNC) &savvy_HAudioSink_pause__impl, 1},
     {"savvy_HAudioSink_play__impl", (DL_FUNC) &savvy_HAudioSink_play__impl, 1},
+    {"savvy_HAudioSink_poll_events__impl", (DL_FUNC) &savvy_HAudioSink_poll_events__impl, 1},
+    {"savvy_HAudioSink_position_interval__impl", (DL_FUNC) &savvy_HAudioSink_position_interval__impl, 1},
+    {"savvy_HAudioSink_render__impl", (DL_FUNC) &savvy_HAudioSink_render__impl, 2},
+    {"savvy_HAudioSink_render_until_end__impl", (DL_FUNC) &savvy_HAudioSink_render_until_end__impl, 1},
+    {"savvy_HAudioSink_set_position_interval__impl", (DL_FUNC) &savvy_HAudioSink_set_position_interval__impl, 2},
     {"savvy_HAudioSink_set_speed__impl", (DL_FUNC) &savvy_HAudioSink_set_speed__impl, 2},
     {"savvy_HAudioSink_set_volume__impl", (DL_FUNC) &savvy_HAudioSink_set_volume__impl, 2},
     {"savvy_HAudioSink_skip_one__impl", (DL_FUNC) &savvy_HAudioSink_skip_one__impl, 1},
     {"savvy_HAudioSink_sleep_until_end__impl", (DL_FUNC) &savvy_HAudioSink_sleep_until_end__impl, 1},
     {"savvy_HAudioSink_speed__impl", (DL_FUNC) &savvy_HAudioSink_speed__impl, 1},
     {"savvy_HAudioSink_stop__impl", (DL_FUNC) &savvy_HAudioSink_stop__impl, 1},
+    {"savvy_HAudioSink_take_stream_error__impl", (DL_FUNC) &savvy_HAudioSink_take_stream_error__impl, 1},
     {"savvy_HAudioSink_try_seek__impl", (DL_FUNC) &savvy_HAudioSink_try_seek__impl, 2},
     {"savvy_HAudioSink_volume__impl", (DL_FUNC) &savvy_HAudioSink_volume__impl, 1},
     {"savvy_HAudioSink_invalidate__impl", (DL_FUNC) &savvy_HAudioSink_invalidate__impl, 1},
@@ -614,8 +749,13 @@ static const R_CallMethodDef CallEntries[] = {
     {"savvy_HDataType_ne__impl", (DL_FUNC) &savvy_HDataType_ne__impl, 2},
     {"savvy_HDecodedAudio_harray__impl", (DL_FUNC) &savvy_HDecodedAudio_harray__impl, 1},
     {"savvy_HDecodedAudio_sr__impl", (DL_FUNC) &savvy_HDecodedAudio_sr__impl, 1},
+    {"savvy_HDecodedAudio_delay__impl", (DL_FUNC) &savvy_HDecodedAudio_delay__impl, 1},
+    {"savvy_HDecodedAudio_padding__impl", (DL_FUNC) &savvy_HDecodedAudio_padding__impl, 1},
+    {"savvy_HDecodedAudio_concealed__impl", (DL_FUNC) &savvy_HDecodedAudio_concealed__impl, 1},
     {"savvy_HDecodedAudio_invalidate__impl", (DL_FUNC) &savvy_HDecodedAudio_invalidate__impl, 1},
     {"savvy_HDecoderStream_stream__impl", (DL_FUNC) &savvy_HDecoderStream_stream__impl, 1},
+    {"savvy_HDecoderStream_seek__impl", (DL_FUNC) &savvy_HDecoderStream_seek__impl, 2},
+    {"savvy_HDecoderStream_position__impl", (DL_FUNC) &savvy_HDecoderStream_position__impl, 1},
     {"savvy_HFft_new_forward__impl", (DL_FUNC) &savvy_HFft_new_forward__impl, 2},
     {"savvy_HFft_new_inverse__impl", (DL_FUNC) &savvy_HFft_new_inverse__impl, 2},
     {"savvy_HFft_new_real_forward__impl", (DL_FUNC) &savvy_HFft_new_real_forward__impl, 2},
@@ -626,17 +766,27 @@ static const R_CallMethodDef CallEntries[] = {
     {"savvy_HFft_clone__impl", (DL_FUNC) &savvy_HFft_clone__impl, 1},
     {"savvy_HFft_is_unique__impl", (DL_FUNC) &savvy_HFft_is_unique__impl, 1},
     {"savvy_HFft_invalidate__impl", (DL_FUNC) &savvy_HFft_invalidate__impl, 1},
-    {"savvy_HFile_decode__impl", (DL_FUNC) &savvy_HFile_decode__impl, 2},
-    {"savvy_HFile_decode_stream__impl", (DL_FUNC) &savvy_HFile_decode_stream__impl, 3},
+    {"savvy_HFile_decode__impl", (DL_FUNC) &savvy_HFile_decode__impl, 4},
+    {"savvy_HFile_decode_tolerant__impl", (DL_FUNC) &savvy_HFile_decode_tolerant__impl, 5},
+    {"savvy_HFile_decode_stream__impl", (DL_FUNC) &savvy_HFile_decode_stream__impl, 6},
+    {"savvy_HFile_load__impl", (DL_FUNC) &savvy_HFile_load__impl, 7},
     {"savvy_HFile_metadata__impl", (DL_FUNC) &savvy_HFile_metadata__impl, 2},
-    {"savvy_HFile_params__impl", (DL_FUNC) &savvy_HFile_params__impl, 1},
-    {"savvy_HFile_verify__impl", (DL_FUNC) &savvy_HFile_verify__impl, 1},
+    {"savvy_HFile_params__impl", (DL_FUNC) &savvy_HFile_params__impl, 2},
+    {"savvy_HFile_verify__impl", (DL_FUNC) &savvy_HFile_verify__impl, 2},
+    {"savvy_HFile_verify_report__impl", (DL_FUNC) &savvy_HFile_verify_report__impl, 3},
+    {"savvy_HFile_tracks__impl", (DL_FUNC) &savvy_HFile_tracks__impl, 1},
     {"savvy_HInterpolationType_print__impl", (DL_FUNC) &savvy_HInterpolationType_print__impl, 1},
     {"savvy_HInterpolationType_eq__impl", (DL_FUNC) &savvy_HInterpolationType_eq__impl, 2},
     {"savvy_HInterpolationType_ne__impl", (DL_FUNC) &savvy_HInterpolationType_ne__impl, 2},
     {"savvy_HMetadataType_print__impl", (DL_FUNC) &savvy_HMetadataType_print__impl, 1},
     {"savvy_HMetadataType_eq__impl", (DL_FUNC) &savvy_HMetadataType_eq__impl, 2},
     {"savvy_HMetadataType_ne__impl", (DL_FUNC) &savvy_HMetadataType_ne__impl, 2},
+    {"savvy_HMixer_new__impl", (DL_FUNC) &savvy_HMixer_new__impl, 3},
+    {"savvy_HMixer_add__impl", (DL_FUNC) &savvy_HMixer_add__impl, 6},
+    {"savvy_HMixer_dtype__impl", (DL_FUNC) &savvy_HMixer_dtype__impl, 1},
+    {"savvy_HMixer_len__impl", (DL_FUNC) &savvy_HMixer_len__impl, 1},
+    {"savvy_HMixer_nframes__impl", (DL_FUNC) &savvy_HMixer_nframes__impl, 1},
+    {"savvy_HMixer_render__impl", (DL_FUNC) &savvy_HMixer_render__impl, 1},
     {"savvy_HPolynomialDegree_print__impl", (DL_FUNC) &savvy_HPolynomialDegree_print__impl, 1},
     {"savvy_HPolynomialDegree_eq__impl", (DL_FUNC) &savvy_HPolynomialDegree_eq__impl, 2},
     {"savvy_HPolynomialDegree_ne__impl", (DL_FUNC) &savvy_HPolynomialDegree_ne__impl, 2},
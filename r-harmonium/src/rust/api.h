@@ -25,24 +25,34 @@ SEXP savvy_HArrayAudio_to_mono__ffi(SEXP c_arg__harray);
 
 // methods and associated functions for HAudioSink
 SEXP savvy_HAudioSink_new__ffi(void);
+SEXP savvy_HAudioSink_new_with_device__ffi(SEXP c_arg__name, SEXP c_arg__nchannels, SEXP c_arg__sr, SEXP c_arg__buffer_size);
+SEXP savvy_HAudioSink_new_offline__ffi(SEXP c_arg__nchannels, SEXP c_arg__sr);
 SEXP savvy_HAudioSink_append_from_harray__ffi(SEXP self__, SEXP c_arg__harray, SEXP c_arg__sr);
 SEXP savvy_HAudioSink_append_from_file__ffi(SEXP self__, SEXP c_arg__fpath);
+SEXP savvy_HAudioSink_append_from_mixer__ffi(SEXP self__, SEXP c_arg__hmixer);
 SEXP savvy_HAudioSink_audio_default_device__ffi(void);
 SEXP savvy_HAudioSink_audio_output_devices__ffi(void);
-SEXP savvy_HAudioSink_audio_supported_configs__ffi(void);
+SEXP savvy_HAudioSink_audio_supported_configs__ffi(SEXP c_arg__name);
 SEXP savvy_HAudioSink_clear__ffi(SEXP self__);
 SEXP savvy_HAudioSink_get_pos__ffi(SEXP self__);
 SEXP savvy_HAudioSink_is_empty__ffi(SEXP self__);
+SEXP savvy_HAudioSink_is_offline__ffi(SEXP self__);
 SEXP savvy_HAudioSink_is_paused__ffi(SEXP self__);
 SEXP savvy_HAudioSink_len__ffi(SEXP self__);
 SEXP savvy_HAudioSink_pause__ffi(SEXP self__);
 SEXP savvy_HAudioSink_play__ffi(SEXP self__);
+SEXP savvy_HAudioSink_poll_events__ffi(SEXP self__);
+SEXP savvy_HAudioSink_position_interval__ffi(SEXP self__);
+SEXP savvy_HAudioSink_render__ffi(SEXP self__, SEXP c_arg__nframes);
+SEXP savvy_HAudioSink_render_until_end__ffi(SEXP self__);
+SEXP savvy_HAudioSink_set_position_interval__ffi(SEXP self__, SEXP c_arg__interval);
 SEXP savvy_HAudioSink_set_speed__ffi(SEXP self__, SEXP c_arg__value);
 SEXP savvy_HAudioSink_set_volume__ffi(SEXP self__, SEXP c_arg__value);
 SEXP savvy_HAudioSink_skip_one__ffi(SEXP self__);
 SEXP savvy_HAudioSink_sleep_until_end__ffi(SEXP self__);
 SEXP savvy_HAudioSink_speed__ffi(SEXP self__);
 SEXP savvy_HAudioSink_stop__ffi(SEXP self__);
+SEXP savvy_HAudioSink_take_stream_error__ffi(SEXP self__);
 SEXP savvy_HAudioSink_try_seek__ffi(SEXP self__, SEXP c_arg__pos);
 SEXP savvy_HAudioSink_volume__ffi(SEXP self__);
 SEXP savvy_HAudioSink_invalidate__ffi(SEXP self__);
@@ -55,10 +65,15 @@ SEXP savvy_HDataType_ne__ffi(SEXP self__, SEXP c_arg__other);
 // methods and associated functions for HDecodedAudio
 SEXP savvy_HDecodedAudio_harray__ffi(SEXP self__);
 SEXP savvy_HDecodedAudio_sr__ffi(SEXP self__);
+SEXP savvy_HDecodedAudio_delay__ffi(SEXP self__);
+SEXP savvy_HDecodedAudio_padding__ffi(SEXP self__);
+SEXP savvy_HDecodedAudio_concealed__ffi(SEXP self__);
 SEXP savvy_HDecodedAudio_invalidate__ffi(SEXP self__);
 
 // methods and associated functions for HDecoderStream
 SEXP savvy_HDecoderStream_stream__ffi(SEXP self__);
+SEXP savvy_HDecoderStream_seek__ffi(SEXP self__, SEXP c_arg__frame);
+SEXP savvy_HDecoderStream_position__ffi(SEXP self__);
 
 // methods and associated functions for HFft
 SEXP savvy_HFft_new_forward__ffi(SEXP c_arg__length, SEXP c_arg__dtype);
@@ -73,11 +88,15 @@ SEXP savvy_HFft_is_unique__ffi(SEXP self__);
 SEXP savvy_HFft_invalidate__ffi(SEXP self__);
 
 // methods and associated functions for HFile
-SEXP savvy_HFile_decode__ffi(SEXP c_arg__fpath, SEXP c_arg__dtype);
-SEXP savvy_HFile_decode_stream__ffi(SEXP c_arg__fpath, SEXP c_arg__frames, SEXP c_arg__dtype);
+SEXP savvy_HFile_decode__ffi(SEXP c_arg__fpath, SEXP c_arg__dtype, SEXP c_arg__track_id, SEXP c_arg__gapless);
+SEXP savvy_HFile_decode_tolerant__ffi(SEXP c_arg__fpath, SEXP c_arg__dtype, SEXP c_arg__track_id, SEXP c_arg__concealment, SEXP c_arg__max_errors);
+SEXP savvy_HFile_decode_stream__ffi(SEXP c_arg__fpath, SEXP c_arg__frames, SEXP c_arg__dtype, SEXP c_arg__track_id, SEXP c_arg__tail, SEXP c_arg__hop_length);
+SEXP savvy_HFile_load__ffi(SEXP c_arg__fpath, SEXP c_arg__dtype, SEXP c_arg__sr, SEXP c_arg__mono, SEXP c_arg__offset, SEXP c_arg__duration, SEXP c_arg__track_id);
 SEXP savvy_HFile_metadata__ffi(SEXP c_arg__fpath, SEXP c_arg__metadata_type);
-SEXP savvy_HFile_params__ffi(SEXP c_arg__fpath);
-SEXP savvy_HFile_verify__ffi(SEXP c_arg__fpath);
+SEXP savvy_HFile_params__ffi(SEXP c_arg__fpath, SEXP c_arg__track_id);
+SEXP savvy_HFile_verify__ffi(SEXP c_arg__fpath, SEXP c_arg__track_id);
+SEXP savvy_HFile_verify_report__ffi(SEXP c_arg__fpath, SEXP c_arg__track_id, SEXP c_arg__lenient);
+SEXP savvy_HFile_tracks__ffi(SEXP c_arg__fpath);
 
 // methods and associated functions for HInterpolationType
 SEXP savvy_HInterpolationType_print__ffi(SEXP self__);
@@ -89,6 +108,14 @@ SEXP savvy_HMetadataType_print__ffi(SEXP self__);
 SEXP savvy_HMetadataType_eq__ffi(SEXP self__, SEXP c_arg__other);
 SEXP savvy_HMetadataType_ne__ffi(SEXP self__, SEXP c_arg__other);
 
+// methods and associated functions for HMixer
+SEXP savvy_HMixer_new__ffi(SEXP c_arg__nchannels, SEXP c_arg__sr, SEXP c_arg__dtype);
+SEXP savvy_HMixer_add__ffi(SEXP self__, SEXP c_arg__harray, SEXP c_arg__sr, SEXP c_arg__gain, SEXP c_arg__pan, SEXP c_arg__offset);
+SEXP savvy_HMixer_dtype__ffi(SEXP self__);
+SEXP savvy_HMixer_len__ffi(SEXP self__);
+SEXP savvy_HMixer_nframes__ffi(SEXP self__);
+SEXP savvy_HMixer_render__ffi(SEXP self__);
+
 // methods and associated functions for HPolynomialDegree
 SEXP savvy_HPolynomialDegree_print__ffi(SEXP self__);
 SEXP savvy_HPolynomialDegree_eq__ffi(SEXP self__, SEXP c_arg__other);
@@ -75,3 +75,9 @@ pub(crate) fn try_from_i32_to_usize(n: i32) -> savvy::Result<usize> {
     n.try_into()
         .map_err(|_| savvy::Error::new("Cannot convert i32 to usize."))
 }
+
+#[inline]
+pub(crate) fn try_from_i32_to_u32(n: i32) -> savvy::Result<u32> {
+    n.try_into()
+        .map_err(|_| savvy::Error::new("Cannot convert i32 to u32."))
+}
@@ -9,10 +9,57 @@ impl From<HError> for HErrorR {
     }
 }
 
+impl HErrorR {
+    /// The R condition class for each kind of error. Every condition also inherits from
+    /// `harmonium_error`, so all errors can be caught at once.
+    fn class(&self) -> &'static str {
+        match self.0 {
+            HError::Io(_) => "harmonium_io_error",
+            HError::DecodeError(_) => "harmonium_decode_error",
+            HError::SeekError(_) => "harmonium_seek_error",
+            HError::Unsupported { .. } => "harmonium_unsupported_error",
+            HError::LimitError(_) => "harmonium_limit_error",
+            HError::ResampleError(_) => "harmonium_resample_error",
+            HError::ShapeMismatch { .. } => "harmonium_shape_mismatch_error",
+            HError::OutOfSpecError(_) => "harmonium_out_of_spec_error",
+            HError::PlayError(_) => "harmonium_play_error",
+            HError::OtherError(_) => "harmonium_other_error",
+        }
+    }
+}
+
 impl From<HErrorR> for savvy::Error {
     fn from(err: HErrorR) -> Self {
-        Self::GeneralError(err.0.to_string())
+        let message = err.0.to_string();
+        let text = format!(
+            "stop(structure(class = c(\"{}\", \"harmonium_error\", \"error\", \"condition\"), list(message = \"{}\", call = NULL)))",
+            err.class(),
+            escape_r_string(&message),
+        );
+
+        // Signaling the condition from R aborts the evaluation, and the aborted state is
+        // propagated back to R as the error.
+        match savvy::eval_parse_text(text) {
+            Err(err) => err,
+            Ok(_) => Self::GeneralError(message),
+        }
+    }
+}
+
+fn escape_r_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:04x}}}", c as u32)),
+            c => escaped.push(c),
+        }
     }
+    escaped
 }
 
 pub(crate) fn to_savvy_error(err: rubato::ResampleError) -> savvy::Error {
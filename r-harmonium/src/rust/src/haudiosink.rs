@@ -1,13 +1,20 @@
 use crate::{
-    conversions::{try_from_usize_to_int_sexp, ToScalar},
+    conversions::{
+        try_from_i32_to_u32, try_from_i32_to_usize, try_from_usize_to_int_sexp, ToScalar,
+    },
     errors::HErrorR,
     harray::HArray,
     hdatatype::HDataType,
+    hmixer::HMixer,
 };
 use harmonium_core::audioop::Audio;
-use harmonium_io::{decode::decode, play};
+use harmonium_io::play;
 use ndarray::IxDyn;
-use savvy::{savvy, OwnedIntegerSexp, OwnedLogicalSexp, OwnedRealSexp, OwnedStringSexp, Sexp};
+use savvy::{
+    savvy, NotAvailableValue, OwnedIntegerSexp, OwnedListSexp, OwnedLogicalSexp, OwnedRealSexp,
+    OwnedStringSexp, Sexp,
+};
+use std::sync::{mpsc::Receiver, Arc};
 
 /// HAudioSink
 /// Handle to a device that outputs sounds.
@@ -15,7 +22,7 @@ use savvy::{savvy, OwnedIntegerSexp, OwnedLogicalSexp, OwnedRealSexp, OwnedStrin
 /// # Methods
 ///
 #[savvy]
-pub struct HAudioSink(play::HAudioSink);
+pub struct HAudioSink(play::HAudioSink, Receiver<play::HPlaybackEvent>);
 
 #[savvy]
 impl HAudioSink {
@@ -42,7 +49,123 @@ impl HAudioSink {
     /// _________
     ///
     fn new() -> savvy::Result<Self> {
-        Ok(Self(play::HAudioSink::try_new().map_err(HErrorR::from)?))
+        Ok(Self::from_sink(
+            play::HAudioSink::try_new().map_err(HErrorR::from)?,
+        ))
+    }
+
+    /// HAudioSink
+    /// ## new_with_device
+    ///
+    /// `new_with_device(name: string, nchannels: integer, sr: integer, buffer_size: integer) -> HAudioSink`
+    ///
+    /// Creates a new `HAudioSink` instance that plays on the chosen audio output device.
+    ///
+    /// The stream configuration is chosen from the configurations supported by the device, as listed by `audio_supported_configs()`.
+    ///
+    /// The sink is set on "play" mode from the start.
+    ///
+    /// #### Arguments
+    ///
+    /// - `name`
+    ///
+    /// The device name as a `string`, as listed by `audio_output_devices()`.
+    ///
+    /// - `nchannels`
+    ///
+    /// An integer. The number of channels. The device's default is used if `NULL`.
+    ///
+    /// - `sr`
+    ///
+    /// An integer. The sampling rate. The device's default is used if `NULL`.
+    ///
+    /// - `buffer_size`
+    ///
+    /// An integer. The buffer size in frames. The device's default is used if `NULL`.
+    ///
+    /// #### Returns
+    ///
+    /// An `HAudioSink`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// haudiosink = HAudioSink$new_with_device(HAudioSink$audio_default_device(), sr = 44100L, buffer_size = 512L)
+    /// ```
+    ///
+    /// _________
+    ///
+    fn new_with_device(
+        name: Sexp,
+        nchannels: Option<Sexp>,
+        sr: Option<Sexp>,
+        buffer_size: Option<Sexp>,
+    ) -> savvy::Result<Self> {
+        let name: &str = name.to_scalar()?;
+        let to_u32 = |x: Option<Sexp>| {
+            x.map(|x| {
+                let x: i32 = x.to_scalar()?;
+                try_from_i32_to_u32(x)
+            })
+            .transpose()
+        };
+        let nchannels = to_u32(nchannels)?
+            .map(|x| u16::try_from(x).map_err(|_| savvy::Error::new("Cannot convert i32 to u16.")))
+            .transpose()?;
+        let config = play::HStreamConfig {
+            nchannels,
+            sr: to_u32(sr)?,
+            buffer_size: to_u32(buffer_size)?,
+        };
+        Ok(Self::from_sink(
+            play::HAudioSink::try_new_with_device(name, &config).map_err(HErrorR::from)?,
+        ))
+    }
+
+    /// HAudioSink
+    /// ## new_offline
+    ///
+    /// `new_offline(nchannels: integer, sr: integer) -> HAudioSink`
+    ///
+    /// Creates a new offline `HAudioSink` instance, which is not attached to an audio device.
+    ///
+    /// The queue advances as its output is rendered by `render()` or `render_until_end()`, which mix the sounds to `nchannels` channels
+    /// at the `sr` sampling rate. Queueing, speed, volume, seeking and skipping behave as they would on a device.
+    ///
+    /// The sink is set on "play" mode from the start.
+    ///
+    /// #### Arguments
+    ///
+    /// - `nchannels`
+    ///
+    /// An integer. The number of channels of the rendered output.
+    ///
+    /// - `sr`
+    ///
+    /// An integer. The sampling rate of the rendered output.
+    ///
+    /// #### Returns
+    ///
+    /// An `HAudioSink`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// haudiosink = HAudioSink$new_offline(2L, 44100L)
+    /// ```
+    ///
+    /// _________
+    ///
+    fn new_offline(nchannels: Sexp, sr: Sexp) -> savvy::Result<Self> {
+        let nchannels: i32 = nchannels.to_scalar()?;
+        let nchannels = try_from_i32_to_usize(nchannels)?;
+        let sr: i32 = sr.to_scalar()?;
+        let sr = try_from_i32_to_u32(sr)?;
+        Ok(Self::from_sink(
+            play::HAudioSink::try_new_offline(nchannels, sr).map_err(HErrorR::from)?,
+        ))
     }
 
     /// HAudioSink
@@ -119,6 +242,8 @@ impl HAudioSink {
     ///
     /// Appends a sound to the queue of sounds to play.
     ///
+    /// The file is decoded while it is played, so playback starts right away and the memory used does not depend on the length of the file.
+    ///
     /// #### Arguments
     ///
     /// - `fpath`
@@ -137,10 +262,41 @@ impl HAudioSink {
     ///
     fn append_from_file(&self, fpath: Sexp) -> savvy::Result<()> {
         let fpath: &str = fpath.to_scalar()?;
+        self.0.append_from_file(fpath).map_err(HErrorR::from)?;
+        Ok(())
+    }
 
-        let (harray, sr) = decode::<f32>(fpath).map_err(HErrorR::from)?;
-        let audio = Audio::D2(&harray);
-        self.0.append_from_harray(&audio, sr);
+    /// HAudioSink
+    /// ## append_from_mixer
+    ///
+    /// `append_from_mixer(hmixer: HMixer)`
+    ///
+    /// Appends a mix to the queue of sounds to play.
+    ///
+    /// The mix is rendered while it is played. Sounds added to the `HMixer` afterwards are not played.
+    ///
+    /// #### Arguments
+    ///
+    /// - `hmixer`
+    ///
+    /// An `HMixer`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// hdecodedaudio = HFile$decode(fpath = "./r-harmonium/testfiles/gs-16b-2c-44100hz.wav", dtype = HDataType$Float32)
+    /// hmixer = HMixer$new(2L, 44100L, HDataType$Float32)
+    /// hmixer$add(hdecodedaudio$harray(), hdecodedaudio$sr())
+    /// hmixer$add(hdecodedaudio$harray(), hdecodedaudio$sr(), gain = 0.5, offset = 1)
+    /// haudiosink = HAudioSink$new()
+    /// haudiosink$append_from_mixer(hmixer)
+    /// ```
+    ///
+    /// _________
+    ///
+    fn append_from_mixer(&self, hmixer: &HMixer) -> savvy::Result<()> {
+        hmixer.0.append_to(&self.0);
         Ok(())
     }
 
@@ -199,23 +355,45 @@ impl HAudioSink {
     /// HAudioSink
     /// ## audio_supported_configs
     ///
-    /// `audio_supported_configs() -> atomicvector`
+    /// `audio_supported_configs(name: string) -> list`
+    ///
+    /// Provides the supported configurations for an audio output device.
+    ///
+    /// Each configuration is a list with the following elements:
+    ///
+    /// - nchannels
+    ///
+    ///     The number of channels.
     ///
-    /// Provides the supported configurations for the default audio output device.
+    /// - min_sr
     ///
-    /// The following informations are given:
+    ///     The minimum sampling rate.
     ///
-    /// - Number of channels.
+    /// - max_sr
     ///
-    /// - Minimum and maximum value for the sampling rate.
+    ///     The maximum sampling rate.
+    ///
+    /// - min_buffer_size
+    ///
+    ///     The minimum buffer size in frames, or `NA` if unknown.
+    ///
+    /// - max_buffer_size
+    ///
+    ///     The maximum buffer size in frames, or `NA` if unknown.
+    ///
+    /// - sample_format
+    ///
+    ///     The type of data expected by the device, such as `"f32"` or `"i16"`.
+    ///
+    /// #### Arguments
     ///
-    /// - Minimum and maximum value for the buffer size.
+    /// - `name`
     ///
-    /// - Type of data expected by the device.
+    /// The device name as a `string`, as listed by `audio_output_devices()`. The default audio output device is used if `NULL`.
     ///
     /// #### Returns
     ///
-    /// A character atomic vector.
+    /// A list of configurations.
     ///
     /// #### Examples
     ///
@@ -226,10 +404,71 @@ impl HAudioSink {
     ///
     /// _________
     ///
-    fn audio_supported_configs() -> savvy::Result<Sexp> {
-        let supported_configs = play::audio_supported_configs().map_err(HErrorR::from)?;
-        let string_sexp = OwnedStringSexp::try_from(supported_configs)?;
-        string_sexp.into()
+    fn audio_supported_configs(name: Option<Sexp>) -> savvy::Result<Sexp> {
+        let supported_configs = match name {
+            Some(name) => {
+                let name: &str = name.to_scalar()?;
+                play::audio_device_supported_configs(name)
+            }
+            None => play::audio_supported_configs(),
+        }
+        .map_err(HErrorR::from)?;
+        let to_int = |x: u32| i32::try_from(x).unwrap_or(i32::na());
+
+        let mut list = OwnedListSexp::new(supported_configs.len(), false)?;
+        for (i, config) in supported_configs.iter().enumerate() {
+            let sample_format = match config.sample_format {
+                play::HDeviceSampleFormat::I8 => "i8",
+                play::HDeviceSampleFormat::I16 => "i16",
+                play::HDeviceSampleFormat::I32 => "i32",
+                play::HDeviceSampleFormat::I64 => "i64",
+                play::HDeviceSampleFormat::U8 => "u8",
+                play::HDeviceSampleFormat::U16 => "u16",
+                play::HDeviceSampleFormat::U32 => "u32",
+                play::HDeviceSampleFormat::U64 => "u64",
+                play::HDeviceSampleFormat::F32 => "f32",
+                play::HDeviceSampleFormat::F64 => "f64",
+            };
+            let (min_buffer_size, max_buffer_size) = config
+                .buffer_size
+                .map(|(min, max)| (to_int(min), to_int(max)))
+                .unwrap_or((i32::na(), i32::na()));
+
+            let mut inner_list = OwnedListSexp::new(6, true)?;
+            inner_list.set_name_and_value(
+                0,
+                "nchannels",
+                OwnedIntegerSexp::try_from_scalar(i32::from(config.nchannels))?,
+            )?;
+            inner_list.set_name_and_value(
+                1,
+                "min_sr",
+                OwnedIntegerSexp::try_from_scalar(to_int(config.min_sr))?,
+            )?;
+            inner_list.set_name_and_value(
+                2,
+                "max_sr",
+                OwnedIntegerSexp::try_from_scalar(to_int(config.max_sr))?,
+            )?;
+            inner_list.set_name_and_value(
+                3,
+                "min_buffer_size",
+                OwnedIntegerSexp::try_from_scalar(min_buffer_size)?,
+            )?;
+            inner_list.set_name_and_value(
+                4,
+                "max_buffer_size",
+                OwnedIntegerSexp::try_from_scalar(max_buffer_size)?,
+            )?;
+            inner_list.set_name_and_value(
+                5,
+                "sample_format",
+                OwnedStringSexp::try_from_scalar(sample_format)?,
+            )?;
+            unsafe { list.set_value_unchecked(i, Sexp::from(inner_list).0) };
+        }
+
+        Ok(list.into())
     }
 
     /// HAudioSink
@@ -313,6 +552,33 @@ impl HAudioSink {
         logical_sexp.into()
     }
 
+    /// HAudioSink
+    /// ## is_offline
+    ///
+    /// `is_offline() -> bool`
+    ///
+    /// Returns true if this sink is not attached to an audio device.
+    ///
+    /// #### Returns
+    ///
+    /// A `bool`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// haudiosink = HAudioSink$new_offline(2L, 44100L)
+    /// haudiosink$is_offline() # TRUE
+    /// ```
+    ///
+    /// _________
+    ///
+    fn is_offline(&self) -> savvy::Result<Sexp> {
+        let is_offline = self.0.is_offline();
+        let logical_sexp: OwnedLogicalSexp = is_offline.try_into()?;
+        logical_sexp.into()
+    }
+
     /// HAudioSink
     /// ## is_paused
     ///
@@ -431,6 +697,203 @@ impl HAudioSink {
         Ok(())
     }
 
+    /// HAudioSink
+    /// ## poll_events
+    ///
+    /// `poll_events() -> list`
+    ///
+    /// Returns the playback events reported since the last call, in the order they happened.
+    ///
+    /// Each event is a list with the fields:
+    ///
+    /// - `type`: `"started"` when a sound starts playing, `"ended"` when it finishes playing or is removed from the queue after
+    /// it started, and `"position"` for the position updates set by `set_position_interval()`, which are also reported after seeking.
+    ///
+    /// - `index`: an integer identifying the sound by the order in which it was appended to the sink, starting at 1.
+    ///
+    /// - `pos`: the position of the sound in seconds for `"position"` events, `NA` otherwise.
+    ///
+    /// Up to 1024 events are kept between calls, and newer events are dropped until the events are polled.
+    ///
+    /// #### Returns
+    ///
+    /// A `list`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// haudiosink = HAudioSink$new_offline(2L, 44100L)
+    /// haudiosink$set_position_interval(1)
+    /// haudiosink$append_from_file(fpath = "./r-harmonium/testfiles/gs-16b-2c-44100hz.wav")
+    /// haudiosink$render(88200L)
+    /// haudiosink$poll_events() # started, then the position at 1 and 2 seconds.
+    /// ```
+    ///
+    /// _________
+    ///
+    fn poll_events(&self) -> savvy::Result<Sexp> {
+        let events: Vec<play::HPlaybackEvent> = self.1.try_iter().collect();
+
+        let mut list = OwnedListSexp::new(events.len(), false)?;
+        for (i, event) in events.iter().enumerate() {
+            let (event_type, index, pos) = match *event {
+                play::HPlaybackEvent::Started { index } => ("started", index, f64::na()),
+                play::HPlaybackEvent::Ended { index } => ("ended", index, f64::na()),
+                play::HPlaybackEvent::Position { index, pos } => {
+                    ("position", index, pos.as_secs_f64())
+                }
+            };
+
+            let mut inner_list = OwnedListSexp::new(3, true)?;
+            inner_list.set_name_and_value(
+                0,
+                "type",
+                OwnedStringSexp::try_from_scalar(event_type)?,
+            )?;
+            inner_list.set_name_and_value(1, "index", try_from_usize_to_int_sexp(index + 1)?)?;
+            inner_list.set_name_and_value(2, "pos", OwnedRealSexp::try_from_scalar(pos)?)?;
+            unsafe { list.set_value_unchecked(i, Sexp::from(inner_list).0) };
+        }
+
+        Ok(list.into())
+    }
+
+    /// HAudioSink
+    /// ## position_interval
+    ///
+    /// `position_interval() -> double`
+    ///
+    /// Gets the interval in seconds between the position events returned by `poll_events()`. Returns `NA` if they are disabled.
+    ///
+    /// #### Returns
+    ///
+    /// A `double`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// haudiosink = HAudioSink$new()
+    /// haudiosink$position_interval() # NA
+    /// ```
+    ///
+    /// _________
+    ///
+    fn position_interval(&self) -> savvy::Result<Sexp> {
+        let interval = self
+            .0
+            .position_interval()
+            .map_or(f64::na(), |x| x.as_secs_f64());
+        let real_sexp: OwnedRealSexp = interval.try_into()?;
+        real_sexp.into()
+    }
+
+    /// HAudioSink
+    /// ## render
+    ///
+    /// `render(nframes: integer) -> HArray`
+    ///
+    /// Renders the next `nframes` frames of an offline sink.
+    ///
+    /// Silence is rendered while the queue is empty or the sink is paused.
+    ///
+    /// #### Arguments
+    ///
+    /// - `nframes`
+    ///
+    /// An integer. The number of frames to render.
+    ///
+    /// #### Returns
+    ///
+    /// A float32 `HArray` with shape `(nchannels, nframes)`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// haudiosink = HAudioSink$new_offline(2L, 44100L)
+    /// haudiosink$append_from_file(fpath = "./r-harmonium/testfiles/gs-16b-2c-44100hz.wav")
+    /// harray = haudiosink$render(1000L)
+    /// ```
+    ///
+    /// _________
+    ///
+    fn render(&self, nframes: Sexp) -> savvy::Result<HArray> {
+        let nframes: i32 = nframes.to_scalar()?;
+        let nframes = try_from_i32_to_usize(nframes)?;
+        let harray = self.0.render(nframes).map_err(HErrorR::from)?;
+        Ok(HArray(Arc::new(harray.into_dynamic())))
+    }
+
+    /// HAudioSink
+    /// ## render_until_end
+    ///
+    /// `render_until_end() -> HArray`
+    ///
+    /// Renders an offline sink until its queue is empty.
+    ///
+    /// Returns an error if the sink is paused, since its sounds would never end.
+    ///
+    /// #### Returns
+    ///
+    /// A float32 `HArray` with shape `(nchannels, nframes)`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// haudiosink = HAudioSink$new_offline(2L, 44100L)
+    /// haudiosink$append_from_file(fpath = "./r-harmonium/testfiles/gs-16b-2c-44100hz.wav")
+    /// harray = haudiosink$render_until_end()
+    /// ```
+    ///
+    /// _________
+    ///
+    fn render_until_end(&self) -> savvy::Result<HArray> {
+        let harray = self.0.render_until_end().map_err(HErrorR::from)?;
+        Ok(HArray(Arc::new(harray.into_dynamic())))
+    }
+
+    /// HAudioSink
+    /// ## set_position_interval
+    ///
+    /// `set_position_interval(interval: double)`
+    ///
+    /// Sets the interval between the position events returned by `poll_events()`, measured in the position of the sound being played.
+    ///
+    /// #### Arguments
+    ///
+    /// - `interval`
+    ///
+    /// A `double`. The interval in seconds. Position events are disabled if `NULL`, which is the default.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// haudiosink = HAudioSink$new()
+    /// haudiosink$set_position_interval(0.5)
+    /// haudiosink$position_interval() # 0.5
+    /// ```
+    ///
+    /// _________
+    ///
+    fn set_position_interval(&self, interval: Option<Sexp>) -> savvy::Result<()> {
+        let interval = interval
+            .map(|x| {
+                let x: f64 = x.to_scalar()?;
+                std::time::Duration::try_from_secs_f64(x).map_err(|_| {
+                    savvy::Error::new("interval must be a positive number of seconds.")
+                })
+            })
+            .transpose()?;
+        self.0
+            .set_position_interval(interval)
+            .map_err(HErrorR::from)?;
+        Ok(())
+    }
+
     /// HAudioSink
     /// ## set_speed
     ///
@@ -608,6 +1071,36 @@ impl HAudioSink {
         Ok(())
     }
 
+    /// HAudioSink
+    /// ## take_stream_error
+    ///
+    /// `take_stream_error() -> string`
+    ///
+    /// Returns the last error reported by the audio stream of a sink created with `new_with_device()`, such as the device being
+    /// disconnected, and clears it.
+    ///
+    /// Returns `NA` if no error was reported since the last call, or for other sinks.
+    ///
+    /// #### Returns
+    ///
+    /// A `string`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// haudiosink = HAudioSink$new_with_device(HAudioSink$audio_default_device())
+    /// haudiosink$take_stream_error() # NA
+    /// ```
+    ///
+    /// _________
+    ///
+    fn take_stream_error(&self) -> savvy::Result<Sexp> {
+        let error = self.0.take_stream_error().map(|err| err.to_string());
+        let string_sexp: OwnedStringSexp = error.as_deref().unwrap_or(<&str>::na()).try_into()?;
+        string_sexp.into()
+    }
+
     /// HAudioSink
     /// ## try_seek
     ///
@@ -705,3 +1198,11 @@ impl HAudioSink {
         Ok(())
     }
 }
+
+impl HAudioSink {
+    // Playback events are received from the creation of the sink.
+    fn from_sink(sink: play::HAudioSink) -> Self {
+        let receiver = sink.subscribe();
+        Self(sink, receiver)
+    }
+}
@@ -1,14 +1,17 @@
 use crate::{
-    conversions::{try_from_i32_to_usize, ToScalar},
+    conversions::{try_from_i32_to_u32, try_from_i32_to_usize, ToScalar},
     errors::HErrorR,
     harray::HArray,
     hdatatype::HDataType,
     hmetadatatype::HMetadataType,
 };
 use harmonium_core::conversions::IntoDynamic;
-use harmonium_io::decode;
-use savvy::{savvy, OwnedIntegerSexp, OwnedListSexp, OwnedRealSexp, OwnedStringSexp, Sexp};
-use std::sync::Arc;
+use harmonium_io::{decode, load};
+use savvy::{
+    savvy, NotAvailableValue, OwnedIntegerSexp, OwnedListSexp, OwnedLogicalSexp, OwnedRawSexp,
+    OwnedRealSexp, OwnedStringSexp, Sexp,
+};
+use std::{num::NonZero, sync::Arc};
 
 /// HFile
 /// A collection of methods designed to streamline input and output operations.
@@ -35,6 +38,9 @@ struct HDecoderStream(Box<dyn HDecoderStreamR>);
 struct HDecodedAudio {
     harray: HArray,
     sr: u32,
+    delay: u32,
+    padding: u32,
+    concealed: decode::HConcealed,
 }
 
 #[savvy]
@@ -98,6 +104,103 @@ impl HDecodedAudio {
         integer_sexp.into()
     }
     /// HDecodedAudio
+    /// ## delay
+    ///
+    /// `delay() -> integer`
+    ///
+    /// Get the number of encoder delay frames trimmed from the start of the audio. Always `0` if the audio was not decoded in gapless mode.
+    ///
+    /// #### Returns
+    ///
+    /// An integer.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// fpath = "../../../testfiles/gs-16b-2c-44100hz.mp3"
+    /// dtype = HDataType$Float32
+    /// hdecodedaudio = HFile$decode(fpath, dtype, gapless = TRUE)
+    /// hdecodedaudio$delay()
+    /// ```
+    ///
+    /// _________
+    ///
+    fn delay(&self) -> savvy::Result<Sexp> {
+        let delay: i32 = self
+            .delay
+            .try_into()
+            .map_err(|_| savvy::Error::new("Cannot convert u32 to i32."))?;
+
+        let integer_sexp = OwnedIntegerSexp::try_from_scalar(delay)?;
+        integer_sexp.into()
+    }
+    /// HDecodedAudio
+    /// ## padding
+    ///
+    /// `padding() -> integer`
+    ///
+    /// Get the number of encoder padding frames trimmed from the end of the audio. Always `0` if the audio was not decoded in gapless mode.
+    ///
+    /// #### Returns
+    ///
+    /// An integer.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// fpath = "../../../testfiles/gs-16b-2c-44100hz.mp3"
+    /// dtype = HDataType$Float32
+    /// hdecodedaudio = HFile$decode(fpath, dtype, gapless = TRUE)
+    /// hdecodedaudio$padding()
+    /// ```
+    ///
+    /// _________
+    ///
+    fn padding(&self) -> savvy::Result<Sexp> {
+        let padding: i32 = self
+            .padding
+            .try_into()
+            .map_err(|_| savvy::Error::new("Cannot convert u32 to i32."))?;
+
+        let integer_sexp = OwnedIntegerSexp::try_from_scalar(padding)?;
+        integer_sexp.into()
+    }
+    /// HDecodedAudio
+    /// ## concealed
+    ///
+    /// `concealed() -> list`
+    ///
+    /// Get the frame ranges that were concealed because their packets failed to decode. Always empty if the audio was not decoded
+    /// with `HFile$decode_tolerant`.
+    ///
+    /// #### Returns
+    ///
+    /// A list with a named double vector for each range, with the first frame in `start` and the frame after the last one in `end`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// fpath = "../../../testfiles/gs-16b-2c-44100hz.flac"
+    /// dtype = HDataType$Float32
+    /// hdecodedaudio = HFile$decode_tolerant(fpath, dtype)
+    /// hdecodedaudio$concealed()
+    /// ```
+    ///
+    /// _________
+    ///
+    fn concealed(&self) -> savvy::Result<Sexp> {
+        let mut list = OwnedListSexp::new(self.concealed.len(), false)?;
+        for (i, range) in self.concealed.iter().enumerate() {
+            let mut values = OwnedRealSexp::try_from_slice([range.start as f64, range.end as f64])?;
+            values.set_names(["start", "end"])?;
+            unsafe { list.set_value_unchecked(i, Sexp::from(values).0) };
+        }
+        list.into()
+    }
+    /// HDecodedAudio
     /// ## invalidate
     ///
     /// `invalidate()`
@@ -129,7 +232,7 @@ impl HFile {
     /// HFile
     /// ## decode
     ///
-    /// `decode(fpath: string, dtype: HDataType) -> HDecodedAudio`
+    /// `decode(fpath: string, dtype: HDataType, track_id: integer | NULL, gapless: bool | NULL) -> HDecodedAudio`
     ///
     /// Decode an audio file, providing its decoded data and the sampling rate.
     ///
@@ -145,6 +248,14 @@ impl HFile {
     ///
     /// A float `HDataType`.
     ///
+    /// - `track_id`
+    ///
+    /// The id of the track to decode, as an integer. The default track is used if `NULL`. Check `HFile$tracks` for the available tracks.
+    ///
+    /// - `gapless`
+    ///
+    /// If `TRUE`, the encoder delay and padding are trimmed from the decoded audio, as given by the LAME/Xing header of MP3 files or by the iTunSMPB tag. Defaults to `FALSE`.
+    ///
     /// #### Returns
     ///
     /// An HDecodedAudio containing:
@@ -153,6 +264,8 @@ impl HFile {
     ///
     /// - The sampling rate as an integer.
     ///
+    /// - The number of trimmed delay and padding frames.
+    ///
     /// #### Examples
     ///
     /// ```r
@@ -164,22 +277,173 @@ impl HFile {
     ///
     /// _________
     ///
-    fn decode(fpath: Sexp, dtype: &HDataType) -> savvy::Result<HDecodedAudio> {
+    fn decode(
+        fpath: Sexp,
+        dtype: &HDataType,
+        track_id: Option<Sexp>,
+        gapless: Option<Sexp>,
+    ) -> savvy::Result<HDecodedAudio> {
         let fpath: &str = fpath.to_scalar()?;
+        let track_id = to_track_id(track_id)?;
+        let gapless: bool = match gapless {
+            Some(gapless) => gapless.to_scalar()?,
+            None => false,
+        };
         match dtype {
             HDataType::Float32 => {
-                let (harray, sr) =
-                    harmonium_io::decode::decode::<f32>(fpath).map_err(HErrorR::from)?;
+                let (harray, sr, trimmed) = if gapless {
+                    decode::decode_gapless::<f32>(fpath, track_id)
+                } else {
+                    decode::decode::<f32>(fpath, track_id)
+                        .map(|(harray, sr)| (harray, sr, decode::HGapless::default()))
+                }
+                .map_err(HErrorR::from)?;
                 let harray = harray.into_dynamic();
                 let harray = HArray(Arc::new(harray));
-                Ok(HDecodedAudio { harray, sr })
+                Ok(HDecodedAudio {
+                    harray,
+                    sr,
+                    delay: trimmed.delay,
+                    padding: trimmed.padding,
+                    concealed: Vec::new(),
+                })
             }
             HDataType::Float64 => {
-                let (harray, sr) =
-                    harmonium_io::decode::decode::<f64>(fpath).map_err(HErrorR::from)?;
+                let (harray, sr, trimmed) = if gapless {
+                    decode::decode_gapless::<f64>(fpath, track_id)
+                } else {
+                    decode::decode::<f64>(fpath, track_id)
+                        .map(|(harray, sr)| (harray, sr, decode::HGapless::default()))
+                }
+                .map_err(HErrorR::from)?;
                 let harray = harray.into_dynamic();
                 let harray = HArray(Arc::new(harray));
-                Ok(HDecodedAudio { harray, sr })
+                Ok(HDecodedAudio {
+                    harray,
+                    sr,
+                    delay: trimmed.delay,
+                    padding: trimmed.padding,
+                    concealed: Vec::new(),
+                })
+            }
+            _ => Err("Operation only allowed for float dtypes.".into()),
+        }
+    }
+
+    /// HFile
+    /// ## decode_tolerant
+    ///
+    /// `decode_tolerant(fpath: string, dtype: HDataType, track_id: integer | NULL, concealment: string | NULL, max_errors: integer | NULL) -> HDecodedAudio`
+    ///
+    /// Decode an audio file, concealing the packets that fail to decode instead of returning an error.
+    ///
+    /// The frames of the corrupt packets and of the packets skipped by the format reader are filled according to `concealment`, and
+    /// so are the frames missing from a truncated file. The output has the same length as the output of `decode` and is the same
+    /// outside the concealed frames.
+    ///
+    /// #### Arguments
+    ///
+    /// - `fpath`
+    ///
+    /// The file path as a string.
+    ///
+    /// - `dtype`
+    ///
+    /// A float `HDataType` to indicate the dtype that the decoded `HArray` will be.
+    ///
+    /// - `track_id`
+    ///
+    /// The id of the track to decode, as an integer. The default track is used if `NULL`.
+    ///
+    /// - `concealment`
+    ///
+    /// How to fill the concealed frames. One of \["zeros", "repeat"\]. "zeros" fills with silence and "repeat" repeats the frames of the
+    /// last packet decoded without errors. Defaults to "zeros".
+    ///
+    /// - `max_errors`
+    ///
+    /// The maximum number of corrupt packets before giving up with an error, as an integer. There is no limit if `NULL`.
+    ///
+    /// #### Returns
+    ///
+    /// An HDecodedAudio containing:
+    ///
+    /// - The decoded audio as a float HArray.
+    ///
+    /// - The sampling rate as an integer.
+    ///
+    /// - The concealed frame ranges.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// fpath = "../../../testfiles/gs-16b-2c-44100hz.flac"
+    /// dtype = HDataType$Float32
+    /// HFile$decode_tolerant(fpath, dtype, max_errors = 10L)
+    /// ```
+    ///
+    /// _________
+    ///
+    fn decode_tolerant(
+        fpath: Sexp,
+        dtype: &HDataType,
+        track_id: Option<Sexp>,
+        concealment: Option<Sexp>,
+        max_errors: Option<Sexp>,
+    ) -> savvy::Result<HDecodedAudio> {
+        let fpath: &str = fpath.to_scalar()?;
+        let track_id = to_track_id(track_id)?;
+        let concealment = match concealment {
+            Some(concealment) => {
+                let concealment: &str = concealment.to_scalar()?;
+                match concealment {
+                    "zeros" => decode::HConcealment::Zeros,
+                    "repeat" => decode::HConcealment::Repeat,
+                    _ => return Err("concealment must be one of \"zeros\" or \"repeat\".".into()),
+                }
+            }
+            None => decode::HConcealment::Zeros,
+        };
+        let max_errors = match max_errors {
+            Some(max_errors) => {
+                let max_errors: i32 = max_errors.to_scalar()?;
+                Some(try_from_i32_to_usize(max_errors)?)
+            }
+            None => None,
+        };
+        let options = decode::HTolerantOptions {
+            concealment,
+            max_errors,
+        };
+        match dtype {
+            HDataType::Float32 => {
+                let (harray, sr, concealed) =
+                    decode::decode_tolerant::<f32>(fpath, track_id, options)
+                        .map_err(HErrorR::from)?;
+                let harray = harray.into_dynamic();
+                let harray = HArray(Arc::new(harray));
+                Ok(HDecodedAudio {
+                    harray,
+                    sr,
+                    delay: 0,
+                    padding: 0,
+                    concealed,
+                })
+            }
+            HDataType::Float64 => {
+                let (harray, sr, concealed) =
+                    decode::decode_tolerant::<f64>(fpath, track_id, options)
+                        .map_err(HErrorR::from)?;
+                let harray = harray.into_dynamic();
+                let harray = HArray(Arc::new(harray));
+                Ok(HDecodedAudio {
+                    harray,
+                    sr,
+                    delay: 0,
+                    padding: 0,
+                    concealed,
+                })
             }
             _ => Err("Operation only allowed for float dtypes.".into()),
         }
@@ -188,7 +452,7 @@ impl HFile {
     /// HFile
     /// ## decode_stream
     ///
-    /// `decode_stream(fpath: string, frames: integer, dtype: HDataType) -> HDecoderStream`
+    /// `decode_stream(fpath: string, frames: integer, dtype: HDataType, track_id: integer | NULL, tail: string | NULL, hop_length: integer | NULL) -> HDecoderStream`
     ///
     /// Creates an `HDecoderStream`, used as an iterator to stream frames of decoded audio.
     ///
@@ -206,6 +470,20 @@ impl HFile {
     ///
     /// A float `HDataType`.
     ///
+    /// - `track_id`
+    ///
+    /// The id of the track to decode, as an integer. The default track is used if `NULL`. Check `HFile$tracks` for the available tracks.
+    ///
+    /// - `tail`
+    ///
+    /// What to do with the last frames of the file, when there are fewer than `frames` left. One of \["discard", "partial", "zero_pad"\].
+    /// `"partial"` returns a shorter `HArray` and `"zero_pad"` pads it with zeros. Defaults to `"discard"` if `NULL`.
+    ///
+    /// - `hop_length`
+    ///
+    /// Number of frames between the start of two consecutive iterations, as an integer. Defaults to `frames` if `NULL`.
+    /// A `hop_length` smaller than `frames` streams overlapping frames, the same used by `HStft`.
+    ///
     /// #### Returns
     ///
     /// An `HDecoderStream`.
@@ -218,6 +496,9 @@ impl HFile {
     /// dtype = HDataType$Float32
     /// frames = 1000L
     /// HFile$decode_stream(fpath, frames, dtype)
+    ///
+    /// # Overlapping frames.
+    /// HFile$decode_stream(fpath, 2048L, dtype, hop_length = 512L)
     /// ```
     ///
     /// _________
@@ -226,31 +507,197 @@ impl HFile {
         fpath: Sexp,
         frames: Sexp,
         dtype: &HDataType,
+        track_id: Option<Sexp>,
+        tail: Option<Sexp>,
+        hop_length: Option<Sexp>,
     ) -> savvy::Result<HDecoderStream> {
         let fpath: &str = fpath.to_scalar()?;
         let frames: i32 = frames.to_scalar()?;
         let frames = try_from_i32_to_usize(frames)?;
+        let frames =
+            NonZero::new(frames).ok_or_else(|| savvy::Error::new("frames can't be zero."))?;
+        let hop_length = match hop_length {
+            Some(hop_length) => {
+                let hop_length: i32 = hop_length.to_scalar()?;
+                let hop_length = try_from_i32_to_usize(hop_length)?;
+                NonZero::new(hop_length)
+                    .ok_or_else(|| savvy::Error::new("hop_length can't be zero."))?
+            }
+            None => frames,
+        };
+        let track_id = to_track_id(track_id)?;
+        let tail = match tail {
+            Some(tail) => {
+                let tail: &str = tail.to_scalar()?;
+                match tail {
+                    "discard" => decode::HStreamTail::Discard,
+                    "partial" => decode::HStreamTail::Partial,
+                    "zero_pad" => decode::HStreamTail::ZeroPad,
+                    _ => {
+                        return Err(
+                            "tail must be one of \"discard\", \"partial\" or \"zero_pad\".".into(),
+                        )
+                    }
+                }
+            }
+            None => decode::HStreamTail::Discard,
+        };
         match dtype {
             HDataType::Float32 => {
                 let streamer =
-                    harmonium_io::decode::stream::<f32>(fpath, frames).map_err(HErrorR::from)?;
+                    harmonium_io::decode::stream_frames::<f32>(fpath, frames, hop_length, track_id)
+                        .map_err(HErrorR::from)?
+                        .with_tail(tail);
                 Ok(HDecoderStream(Box::new(streamer)))
             }
             HDataType::Float64 => {
                 let streamer =
-                    harmonium_io::decode::stream::<f64>(fpath, frames).map_err(HErrorR::from)?;
+                    harmonium_io::decode::stream_frames::<f64>(fpath, frames, hop_length, track_id)
+                        .map_err(HErrorR::from)?
+                        .with_tail(tail);
                 Ok(HDecoderStream(Box::new(streamer)))
             }
             _ => Err("Operation only allowed for float dtypes.".into()),
         }
     }
 
+    /// HFile
+    /// ## load
+    ///
+    /// `load(fpath: string, dtype: HDataType, sr: integer | NULL, mono: bool | NULL, offset: double | NULL, duration: double | NULL, track_id: integer | NULL) -> HDecodedAudio`
+    ///
+    /// Load an audio file, optionally downmixed to mono and resampled to a target sampling rate.
+    ///
+    /// The file is decoded, downmixed and resampled in a single streaming pass, so only the output is kept in memory. The resampler is an FFT resampler whose
+    /// delay is compensated, and the number of output frames is `ceiling(nframes * sr / native_sr)`.
+    ///
+    /// The samples are normalized to fit in the range of \[-1.0, 1.0\].
+    ///
+    /// #### Arguments
+    ///
+    /// - `fpath`
+    ///
+    /// The file path as a string.
+    ///
+    /// - `dtype`
+    ///
+    /// A float `HDataType`.
+    ///
+    /// - `sr`
+    ///
+    /// The target sampling rate, as an integer. The native sampling rate is kept if `NULL`.
+    ///
+    /// - `mono`
+    ///
+    /// If `TRUE`, the channels are averaged into a single one. Defaults to `TRUE`.
+    ///
+    /// - `offset`
+    ///
+    /// Start of the audio to load, in seconds. Defaults to `0`.
+    ///
+    /// - `duration`
+    ///
+    /// Length of the audio to load, in seconds. Loads until the end of the file if `NULL`.
+    ///
+    /// - `track_id`
+    ///
+    /// The id of the track to load, as an integer. The default track is used if `NULL`. Check `HFile$tracks` for the available tracks.
+    ///
+    /// #### Returns
+    ///
+    /// An HDecodedAudio containing:
+    ///
+    /// - The loaded audio as a float HArray, with shape (nchannels, nframes).
+    ///
+    /// - The sampling rate as an integer.
+    ///
+    /// - The delay and padding, which are always 0.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// fpath = "../../../testfiles/gs-16b-2c-44100hz.flac"
+    /// dtype = HDataType$Float32
+    /// HFile$load(fpath, dtype, sr = 22050L, offset = 1, duration = 2)
+    /// ```
+    ///
+    /// _________
+    ///
+    fn load(
+        fpath: Sexp,
+        dtype: &HDataType,
+        sr: Option<Sexp>,
+        mono: Option<Sexp>,
+        offset: Option<Sexp>,
+        duration: Option<Sexp>,
+        track_id: Option<Sexp>,
+    ) -> savvy::Result<HDecodedAudio> {
+        let fpath: &str = fpath.to_scalar()?;
+        let sr = match sr {
+            Some(sr) => {
+                let sr: i32 = sr.to_scalar()?;
+                Some(try_from_i32_to_u32(sr)?)
+            }
+            None => None,
+        };
+        let mono: bool = match mono {
+            Some(mono) => mono.to_scalar()?,
+            None => true,
+        };
+        let offset: f64 = match offset {
+            Some(offset) => offset.to_scalar()?,
+            None => 0.,
+        };
+        let duration = match duration {
+            Some(duration) => {
+                let duration: f64 = duration.to_scalar()?;
+                Some(decode::HTime::Seconds(duration))
+            }
+            None => None,
+        };
+        let options = load::HLoadOptions {
+            sr,
+            mono,
+            offset: decode::HTime::Seconds(offset),
+            duration,
+            track_id: to_track_id(track_id)?,
+        };
+        match dtype {
+            HDataType::Float32 => {
+                let (harray, sr) = load::load::<f32>(fpath, options).map_err(HErrorR::from)?;
+                let harray = harray.into_dynamic();
+                let harray = HArray(Arc::new(harray));
+                Ok(HDecodedAudio {
+                    harray,
+                    sr,
+                    delay: 0,
+                    padding: 0,
+                    concealed: Vec::new(),
+                })
+            }
+            HDataType::Float64 => {
+                let (harray, sr) = load::load::<f64>(fpath, options).map_err(HErrorR::from)?;
+                let harray = harray.into_dynamic();
+                let harray = HArray(Arc::new(harray));
+                Ok(HDecodedAudio {
+                    harray,
+                    sr,
+                    delay: 0,
+                    padding: 0,
+                    concealed: Vec::new(),
+                })
+            }
+            _ => Err("Operation only allowed for float dtypes.".into()),
+        }
+    }
+
     /// HFile
     /// ## metadata
     ///
     /// `metadata(fpath: string, metadata_type: HMetadataType) -> list`
     ///
-    /// Extract text and visual metadata from a file.
+    /// Extract text and visual metadata, markers or broadcast metadata from a file.
     ///
     /// Tags that are part of the container format are preferentially extracted. Additional tags that were found while probing will not be extracted.
     ///
@@ -280,7 +727,7 @@ impl HFile {
     ///
     ///     The value of the Tag.
     ///
-    /// Each `VisualMetadata` will be comprised of the following fields:
+    /// Each `VisualMetadata` will be a named list comprised of the following fields:
     ///
     /// - usage
     ///
@@ -294,13 +741,14 @@ impl HFile {
     ///
     /// - dimensions
     ///
-    ///     The dimensions (width and height) of the Visual, represented in pixels.
+    ///     An integer vector with the `width` and `height` of the Visual, represented in pixels, or `NA` if unknown.
     ///     Note: This value may not be accurate as it comes from metadata, not the
     ///     embedded graphic itself. Consider it only a hint.
     ///
-    /// - bits_per_pixel
+    /// - bpp
+    ///
+    ///     The number of bits-per-pixel (aka bit-depth) of the unencoded image, as an integer, or `NA` if unknown.
     ///
-    ///     The number of bits-per-pixel (aka bit-depth) of the unencoded image.
     /// - color_mode
     ///
     ///     Indicates how the color of a pixel is encoded in a Visual. Variants:
@@ -314,13 +762,13 @@ impl HFile {
     ///         Each pixel in the Visual stores an index into a color palette containing the color information. The value stored by this variant indicates the number
     ///         of colors in the color palette.
     ///
-    /// - size
+    /// - data
     ///
-    ///     Size of the image in bytes.
+    ///     The encoded image as a raw vector, in the format given by `media_type`. It can be saved with `writeBin`.
     ///
-    /// - tag
+    /// - tags
     ///
-    ///     `Tag` with the following fields:
+    ///     A list of `Tag`, each with the following fields:
     ///
     ///      - tag_key
     ///
@@ -338,6 +786,38 @@ impl HFile {
     ///
     ///        The value of the Tag.
     ///
+    /// `HMetadataType$Markers` returns the chapters, cue points and loops, sorted by their start. They are read from WAV `cue `, `adtl` and `smpl` chunks,
+    /// FLAC cuesheets, ID3v2 `CHAP` frames, MP4 QuickTime and Nero chapters, MKV chapters and Vorbis Comment chapters. Each marker is a named list with the following fields:
+    ///
+    /// - label
+    ///
+    ///     The label of the marker, or an empty string.
+    ///
+    /// - start
+    ///
+    ///     The first frame of the marker, as a double.
+    ///
+    /// - end
+    ///
+    ///     The frame after the last one of the marker, as a double. `NA` for cue points and for a last chapter that lasts until the end of the file.
+    ///
+    /// - loop_type
+    ///
+    ///     One of \["forward", "alternating", "backward"\] for loops, `NA` otherwise.
+    ///
+    /// `HMetadataType$Broadcast` returns the `bext` and `iXML` chunks of a Broadcast Wave file, as a named list with the following fields. Each one is an
+    /// empty list if the chunk is missing.
+    ///
+    /// - bext
+    ///
+    ///     A named list with the description, originator, originator_reference, origination_date (`yyyy-mm-dd`), origination_time (`hh:mm:ss`),
+    ///     time_reference, version and coding_history. time_reference is the position of the first frame of the file in frames since midnight, as a double,
+    ///     and can be used to align recordings of different devices.
+    ///
+    /// - ixml
+    ///
+    ///     A named list with the project, scene, take, tape and note, `NA` if missing, circled, a logical, and xml, the whole iXML document.
+    ///
     /// #### Arguments
     ///
     /// - `fpath`
@@ -369,6 +849,8 @@ impl HFile {
             HMetadataType::All => decode::HMetadataType::All,
             HMetadataType::Text => decode::HMetadataType::Text,
             HMetadataType::Visual => decode::HMetadataType::Visual,
+            HMetadataType::Markers => decode::HMetadataType::Markers,
+            HMetadataType::Broadcast => decode::HMetadataType::Broadcast,
         };
 
         let opt_metadata =
@@ -397,6 +879,8 @@ impl HFile {
                 }
                 decode::HMetadata::Text(text) => list_from_textmetadata(text)?.into(),
                 decode::HMetadata::Visual(visual) => list_from_visualmetadata(visual)?.into(),
+                decode::HMetadata::Markers(markers) => list_from_markers(markers)?.into(),
+                decode::HMetadata::Broadcast(broadcast) => list_from_broadcast(*broadcast)?.into(),
             }
         } else {
             let list = OwnedListSexp::new(0, false)?;
@@ -407,26 +891,63 @@ impl HFile {
     /// HFile
     /// ## params
     ///
-    /// `params(fpath: string) -> atomicvector`
+    /// `params(fpath: string, track_id: integer | NULL) -> list`
     ///
     /// Get audio parameters from a file.
     ///
     /// Note that this avoids loading the contents into memory, and is therefore useful for querying these parameters from long files.
     ///
+    /// When the number of frames is missing from the header, the packets are scanned to compute it.
+    ///
     /// #### Arguments
     ///
     /// - `fpath`
     ///
     /// The file path as a string.
     ///
+    /// - `track_id`
+    ///
+    /// The id of the track to query, as an integer. The default track is used if `NULL`.
+    ///
     /// #### Returns
     ///
-    /// A double atomic vector containing, in order:
+    /// A named list containing:
+    ///
+    /// - sr
+    ///
+    ///     The sampling rate in Hz, as an integer.
+    ///
+    /// - nframes
+    ///
+    ///     The number of frames, as a double.
+    ///
+    /// - nchannels
+    ///
+    ///     The number of channels, as an integer.
+    ///
+    /// - channel_layout
+    ///
+    ///     A character vector with the name of each channel, in the order they are stored.
+    ///
+    /// - codec
+    ///
+    ///     Short name of the codec.
+    ///
+    /// - bits_per_sample
+    ///
+    ///     An integer, or `NA` if unknown.
+    ///
+    /// - sample_format
+    ///
+    ///     The sample format of the encoded samples, or `NA` if unknown. One of \["u8", "u16", "u24", "u32", "s8", "s16", "s24", "s32", "f32", "f64"\].
+    ///
+    /// - bitrate
     ///
-    /// - sampling rate in Hz.
-    /// - number of frames.
-    /// - number of channels.
-    /// - duration in seconds.
+    ///     The average bitrate in bits per second, as a double, or `NA` if unknown. Computed from the file size, so it includes the container overhead.
+    ///
+    /// - duration
+    ///
+    ///     The duration in seconds.
     ///
     /// #### Examples
     ///
@@ -438,19 +959,75 @@ impl HFile {
     ///
     /// _________
     ///
-    fn params(fpath: Sexp) -> savvy::Result<Sexp> {
+    fn params(fpath: Sexp, track_id: Option<Sexp>) -> savvy::Result<Sexp> {
         let fpath: &str = fpath.to_scalar()?;
-        let (sr, nframes, nchannels, duration) =
-            decode::get_params_from_file(fpath).map_err(HErrorR::from)?;
-        let arr = [sr as f64, nframes as f64, nchannels as f64, duration];
-        let real_sexp = OwnedRealSexp::try_from_slice(arr.as_slice())?;
-        Ok(real_sexp.into())
+        let track_id = to_track_id(track_id)?;
+        let info = decode::get_params_from_file(fpath, track_id).map_err(HErrorR::from)?;
+
+        let sr: i32 = info
+            .sr
+            .try_into()
+            .map_err(|_| savvy::Error::new("Cannot convert u32 to i32."))?;
+        let nchannels: i32 = info
+            .nchannels
+            .try_into()
+            .map_err(|_| savvy::Error::new("Cannot convert usize to i32."))?;
+        let bits_per_sample = info
+            .bits_per_sample
+            .and_then(|bits_per_sample| i32::try_from(bits_per_sample).ok())
+            .unwrap_or(i32::na());
+        let sample_format = info.sample_format.as_deref().unwrap_or(<&str>::na());
+        let bitrate = info
+            .bitrate
+            .map(|bitrate| bitrate as f64)
+            .unwrap_or(f64::na());
+
+        let mut list = OwnedListSexp::new(9, true)?;
+        list.set_name_and_value(0, "sr", OwnedIntegerSexp::try_from_scalar(sr)?)?;
+        list.set_name_and_value(
+            1,
+            "nframes",
+            OwnedRealSexp::try_from_scalar(info.nframes as f64)?,
+        )?;
+        list.set_name_and_value(
+            2,
+            "nchannels",
+            OwnedIntegerSexp::try_from_scalar(nchannels)?,
+        )?;
+        list.set_name_and_value(
+            3,
+            "channel_layout",
+            OwnedStringSexp::try_from_slice(info.channel_layout.as_slice())?,
+        )?;
+        list.set_name_and_value(
+            4,
+            "codec",
+            OwnedStringSexp::try_from_scalar(info.codec.as_str())?,
+        )?;
+        list.set_name_and_value(
+            5,
+            "bits_per_sample",
+            OwnedIntegerSexp::try_from_scalar(bits_per_sample)?,
+        )?;
+        list.set_name_and_value(
+            6,
+            "sample_format",
+            OwnedStringSexp::try_from_scalar(sample_format)?,
+        )?;
+        list.set_name_and_value(7, "bitrate", OwnedRealSexp::try_from_scalar(bitrate)?)?;
+        list.set_name_and_value(
+            8,
+            "duration",
+            OwnedRealSexp::try_from_scalar(info.duration)?,
+        )?;
+
+        Ok(list.into())
     }
 
     /// HFile
     /// ## verify
     ///
-    /// `verify(fpath: string) -> string`
+    /// `verify(fpath: string, track_id: integer | NULL) -> string`
     ///
     /// Verify an audio file, if supported by the decoder.
     ///
@@ -462,6 +1039,10 @@ impl HFile {
     ///
     /// The file path as a string.
     ///
+    /// - `track_id`
+    ///
+    /// The id of the track to verify, as an integer. The default track is used if `NULL`.
+    ///
     /// #### Returns
     ///
     /// A string.
@@ -478,16 +1059,260 @@ impl HFile {
     ///
     /// _________
     ///
-    fn verify(fpath: Sexp) -> savvy::Result<Sexp> {
+    fn verify(fpath: Sexp, track_id: Option<Sexp>) -> savvy::Result<Sexp> {
         let fpath: &str = fpath.to_scalar()?;
-        let verified = match decode::verify_file(fpath).map_err(HErrorR::from)? {
-            decode::HVerifyDecode::Passed => "passed",
-            decode::HVerifyDecode::Failed => "failed",
-            decode::HVerifyDecode::NotSupported => "not_supported",
-        };
-        let string_sexp: OwnedStringSexp = verified.try_into()?;
+        let track_id = to_track_id(track_id)?;
+        let verified = decode::verify_file(fpath, track_id).map_err(HErrorR::from)?;
+        let string_sexp: OwnedStringSexp = verify_result_str(verified).try_into()?;
         Ok(string_sexp.into())
     }
+
+    /// HFile
+    /// ## verify_report
+    ///
+    /// `verify_report(fpath: string, track_id: integer | NULL, lenient: bool | NULL) -> list`
+    ///
+    /// Verify an audio file and report the packets that failed to decode and the checksums.
+    ///
+    /// Unlike `verify`, a corrupt packet is not an error but is reported together with its position and the error returned by the
+    /// decoder. Packets skipped by the format reader are reported as missing frames.
+    ///
+    /// #### Arguments
+    ///
+    /// - `fpath`
+    ///
+    /// The file path as a string.
+    ///
+    /// - `track_id`
+    ///
+    /// The id of the track to verify, as an integer. The default track is used if `NULL`.
+    ///
+    /// - `lenient`
+    ///
+    /// If `TRUE`, decoding goes on past corrupt packets so that all of them are reported. Otherwise it stops at the first one.
+    /// Defaults to `FALSE`.
+    ///
+    /// #### Returns
+    ///
+    /// A named list with the following fields:
+    ///
+    /// - result
+    ///
+    /// One of \["passed", "failed", "not_supported"\]. "failed" if a packet failed to decode or the checksum does not match.
+    ///
+    /// - npackets
+    ///
+    /// The number of packets read, as a double.
+    ///
+    /// - nframes
+    ///
+    /// The number of frames decoded without errors, as a double.
+    ///
+    /// - errors
+    ///
+    /// A list with a named list for each failed packet, with the packet index (starting at 0) in `packet`, its first frame
+    /// in `frame` and the error message in `error`.
+    ///
+    /// - checksum_type
+    ///
+    /// One of \["md5", "crc8", "crc16", "crc32", "other"\], or `NA` if the file has no checksum.
+    ///
+    /// - expected_checksum
+    ///
+    /// The checksum stored in the file as a hexadecimal string, or `NA`.
+    ///
+    /// - computed_checksum
+    ///
+    /// The checksum of the decoded samples as a hexadecimal string, or `NA`. Only computed for MD5, which is used by FLAC.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// fpath = "../../../testfiles/gs-16b-2c-44100hz.flac"
+    /// HFile$verify_report(fpath, lenient = TRUE)
+    /// ```
+    ///
+    /// _________
+    ///
+    fn verify_report(
+        fpath: Sexp,
+        track_id: Option<Sexp>,
+        lenient: Option<Sexp>,
+    ) -> savvy::Result<Sexp> {
+        let fpath: &str = fpath.to_scalar()?;
+        let track_id = to_track_id(track_id)?;
+        let lenient: bool = match lenient {
+            Some(lenient) => lenient.to_scalar()?,
+            None => false,
+        };
+        let report = decode::verify_file_report(fpath, track_id, lenient).map_err(HErrorR::from)?;
+
+        let mut errors = OwnedListSexp::new(report.errors.len(), false)?;
+        for (i, error) in report.errors.iter().enumerate() {
+            let mut inner_list = OwnedListSexp::new(3, true)?;
+            inner_list.set_name_and_value(
+                0,
+                "packet",
+                OwnedRealSexp::try_from_scalar(error.packet as f64)?,
+            )?;
+            inner_list.set_name_and_value(
+                1,
+                "frame",
+                OwnedRealSexp::try_from_scalar(error.frame as f64)?,
+            )?;
+            inner_list.set_name_and_value(
+                2,
+                "error",
+                OwnedStringSexp::try_from_scalar(error.error.as_str())?,
+            )?;
+            unsafe { errors.set_value_unchecked(i, Sexp::from(inner_list).0) };
+        }
+
+        let mut list = OwnedListSexp::new(7, true)?;
+        list.set_name_and_value(
+            0,
+            "result",
+            OwnedStringSexp::try_from_scalar(verify_result_str(report.result))?,
+        )?;
+        list.set_name_and_value(
+            1,
+            "npackets",
+            OwnedRealSexp::try_from_scalar(report.npackets as f64)?,
+        )?;
+        list.set_name_and_value(
+            2,
+            "nframes",
+            OwnedRealSexp::try_from_scalar(report.nframes as f64)?,
+        )?;
+        list.set_name_and_value(3, "errors", errors)?;
+        let strings = [
+            ("checksum_type", &report.checksum_type),
+            ("expected_checksum", &report.expected_checksum),
+            ("computed_checksum", &report.computed_checksum),
+        ];
+        for (i, (name, value)) in strings.into_iter().enumerate() {
+            let value = value.as_deref().unwrap_or(<&str>::na());
+            list.set_name_and_value(i + 4, name, OwnedStringSexp::try_from_scalar(value)?)?;
+        }
+
+        Ok(list.into())
+    }
+
+    /// HFile
+    /// ## tracks
+    ///
+    /// `tracks(fpath: string) -> list`
+    ///
+    /// List all the tracks in a file.
+    ///
+    /// Each track is a named list with the following fields:
+    ///
+    /// - id
+    ///
+    ///     The track id as an integer, to be used to select the track when decoding.
+    ///
+    /// - codec
+    ///
+    ///     Short name of the codec.
+    ///
+    /// - sr
+    ///
+    ///     The sampling rate in Hz, or `NA` if unknown.
+    ///
+    /// - nchannels
+    ///
+    ///     The number of channels, or `NA` if unknown.
+    ///
+    /// - nframes
+    ///
+    ///     The number of frames as a double, or `NA` if unknown.
+    ///
+    /// - language
+    ///
+    ///     The language of the track, or `NA` if unknown.
+    ///
+    /// #### Arguments
+    ///
+    /// - `fpath`
+    ///
+    /// The file path as a string.
+    ///
+    /// #### Returns
+    ///
+    /// A list of tracks.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// fpath = "../../../testfiles/gs-16b-2c-44100hz.mp4"
+    /// HFile$tracks(fpath)
+    /// ```
+    ///
+    /// _________
+    ///
+    fn tracks(fpath: Sexp) -> savvy::Result<Sexp> {
+        let fpath: &str = fpath.to_scalar()?;
+        let tracks = decode::tracks_from_file(fpath).map_err(HErrorR::from)?;
+        let mut list = OwnedListSexp::new(tracks.len(), false)?;
+
+        for (i, track) in tracks.iter().enumerate() {
+            let id: i32 = track
+                .id
+                .try_into()
+                .map_err(|_| savvy::Error::new("Cannot convert u32 to i32."))?;
+            let sr = track
+                .sr
+                .and_then(|sr| i32::try_from(sr).ok())
+                .unwrap_or(i32::na());
+            let nchannels = track
+                .nchannels
+                .and_then(|nchannels| i32::try_from(nchannels).ok())
+                .unwrap_or(i32::na());
+            let nframes = track
+                .nframes
+                .map(|nframes| nframes as f64)
+                .unwrap_or(f64::na());
+            let language = track.language.as_deref().unwrap_or(<&str>::na());
+
+            let mut inner_list = OwnedListSexp::new(6, true)?;
+            inner_list.set_name_and_value(0, "id", OwnedIntegerSexp::try_from_scalar(id)?)?;
+            inner_list.set_name_and_value(
+                1,
+                "codec",
+                OwnedStringSexp::try_from_scalar(track.codec.as_str())?,
+            )?;
+            inner_list.set_name_and_value(2, "sr", OwnedIntegerSexp::try_from_scalar(sr)?)?;
+            inner_list.set_name_and_value(
+                3,
+                "nchannels",
+                OwnedIntegerSexp::try_from_scalar(nchannels)?,
+            )?;
+            inner_list.set_name_and_value(
+                4,
+                "nframes",
+                OwnedRealSexp::try_from_scalar(nframes)?,
+            )?;
+            inner_list.set_name_and_value(
+                5,
+                "language",
+                OwnedStringSexp::try_from_scalar(language)?,
+            )?;
+            unsafe { list.set_value_unchecked(i, Sexp::from(inner_list).0) };
+        }
+
+        Ok(list.into())
+    }
+}
+
+fn to_track_id(track_id: Option<Sexp>) -> savvy::Result<Option<u32>> {
+    track_id
+        .map(|track_id| {
+            let track_id: i32 = track_id.to_scalar()?;
+            try_from_i32_to_u32(track_id)
+        })
+        .transpose()
 }
 
 #[savvy]
@@ -524,10 +1349,79 @@ impl HDecoderStream {
     fn stream(&mut self) -> savvy::Result<HArray> {
         self.0.next()
     }
+
+    /// HDecoderStream
+    /// ## seek
+    ///
+    /// `seek(frame: double)`
+    ///
+    /// Seeks to a frame, so it is the first frame returned in the next call to `stream`.
+    ///
+    /// The seek is sample-exact: the stream continues exactly as if all the frames before `frame` had been streamed.
+    ///
+    /// #### Arguments
+    ///
+    /// - `frame`
+    ///
+    /// The frame index to seek to, as a double. Indexing starts at 0.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// fpath = "../../../testfiles/gs-16b-2c-44100hz.flac"
+    /// dtype = HDataType$Float32
+    /// frames = 1000L
+    /// hdecoder_stream = HFile$decode_stream(fpath, frames, dtype)
+    /// hdecoder_stream$seek(44100)
+    /// hdecoder_stream$stream()
+    /// ```
+    ///
+    /// _________
+    ///
+    fn seek(&mut self, frame: Sexp) -> savvy::Result<()> {
+        let frame: f64 = frame.to_scalar()?;
+        if frame < 0. || frame.fract() != 0. {
+            return Err("frame must be a non-negative whole number.".into());
+        }
+        self.0.seek(frame as u64)
+    }
+
+    /// HDecoderStream
+    /// ## position
+    ///
+    /// `position() -> double`
+    ///
+    /// Gets the index of the first frame to be returned in the next call to `stream`.
+    ///
+    /// #### Returns
+    ///
+    /// A double.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// fpath = "../../../testfiles/gs-16b-2c-44100hz.flac"
+    /// dtype = HDataType$Float32
+    /// frames = 1000L
+    /// hdecoder_stream = HFile$decode_stream(fpath, frames, dtype)
+    /// hdecoder_stream$stream()
+    /// hdecoder_stream$position() # 1000
+    /// ```
+    ///
+    /// _________
+    ///
+    fn position(&self) -> savvy::Result<Sexp> {
+        let real_sexp = OwnedRealSexp::try_from_scalar(self.0.position() as f64)?;
+        real_sexp.into()
+    }
 }
 
 pub trait HDecoderStreamR {
     fn next(&mut self) -> savvy::Result<HArray>;
+    fn seek(&mut self, frame: u64) -> savvy::Result<()>;
+    fn position(&self) -> u64;
 }
 
 impl HDecoderStreamR for harmonium_io::decode::DecoderStream<f32> {
@@ -538,6 +1432,14 @@ impl HDecoderStreamR for harmonium_io::decode::DecoderStream<f32> {
             Err("The iterator has no more values to yield.".into())
         }
     }
+
+    fn seek(&mut self, frame: u64) -> savvy::Result<()> {
+        harmonium_io::decode::DecoderStream::seek(self, frame).map_err(|e| HErrorR::from(e).into())
+    }
+
+    fn position(&self) -> u64 {
+        harmonium_io::decode::DecoderStream::position(self)
+    }
 }
 
 impl HDecoderStreamR for harmonium_io::decode::DecoderStream<f64> {
@@ -548,6 +1450,14 @@ impl HDecoderStreamR for harmonium_io::decode::DecoderStream<f64> {
             Err("The iterator has no more values to yield.".into())
         }
     }
+
+    fn seek(&mut self, frame: u64) -> savvy::Result<()> {
+        harmonium_io::decode::DecoderStream::seek(self, frame).map_err(|e| HErrorR::from(e).into())
+    }
+
+    fn position(&self) -> u64 {
+        harmonium_io::decode::DecoderStream::position(self)
+    }
 }
 
 fn list_from_textmetadata(text: decode::HTextMetadata) -> savvy::Result<OwnedListSexp> {
@@ -572,27 +1482,24 @@ fn list_from_visualmetadata(visual: decode::HVisualMetadata) -> savvy::Result<Ow
     if let Some(svm_vec) = visual.0 {
         let mut list = OwnedListSexp::new(svm_vec.len(), false)?;
         for (i, hsvm) in svm_vec.as_slice().iter().enumerate() {
-            let mut inner_list = OwnedListSexp::new(2, false)?;
-            let mut string_sexp = OwnedStringSexp::try_from_slice([
-                hsvm.usage.as_str(),
-                hsvm.media_type.as_str(),
-                hsvm.dimensions.as_str(),
-                hsvm.bpp.as_str(),
-                hsvm.color_mode.as_str(),
-                hsvm.size.as_str(),
-            ])?;
-            string_sexp.set_names([
-                "usage",
-                "media_type",
-                "dimensions",
-                "bpp",
-                "color_mode",
-                "size",
-            ])?;
-            unsafe { inner_list.set_value_unchecked(0, Sexp::from(string_sexp).0) };
+            let (width, height) = hsvm
+                .dimensions
+                .map(|(width, height)| {
+                    (
+                        i32::try_from(width).unwrap_or(i32::na()),
+                        i32::try_from(height).unwrap_or(i32::na()),
+                    )
+                })
+                .unwrap_or((i32::na(), i32::na()));
+            let mut dimensions = OwnedIntegerSexp::try_from_slice([width, height])?;
+            dimensions.set_names(["width", "height"])?;
+            let bpp = hsvm
+                .bpp
+                .and_then(|bpp| i32::try_from(bpp).ok())
+                .unwrap_or(i32::na());
 
-            let inner_inner_list = if let Some(tags_vec) = &hsvm.tags_vec {
-                let mut inner_inner_list = OwnedListSexp::new(tags_vec.len(), false)?;
+            let tags = if let Some(tags_vec) = &hsvm.tags_vec {
+                let mut tags = OwnedListSexp::new(tags_vec.len(), false)?;
                 for (i, htag) in tags_vec.as_slice().iter().enumerate() {
                     let mut string_sexp = OwnedStringSexp::try_from_slice([
                         htag.tag_key.as_str(),
@@ -600,14 +1507,37 @@ fn list_from_visualmetadata(visual: decode::HVisualMetadata) -> savvy::Result<Ow
                         htag.tag_value.as_str(),
                     ])?;
                     string_sexp.set_names(["tag_key", "tag_std_key", "tag_value"])?;
-                    unsafe { inner_inner_list.set_value_unchecked(i, Sexp::from(string_sexp).0) };
+                    unsafe { tags.set_value_unchecked(i, Sexp::from(string_sexp).0) };
                 }
-                inner_inner_list
+                tags
             } else {
                 OwnedListSexp::new(0, false)?
             };
 
-            unsafe { inner_list.set_value_unchecked(1, Sexp::from(inner_inner_list).0) };
+            let mut inner_list = OwnedListSexp::new(7, true)?;
+            inner_list.set_name_and_value(
+                0,
+                "usage",
+                OwnedStringSexp::try_from_scalar(hsvm.usage.as_str())?,
+            )?;
+            inner_list.set_name_and_value(
+                1,
+                "media_type",
+                OwnedStringSexp::try_from_scalar(hsvm.media_type.as_str())?,
+            )?;
+            inner_list.set_name_and_value(2, "dimensions", dimensions)?;
+            inner_list.set_name_and_value(3, "bpp", OwnedIntegerSexp::try_from_scalar(bpp)?)?;
+            inner_list.set_name_and_value(
+                4,
+                "color_mode",
+                OwnedStringSexp::try_from_scalar(hsvm.color_mode.as_str())?,
+            )?;
+            inner_list.set_name_and_value(
+                5,
+                "data",
+                OwnedRawSexp::try_from_slice(hsvm.data.as_slice())?,
+            )?;
+            inner_list.set_name_and_value(6, "tags", tags)?;
 
             unsafe { list.set_value_unchecked(i, Sexp::from(inner_list).0) };
         }
@@ -616,3 +1546,120 @@ fn list_from_visualmetadata(visual: decode::HVisualMetadata) -> savvy::Result<Ow
         OwnedListSexp::new(0, false)
     }
 }
+
+fn verify_result_str(result: decode::HVerifyDecode) -> &'static str {
+    match result {
+        decode::HVerifyDecode::Passed => "passed",
+        decode::HVerifyDecode::Failed => "failed",
+        decode::HVerifyDecode::NotSupported => "not_supported",
+    }
+}
+
+fn list_from_markers(markers: decode::HMarkers) -> savvy::Result<OwnedListSexp> {
+    if let Some(markers_vec) = markers.0 {
+        let mut list = OwnedListSexp::new(markers_vec.len(), false)?;
+        for (i, marker) in markers_vec.iter().enumerate() {
+            let end = marker.end.map(|end| end as f64).unwrap_or(f64::na());
+            let loop_type = match marker.loop_type {
+                Some(decode::HLoopType::Forward) => "forward",
+                Some(decode::HLoopType::Alternating) => "alternating",
+                Some(decode::HLoopType::Backward) => "backward",
+                None => <&str>::na(),
+            };
+
+            let mut inner_list = OwnedListSexp::new(4, true)?;
+            inner_list.set_name_and_value(
+                0,
+                "label",
+                OwnedStringSexp::try_from_scalar(marker.label.as_str())?,
+            )?;
+            inner_list.set_name_and_value(
+                1,
+                "start",
+                OwnedRealSexp::try_from_scalar(marker.start as f64)?,
+            )?;
+            inner_list.set_name_and_value(2, "end", OwnedRealSexp::try_from_scalar(end)?)?;
+            inner_list.set_name_and_value(
+                3,
+                "loop_type",
+                OwnedStringSexp::try_from_scalar(loop_type)?,
+            )?;
+
+            unsafe { list.set_value_unchecked(i, Sexp::from(inner_list).0) };
+        }
+        Ok(list)
+    } else {
+        OwnedListSexp::new(0, false)
+    }
+}
+
+fn list_from_broadcast(broadcast: decode::HBroadcastMetadata) -> savvy::Result<OwnedListSexp> {
+    let bext = if let Some(bext) = broadcast.bext {
+        let mut list = OwnedListSexp::new(8, true)?;
+        let strings = [
+            ("description", bext.description.as_str()),
+            ("originator", bext.originator.as_str()),
+            ("originator_reference", bext.originator_reference.as_str()),
+            ("origination_date", bext.origination_date.as_str()),
+            ("origination_time", bext.origination_time.as_str()),
+        ];
+        for (i, (name, value)) in strings.into_iter().enumerate() {
+            list.set_name_and_value(i, name, OwnedStringSexp::try_from_scalar(value)?)?;
+        }
+        list.set_name_and_value(
+            5,
+            "time_reference",
+            OwnedRealSexp::try_from_scalar(bext.time_reference as f64)?,
+        )?;
+        list.set_name_and_value(
+            6,
+            "version",
+            OwnedIntegerSexp::try_from_scalar(bext.version as i32)?,
+        )?;
+        list.set_name_and_value(
+            7,
+            "coding_history",
+            OwnedStringSexp::try_from_scalar(bext.coding_history.as_str())?,
+        )?;
+        list
+    } else {
+        OwnedListSexp::new(0, false)?
+    };
+
+    let ixml = if let Some(ixml) = broadcast.ixml {
+        let mut list = OwnedListSexp::new(7, true)?;
+        let strings = [
+            ("project", &ixml.project),
+            ("scene", &ixml.scene),
+            ("take", &ixml.take),
+            ("tape", &ixml.tape),
+            ("note", &ixml.note),
+        ];
+        for (i, (name, value)) in strings.into_iter().enumerate() {
+            let value = value.as_deref().unwrap_or(<&str>::na());
+            list.set_name_and_value(i, name, OwnedStringSexp::try_from_scalar(value)?)?;
+        }
+        let circled = match ixml.circled {
+            Some(circled) => OwnedLogicalSexp::try_from_scalar(circled)?,
+            None => {
+                let mut circled = OwnedLogicalSexp::new(1)?;
+                circled.set_na(0)?;
+                circled
+            }
+        };
+        list.set_name_and_value(5, "circled", circled)?;
+        list.set_name_and_value(
+            6,
+            "xml",
+            OwnedStringSexp::try_from_scalar(ixml.xml.as_str())?,
+        )?;
+        list
+    } else {
+        OwnedListSexp::new(0, false)?
+    };
+
+    let mut list = OwnedListSexp::new(2, true)?;
+    list.set_name_and_value(0, "bext", bext)?;
+    list.set_name_and_value(1, "ixml", ixml)?;
+    Ok(list)
+}
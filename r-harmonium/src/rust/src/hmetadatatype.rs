@@ -5,7 +5,7 @@ use savvy::{r_println, savvy, OwnedLogicalSexp, Sexp};
 /// HMetadataType
 /// A metadata type representation.
 ///
-/// Supports `All`, `Text` and `Visual` types.
+/// Supports `All`, `Text`, `Visual`, `Markers` and `Broadcast` types.
 ///
 /// # Methods
 ///
@@ -15,6 +15,8 @@ pub enum HMetadataType {
     All,
     Text,
     Visual,
+    Markers,
+    Broadcast,
 }
 
 #[savvy]
@@ -127,6 +129,8 @@ impl fmt::Display for HMetadataType {
             HMetadataType::All => write!(f, "All"),
             HMetadataType::Text => write!(f, "Text"),
             HMetadataType::Visual => write!(f, "Visual"),
+            HMetadataType::Markers => write!(f, "Markers"),
+            HMetadataType::Broadcast => write!(f, "Broadcast"),
         }
     }
 }
@@ -0,0 +1,316 @@
+use crate::{
+    conversions::{
+        try_from_i32_to_u32, try_from_i32_to_usize, try_from_usize_to_int_sexp, ToScalar,
+    },
+    errors::HErrorR,
+    harray::HArray,
+    hdatatype::HDataType,
+};
+use harmonium_io::{
+    decode::HTime,
+    mix::{self, HMixOptions},
+    play,
+};
+use ndarray::{Axis, Ix1, Ix2, IxDyn};
+use savvy::{savvy, OwnedIntegerSexp, OwnedRealSexp, Sexp};
+use std::sync::Arc;
+
+pub trait HMixerR: Send {
+    fn add(&mut self, harray: &HArray, sr: u32, options: HMixOptions) -> savvy::Result<()>;
+    fn append_to(&self, haudiosink: &play::HAudioSink);
+    fn dtype(&self) -> savvy::Result<HDataType>;
+    fn len(&self) -> usize;
+    fn nframes(&self) -> u64;
+    fn render(&self) -> savvy::Result<HArray>;
+}
+
+/// HMixer
+/// Layers several sounds in a single audio, each one with its own gain, pan and start offset.
+///
+/// Sounds are resampled to the mix sampling rate when they are added. Mono sounds are played on every channel of the mix, and
+/// other sounds must have as many channels as the mix.
+///
+/// The mix can be rendered to an `HArray` or played by an `HAudioSink`.
+///
+/// # Methods
+///
+#[savvy]
+pub struct HMixer(pub Box<dyn HMixerR>);
+
+#[savvy]
+impl HMixer {
+    /// HMixer
+    /// ## new
+    ///
+    /// `new(nchannels: integer, sr: integer, dtype: HDataType) -> HMixer`
+    ///
+    /// Creates an empty mix.
+    ///
+    /// #### Arguments
+    ///
+    /// - `nchannels`
+    ///
+    /// An integer. The number of channels of the mix.
+    ///
+    /// - `sr`
+    ///
+    /// An integer. The sampling rate of the mix.
+    ///
+    /// - `dtype`
+    ///
+    /// A float `HDataType` to indicate the dtype of the mix. The sounds added to the mix must have the same dtype.
+    ///
+    /// #### Returns
+    ///
+    /// An `HMixer`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// hmixer = HMixer$new(2L, 44100L, HDataType$Float32)
+    /// ```
+    ///
+    /// _________
+    ///
+    fn new(nchannels: Sexp, sr: Sexp, dtype: &HDataType) -> savvy::Result<HMixer> {
+        let nchannels: i32 = nchannels.to_scalar()?;
+        let nchannels = try_from_i32_to_usize(nchannels)?;
+        let sr: i32 = sr.to_scalar()?;
+        let sr = try_from_i32_to_u32(sr)?;
+
+        match dtype {
+            HDataType::Float32 => Ok(HMixer(Box::new(
+                mix::HMixer::<f32>::new(nchannels, sr).map_err(HErrorR::from)?,
+            ))),
+            HDataType::Float64 => Ok(HMixer(Box::new(
+                mix::HMixer::<f64>::new(nchannels, sr).map_err(HErrorR::from)?,
+            ))),
+            _ => Err("The HMixer dtype must be Float32 or Float64.".into()),
+        }
+    }
+
+    /// HMixer
+    /// ## add
+    ///
+    /// `add(harray: HArray, sr: integer, gain: double, pan: double, offset: double)`
+    ///
+    /// Adds a sound to the mix.
+    ///
+    /// #### Arguments
+    ///
+    /// - `harray`
+    ///
+    /// An `HArray` with the same dtype as the mix. It must be mono or have as many channels as the mix.
+    ///
+    /// - `sr`
+    ///
+    /// An integer. The sampling rate of the sound. The sound is resampled if it differs from the mix sampling rate.
+    ///
+    /// - `gain`
+    ///
+    /// A `double`. The linear gain applied to the sound. Defaults to 1 if `NULL`.
+    ///
+    /// - `pan`
+    ///
+    /// A `double`. The position of the sound between the left (-1) and right (1) channels of a stereo mix. The channel opposite
+    /// to the pan is attenuated linearly. Defaults to 0 (centered) if `NULL`, which is the only value allowed in mixes that are not stereo.
+    ///
+    /// - `offset`
+    ///
+    /// A `double`. The start of the sound in the mix in seconds. Defaults to 0 if `NULL`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// hdecodedaudio = HFile$decode(fpath = "./r-harmonium/testfiles/gs-16b-2c-44100hz.wav", dtype = HDataType$Float32)
+    /// hmixer = HMixer$new(2L, 44100L, HDataType$Float32)
+    /// hmixer$add(hdecodedaudio$harray(), hdecodedaudio$sr())
+    /// hmixer$add(hdecodedaudio$harray(), hdecodedaudio$sr(), gain = 0.5, pan = -1, offset = 2)
+    /// ```
+    ///
+    /// _________
+    ///
+    fn add(
+        &mut self,
+        harray: &HArray,
+        sr: Sexp,
+        gain: Option<Sexp>,
+        pan: Option<Sexp>,
+        offset: Option<Sexp>,
+    ) -> savvy::Result<()> {
+        let sr: i32 = sr.to_scalar()?;
+        let sr = try_from_i32_to_u32(sr)?;
+        let mut options = HMixOptions::default();
+        if let Some(gain) = gain {
+            options.gain = gain.to_scalar()?;
+        }
+        if let Some(pan) = pan {
+            options.pan = pan.to_scalar()?;
+        }
+        if let Some(offset) = offset {
+            let offset: f64 = offset.to_scalar()?;
+            options.offset = HTime::Seconds(offset);
+        }
+        self.0.add(harray, sr, options)
+    }
+
+    /// HMixer
+    /// ## dtype
+    ///
+    /// `dtype() -> HDataType`
+    ///
+    /// Gets the `HMixer`'s dtype.
+    ///
+    /// #### Returns
+    ///
+    /// An `HDataType`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// hmixer = HMixer$new(2L, 44100L, HDataType$Float32)
+    /// hmixer$dtype()
+    /// ```
+    ///
+    /// _________
+    ///
+    fn dtype(&self) -> savvy::Result<HDataType> {
+        self.0.dtype()
+    }
+
+    /// HMixer
+    /// ## len
+    ///
+    /// `len() -> integer`
+    ///
+    /// Returns the number of sounds in the mix.
+    ///
+    /// #### Returns
+    ///
+    /// An integer.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// hmixer = HMixer$new(2L, 44100L, HDataType$Float32)
+    /// hmixer$len() # 0
+    /// ```
+    ///
+    /// _________
+    ///
+    fn len(&self) -> savvy::Result<Sexp> {
+        let integer_sexp: OwnedIntegerSexp = try_from_usize_to_int_sexp(self.0.len())?;
+        integer_sexp.into()
+    }
+
+    /// HMixer
+    /// ## nframes
+    ///
+    /// `nframes() -> double`
+    ///
+    /// Returns the number of frames of the mix, up to the end of the last sound.
+    ///
+    /// #### Returns
+    ///
+    /// A `double`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// hdecodedaudio = HFile$decode(fpath = "./r-harmonium/testfiles/gs-16b-2c-44100hz.wav", dtype = HDataType$Float32)
+    /// hmixer = HMixer$new(2L, 44100L, HDataType$Float32)
+    /// hmixer$add(hdecodedaudio$harray(), hdecodedaudio$sr(), offset = 1)
+    /// hmixer$nframes() # 742294
+    /// ```
+    ///
+    /// _________
+    ///
+    fn nframes(&self) -> savvy::Result<Sexp> {
+        let real_sexp: OwnedRealSexp = (self.0.nframes() as f64).try_into()?;
+        real_sexp.into()
+    }
+
+    /// HMixer
+    /// ## render
+    ///
+    /// `render() -> HArray`
+    ///
+    /// Renders the whole mix, with shape `(nchannels, nframes)`.
+    ///
+    /// #### Returns
+    ///
+    /// An `HArray`.
+    ///
+    /// #### Examples
+    ///
+    /// ```r
+    /// library(harmonium)
+    /// hdecodedaudio = HFile$decode(fpath = "./r-harmonium/testfiles/gs-16b-2c-44100hz.wav", dtype = HDataType$Float32)
+    /// hmixer = HMixer$new(2L, 44100L, HDataType$Float32)
+    /// hmixer$add(hdecodedaudio$harray(), hdecodedaudio$sr(), pan = 1)
+    /// hmixer$render()
+    /// ```
+    ///
+    /// _________
+    ///
+    fn render(&self) -> savvy::Result<HArray> {
+        self.0.render()
+    }
+}
+
+macro_rules! impl_hmixer {
+    ($(($t1:ty, $t2:ty, $e1:expr)),+) => {
+        $(
+            impl HMixerR for mix::HMixer<$t1> {
+                fn add(&mut self, harray: &HArray, sr: u32, options: HMixOptions) -> savvy::Result<()> {
+                    let harray = harray.0.as_any().downcast_ref::<$t2>().ok_or_else(|| savvy::Error::new("HArray and HMixer must have the same HDataType."))?;
+                    // A 1D HArray is added as a mono sound.
+                    let harray = match harray.ndim() {
+                        1 => harray.0.clone().into_dimensionality::<Ix1>().unwrap().insert_axis(Axis(0)),
+                        2 => harray.0.clone().into_dimensionality::<Ix2>().unwrap(),
+                        _ => return Err("The HArray must have 1 or 2 dimensions.".into()),
+                    };
+                    mix::HMixer::add(self, &harmonium_core::array::HArray(harray), sr, options).map_err(|err| savvy::Error::from(HErrorR::from(err)))
+                }
+
+                fn append_to(&self, haudiosink: &play::HAudioSink) {
+                    haudiosink.append_from_mixer(self);
+                }
+
+                fn dtype(&self) -> savvy::Result<HDataType> {
+                    Ok($e1)
+                }
+
+                fn len(&self) -> usize {
+                    mix::HMixer::len(self)
+                }
+
+                fn nframes(&self) -> u64 {
+                    mix::HMixer::nframes(self)
+                }
+
+                fn render(&self) -> savvy::Result<HArray> {
+                    Ok(HArray(Arc::new(mix::HMixer::render(self).into_dynamic())))
+                }
+            }
+        )+
+    };
+}
+
+impl_hmixer!(
+    (
+        f32,
+        harmonium_core::array::HArray<f32, IxDyn>,
+        HDataType::Float32
+    ),
+    (
+        f64,
+        harmonium_core::array::HArray<f64, IxDyn>,
+        HDataType::Float64
+    )
+);
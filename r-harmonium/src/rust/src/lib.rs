@@ -9,6 +9,7 @@ mod hfft;
 mod hfile;
 mod hinterpolationtype;
 mod hmetadatatype;
+mod hmixer;
 mod hpolynomialdegree;
 mod hresampler;
 mod hresamplertype;